@@ -0,0 +1,94 @@
+use hyveos_core::grpc::{self, admin_server::Admin};
+use hyveos_p2p_stack::Client;
+use libp2p::PeerId;
+use tonic::{Request as TonicRequest, Response as TonicResponse, Status};
+
+use crate::{Telemetry, TonicResult};
+
+pub struct AdminServer {
+    client: Client,
+    is_application_bridge: bool,
+    telemetry: Telemetry,
+}
+
+impl AdminServer {
+    pub fn new(client: Client, is_application_bridge: bool, telemetry: Telemetry) -> Self {
+        Self {
+            client,
+            is_application_bridge,
+            telemetry,
+        }
+    }
+
+    fn ensure_not_application(&self) -> Result<(), Status> {
+        if self.is_application_bridge {
+            Err(Status::permission_denied(
+                "Admin service is not available to applications",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[tonic::async_trait] // TODO: rewrite when https://github.com/hyperium/tonic/pull/1697 is merged
+impl Admin for AdminServer {
+    async fn block_peer(&self, request: TonicRequest<grpc::Peer>) -> TonicResult<grpc::Empty> {
+        self.telemetry.track("admin.block_peer");
+        self.ensure_not_application()?;
+        let request = request.into_inner();
+
+        tracing::debug!(request=?request, "Received block_peer request");
+
+        let peer_id = PeerId::try_from(request)?;
+
+        if peer_id == self.client.peer_id() {
+            return Err(Status::invalid_argument("Can't block the local peer"));
+        }
+
+        self.client
+            .gate()
+            .block_peer(peer_id)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(TonicResponse::new(grpc::Empty {}))
+    }
+
+    async fn unblock_peer(&self, request: TonicRequest<grpc::Peer>) -> TonicResult<grpc::Empty> {
+        self.telemetry.track("admin.unblock_peer");
+        self.ensure_not_application()?;
+        let request = request.into_inner();
+
+        tracing::debug!(request=?request, "Received unblock_peer request");
+
+        let peer_id = PeerId::try_from(request)?;
+
+        self.client
+            .gate()
+            .unblock_peer(peer_id)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(TonicResponse::new(grpc::Empty {}))
+    }
+
+    async fn get_peer_gate(
+        &self,
+        _request: TonicRequest<grpc::Empty>,
+    ) -> TonicResult<grpc::PeerGate> {
+        self.telemetry.track("admin.get_peer_gate");
+        self.ensure_not_application()?;
+
+        tracing::debug!("Received get_peer_gate request");
+
+        let peer_gate = self
+            .client
+            .gate()
+            .get_peer_gate()
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(TonicResponse::new(peer_gate.into()))
+    }
+}
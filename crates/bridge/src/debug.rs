@@ -98,4 +98,27 @@ impl Debug for DebugServer {
 
         Ok(TonicResponse::new(drop_stream))
     }
+
+    async fn get_pending_requests(
+        &self,
+        _request: TonicRequest<grpc::Empty>,
+    ) -> TonicResult<grpc::PendingRequests> {
+        self.telemetry.track("debug.get_pending_requests");
+        tracing::debug!("Received get_pending_requests request");
+
+        let (sender, receiver) = oneshot::channel();
+
+        self.command_sender
+            .send(DebugClientCommand::GetPendingRequests(sender))
+            .await
+            .map_err(|_| Status::internal("Failed to send command"))?;
+
+        let requests = receiver
+            .await
+            .map_err(|_| Status::internal("Failed to receive response"))?;
+
+        Ok(TonicResponse::new(grpc::PendingRequests {
+            requests: requests.into_iter().map(Into::into).collect(),
+        }))
+    }
 }
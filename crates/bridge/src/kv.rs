@@ -1,16 +1,27 @@
+use std::{
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
+
 use futures::{
-    future,
-    stream::{StreamExt as _, TryStreamExt as _},
+    future, pin_mut,
+    stream::{self, StreamExt as _},
 };
 use hyveos_core::{
-    dht::Key as DhtKey,
+    dht::{to_system_time, Key as DhtKey, Quorum as DhtQuorum, Record, RecordValue},
     grpc::{self, kv_server::Kv},
 };
-use hyveos_p2p_stack::Client;
-use libp2p::kad::{GetRecordOk, Quorum, RecordKey};
+use hyveos_p2p_stack::{
+    kad::{verify_record, ValidationError, VerifiedRecord, WatchEvent},
+    Client,
+};
+use libp2p::{
+    kad::{GetRecordError, GetRecordOk, PeerRecord, Quorum, RecordKey},
+    PeerId,
+};
 use tonic::{Request as TonicRequest, Response as TonicResponse, Status};
 
-use crate::{Telemetry, TonicResult};
+use crate::{ServerStream, Telemetry, TonicResult};
 
 pub(crate) fn convert_key(key: grpc::DhtKey) -> Result<RecordKey, Status> {
     DhtKey::from(key)
@@ -19,6 +30,114 @@ pub(crate) fn convert_key(key: grpc::DhtKey) -> Result<RecordKey, Status> {
         .map_err(Into::into)
 }
 
+fn convert_quorum(quorum: Option<grpc::DhtQuorum>) -> Result<Quorum, Status> {
+    let quorum = quorum
+        .map(DhtQuorum::try_from)
+        .transpose()?
+        .unwrap_or_default();
+
+    Ok(match quorum {
+        DhtQuorum::One => Quorum::One,
+        DhtQuorum::Majority => Quorum::Majority,
+        DhtQuorum::All => Quorum::All,
+        DhtQuorum::N(n) => Quorum::N(
+            usize::try_from(n.get())
+                .ok()
+                .and_then(NonZeroUsize::new)
+                .ok_or_else(|| Status::invalid_argument("Quorum is too large"))?,
+        ),
+    })
+}
+
+/// Unwraps owned records, so the value is the value that was put by the owner
+/// and the publisher is the verified owner.
+///
+/// Returns `None` for tombstones of deleted records.
+fn convert_record(record: libp2p::kad::Record) -> Result<Option<Record>, ValidationError> {
+    let expires = record.expires.map(to_system_time);
+    let VerifiedRecord {
+        value,
+        publisher,
+        owned,
+        deleted,
+    } = verify_record(record)?;
+
+    Ok((!deleted).then_some(Record {
+        value,
+        publisher,
+        expires,
+        owned,
+    }))
+}
+
+/// Runs a get record query until the quorum is reached and returns all found records.
+///
+/// Returns an empty list if the record is not found.
+async fn find_records(
+    client: &Client,
+    request: grpc::DhtGetRecord,
+) -> Result<Vec<(Option<PeerId>, Record)>, Status> {
+    let grpc::DhtGetRecord { key, quorum } = request;
+
+    let records = client
+        .kad()
+        .get_record(convert_key(key)?, convert_quorum(quorum)?)
+        .await
+        .map_err(|e| Status::internal(format!("{e:?}")))?;
+
+    pin_mut!(records);
+
+    let mut found = Vec::new();
+    // The latest expiry of all tombstones found, `Some(None)` if a tombstone never expires
+    let mut deleted_until = None;
+    while let Some(res) = records.next().await {
+        match res {
+            Ok(GetRecordOk::FoundRecord(PeerRecord { peer, record })) => {
+                let expires = record.expires.map(to_system_time);
+                match convert_record(record) {
+                    Ok(Some(record)) => found.push((peer, record)),
+                    Ok(None) => {
+                        deleted_until = match (deleted_until, expires) {
+                            (Some(None), _) | (_, None) => Some(None),
+                            (Some(Some(until)), Some(expires)) => Some(Some(expires.max(until))),
+                            (None, Some(expires)) => Some(Some(expires)),
+                        };
+                    }
+                    Err(e) => tracing::warn!(?peer, error = %e, "Ignoring invalid record"),
+                }
+            }
+            Ok(GetRecordOk::FinishedWithNoAdditionalRecord { .. })
+            | Err(GetRecordError::NotFound { .. }) => {}
+            Err(e) => return Err(Status::internal(e.to_string())),
+        }
+    }
+
+    // Stale replicas that didn't receive the tombstone still return the deleted record
+    if let Some(deleted_until) = deleted_until {
+        found.retain(|(_, record)| match (record.expires, deleted_until) {
+            (Some(expires), Some(until)) => expires > until,
+            (None, Some(_)) => true,
+            (_, None) => false,
+        });
+    }
+
+    Ok(found)
+}
+
+/// Decides whether `record` is a change compared to the last value sent to a watcher, if any.
+///
+/// Change notifications for owned records are only accepted from the owner,
+/// just like in the DHT itself. Values read from the DHT during a resync are always accepted.
+fn is_change(last: Option<&Option<Record>>, record: Option<&Record>, resync: bool) -> bool {
+    if let (Some(Some(last)), Some(record)) = (last, record) {
+        let same_owner = !last.owned || (record.owned && record.publisher == last.publisher);
+        let same_value = record.value == last.value && record.owned == last.owned;
+        !same_value && (same_owner || resync)
+    } else {
+        last.map(Option::as_ref) != Some(record)
+    }
+}
+
 pub struct KvServer {
     client: Client,
     telemetry: Telemetry,
@@ -32,6 +151,8 @@ impl KvServer {
 
 #[tonic::async_trait] // TODO: rewrite when https://github.com/hyperium/tonic/pull/1697 is merged
 impl Kv for KvServer {
+    type WatchStream = ServerStream<grpc::OptionalDhtValue>;
+
     async fn put_record(&self, request: TonicRequest<grpc::DhtRecord>) -> TonicResult<grpc::Empty> {
         self.telemetry.track("kv.put_record");
 
@@ -39,11 +160,31 @@ impl Kv for KvServer {
 
         tracing::debug!(request=?record, "Received put_record request");
 
-        let grpc::DhtRecord { key, value } = record;
+        let grpc::DhtRecord {
+            key,
+            value,
+            ttl,
+            quorum,
+            owned,
+        } = record;
+
+        let expires = ttl
+            .map(|ttl| {
+                Instant::now()
+                    .checked_add(Duration::from_secs(ttl))
+                    .ok_or_else(|| Status::invalid_argument("TTL is too large"))
+            })
+            .transpose()?;
 
         self.client
             .kad()
-            .put_record(convert_key(key)?, value.into(), None, Quorum::One)
+            .put_record(
+                convert_key(key)?,
+                value.into(),
+                expires,
+                convert_quorum(quorum)?,
+                owned.unwrap_or_default(),
+            )
             .await
             .map(|_| TonicResponse::new(grpc::Empty {}))
             .map_err(|e| Status::internal(e.to_string()))
@@ -52,49 +193,161 @@ impl Kv for KvServer {
     async fn get_record(
         &self,
         request: TonicRequest<grpc::DhtKey>,
-    ) -> TonicResult<grpc::OptionalData> {
+    ) -> TonicResult<grpc::OptionalDhtValue> {
         self.telemetry.track("kv.get_record");
 
         let key = request.into_inner();
 
         tracing::debug!(request=?key, "Received get_record request");
 
-        let records = self
+        let request = grpc::DhtGetRecord { key, quorum: None };
+        let record = find_records(&self.client, request)
+            .await?
+            .into_iter()
+            .next()
+            .map(|(_, record)| record);
+
+        Ok(TonicResponse::new(record.into()))
+    }
+
+    async fn get_record_with_quorum(
+        &self,
+        request: TonicRequest<grpc::DhtGetRecord>,
+    ) -> TonicResult<grpc::OptionalDhtValue> {
+        self.telemetry.track("kv.get_record_with_quorum");
+
+        let request = request.into_inner();
+
+        tracing::debug!(?request, "Received get_record_with_quorum request");
+
+        let record = find_records(&self.client, request)
+            .await?
+            .into_iter()
+            .next()
+            .map(|(_, record)| record);
+
+        Ok(TonicResponse::new(record.into()))
+    }
+
+    async fn get_record_values(
+        &self,
+        request: TonicRequest<grpc::DhtGetRecord>,
+    ) -> TonicResult<grpc::DhtRecordValues> {
+        self.telemetry.track("kv.get_record_values");
+
+        let request = request.into_inner();
+
+        tracing::debug!(?request, "Received get_record_values request");
+
+        let mut values: Vec<RecordValue> = Vec::new();
+
+        for (peer, record) in find_records(&self.client, request).await? {
+            let index = values
+                .iter()
+                .position(|value| value.value == record.value && value.owned == record.owned)
+                .unwrap_or_else(|| {
+                    values.push(RecordValue {
+                        value: record.value,
+                        publisher: record.publisher,
+                        peers: Vec::new(),
+                        local: false,
+                        owned: record.owned,
+                    });
+                    values.len() - 1
+                });
+
+            match peer {
+                Some(peer) => values[index].peers.push(peer),
+                None => values[index].local = true,
+            }
+        }
+
+        Ok(TonicResponse::new(grpc::DhtRecordValues {
+            values: values.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn watch(&self, request: TonicRequest<grpc::DhtKey>) -> TonicResult<Self::WatchStream> {
+        self.telemetry.track("kv.watch");
+
+        let key = request.into_inner();
+
+        tracing::debug!(request=?key, "Received watch request");
+
+        let changes = self
             .client
             .kad()
-            .get_record(convert_key(key)?)
-            .await
-            .map_err(|e| Status::internal(format!("{e:?}")))?;
-
-        let value = records
-            .try_filter_map(|get_record| {
-                future::ready(Ok(if let GetRecordOk::FoundRecord(record) = get_record {
-                    Some(record.record.value)
-                } else {
-                    None
-                }))
-            })
-            .next()
+            .watch_record(convert_key(key.clone())?)
             .await
-            .transpose()
             .map_err(|e| Status::internal(e.to_string()))?;
 
-        Ok(TonicResponse::new(value.into()))
+        let client = self.client.clone();
+
+        // The current value is read from the DHT first,
+        // and again whenever changes were missed or the record was deleted.
+        let stream = stream::once(future::ready(WatchEvent::Lagged))
+            .chain(changes)
+            .then(move |event| {
+                let client = client.clone();
+                let key = key.clone();
+                async move {
+                    if let WatchEvent::Changed(record) = event {
+                        match convert_record(record) {
+                            Ok(Some(record)) => return Ok(Some((Some(record), false))),
+                            // The record was deleted, the current value is read from the DHT
+                            Ok(None) => {}
+                            Err(_) => return Ok(None),
+                        }
+                    }
+
+                    let request = grpc::DhtGetRecord { key, quorum: None };
+                    let record = find_records(&client, request)
+                        .await?
+                        .into_iter()
+                        .next()
+                        .map(|(_, record)| record);
+                    Ok(Some((record, true)))
+                }
+            })
+            .scan(None, |last: &mut Option<Option<Record>>, res| {
+                let change = res.map(|update| {
+                    update
+                        .filter(|(record, resync)| {
+                            is_change(last.as_ref(), record.as_ref(), *resync)
+                        })
+                        .map(|(record, _)| {
+                            *last = Some(record.clone());
+                            record
+                        })
+                });
+                future::ready(Some(change.transpose()))
+            })
+            .filter_map(future::ready)
+            .map(|res| res.map(Into::into))
+            .boxed();
+
+        Ok(TonicResponse::new(stream))
     }
 
-    async fn remove_record(&self, request: TonicRequest<grpc::DhtKey>) -> TonicResult<grpc::Empty> {
+    async fn remove_record(
+        &self,
+        request: TonicRequest<grpc::DhtKey>,
+    ) -> TonicResult<grpc::DhtRemoveRecordResult> {
         self.telemetry.track("kv.remove_record");
 
         let key = request.into_inner();
 
         tracing::debug!(request=?key, "Received remove_record request");
 
-        self.client
+        let replicas = self
+            .client
             .kad()
-            .remove_record(convert_key(key)?)
+            .delete_record(convert_key(key)?)
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
 
-        Ok(TonicResponse::new(grpc::Empty {}))
+        Ok(TonicResponse::new(grpc::DhtRemoveRecordResult {
+            replicas: u64::try_from(replicas).unwrap_or(u64::MAX),
+        }))
     }
 }
@@ -24,13 +24,19 @@ use ulid::Ulid;
 
 #[cfg(feature = "batman")]
 use crate::debug::DebugServer;
-pub use crate::{apps::AppsClient, local_kv::DbClient, telemetry::Telemetry};
 use crate::{
-    apps::AppsServer, control::ControlServer, discovery::DiscoveryServer,
+    admin::AdminServer, apps::AppsServer, control::ControlServer, discovery::DiscoveryServer,
     file_transfer::FileTransferServer, kv::KvServer, local_kv::LocalKvServer,
-    neighbours::NeighboursServer, pub_sub::PubSubServer, req_resp::ReqRespServer,
+    location::LocationServer, neighbours::NeighboursServer, pub_sub::PubSubServer,
+    req_resp::ReqRespServer, stream::StreamServer,
+};
+pub use crate::{
+    apps::AppsClient,
+    local_kv::DbClient,
+    telemetry::{BridgeMetrics, Telemetry},
 };
 
+mod admin;
 mod apps;
 mod control;
 #[cfg(feature = "batman")]
@@ -39,9 +45,11 @@ mod discovery;
 mod file_transfer;
 mod kv;
 mod local_kv;
+mod location;
 mod neighbours;
 mod pub_sub;
 mod req_resp;
+mod stream;
 mod telemetry;
 
 pub const CONTAINER_SHARED_DIR: &str = "/hyveos/shared";
@@ -262,18 +270,22 @@ pub struct BridgeClient<Db, Apps> {
 macro_rules! build_tonic {
     (
         $tonic:expr,
+        $admin:ident,
         $apps:ident,
         $control:ident,
         $discovery:ident,
         $kv:ident,
         $local_kv:ident,
+        $location:ident,
         $neighbours:ident,
         $pub_sub:ident,
         $req_resp:ident,
+        $stream:ident,
         $debug:ident,
         $transform:expr
     ) => {{
         let tmp = $tonic
+            .add_service($transform(grpc::admin_server::AdminServer::new($admin)))
             .add_service($transform(grpc::apps_server::AppsServer::new($apps)))
             .add_service($transform(grpc::control_server::ControlServer::new(
                 $control,
@@ -285,6 +297,9 @@ macro_rules! build_tonic {
             .add_service($transform(grpc::local_kv_server::LocalKvServer::new(
                 $local_kv,
             )))
+            .add_service($transform(grpc::location_server::LocationServer::new(
+                $location,
+            )))
             .add_service($transform(grpc::neighbours_server::NeighboursServer::new(
                 $neighbours,
             )))
@@ -293,7 +308,8 @@ macro_rules! build_tonic {
             )))
             .add_service($transform(grpc::req_resp_server::ReqRespServer::new(
                 $req_resp,
-            )));
+            )))
+            .add_service($transform(grpc::stream_server::StreamServer::new($stream)));
 
         #[cfg(feature = "batman")]
         let tmp = tmp.add_service($transform(grpc::debug_server::DebugServer::new($debug)));
@@ -304,6 +320,11 @@ macro_rules! build_tonic {
 
 impl<Db: DbClient, Apps: AppsClient> BridgeClient<Db, Apps> {
     pub async fn run(self) -> Result<(), Error> {
+        let admin = AdminServer::new(
+            self.client.clone(),
+            self.is_application_bridge,
+            self.telemetry.clone().service("admin"),
+        );
         let apps = AppsServer::new(
             self.apps_client,
             self.ulid,
@@ -327,6 +348,10 @@ impl<Db: DbClient, Apps: AppsClient> BridgeClient<Db, Apps> {
         let kv = KvServer::new(self.client.clone(), self.telemetry.clone().service("kv"));
         let local_kv =
             LocalKvServer::new(self.db_client, self.telemetry.clone().service("local_kv"));
+        let location = LocationServer::new(
+            self.client.clone(),
+            self.telemetry.clone().service("location"),
+        );
         let neighbours = NeighboursServer::new(
             self.client.clone(),
             self.telemetry.clone().service("neighbours"),
@@ -335,7 +360,11 @@ impl<Db: DbClient, Apps: AppsClient> BridgeClient<Db, Apps> {
             self.client.clone(),
             self.telemetry.clone().service("pub_sub"),
         );
-        let req_resp = ReqRespServer::new(self.client, self.telemetry.clone().service("req_resp"));
+        let req_resp = ReqRespServer::new(
+            self.client.clone(),
+            self.telemetry.clone().service("req_resp"),
+        );
+        let stream = StreamServer::new(self.client, self.telemetry.clone().service("stream"));
 
         #[cfg(feature = "batman")]
         let debug = DebugServer::new(
@@ -349,14 +378,17 @@ impl<Db: DbClient, Apps: AppsClient> BridgeClient<Db, Apps> {
             Connection::Local(socket) => {
                 let router = build_tonic!(
                     TonicServer::builder(),
+                    admin,
                     apps,
                     control,
                     discovery,
                     kv,
                     local_kv,
+                    location,
                     neighbours,
                     pub_sub,
                     req_resp,
+                    stream,
                     debug,
                     std::convert::identity
                 )
@@ -387,14 +419,17 @@ impl<Db: DbClient, Apps: AppsClient> BridgeClient<Db, Apps> {
 
                 let tonic_routes = build_tonic!(
                     TonicRoutes::from(router),
+                    admin,
                     apps,
                     control,
                     discovery,
                     kv,
                     local_kv,
+                    location,
                     neighbours,
                     pub_sub,
                     req_resp,
+                    stream,
                     debug,
                     tonic_web::enable
                 );
@@ -0,0 +1,149 @@
+#[cfg(feature = "location")]
+use futures::stream::StreamExt as _;
+use hyveos_core::grpc::{self, location_server::Location};
+#[cfg(feature = "location")]
+use hyveos_core::location::{Location as CoreLocation, NearbyQuery};
+use hyveos_p2p_stack::Client;
+#[cfg(feature = "location")]
+use libp2p::PeerId;
+#[cfg(feature = "location")]
+use tonic::Response as TonicResponse;
+use tonic::{Request as TonicRequest, Status};
+
+use crate::{ServerStream, Telemetry, TonicResult};
+
+#[cfg_attr(not(feature = "location"), allow(dead_code))]
+pub struct LocationServer {
+    client: Client,
+    telemetry: Telemetry,
+}
+
+impl LocationServer {
+    pub fn new(client: Client, telemetry: Telemetry) -> Self {
+        Self { client, telemetry }
+    }
+}
+
+#[tonic::async_trait] // TODO: rewrite when https://github.com/hyperium/tonic/pull/1697 is merged
+impl Location for LocationServer {
+    type SubscribeStream = ServerStream<grpc::PeerLocation>;
+
+    #[cfg(feature = "location")]
+    async fn set(&self, request: TonicRequest<grpc::Position>) -> TonicResult<grpc::Empty> {
+        self.telemetry.track("location.set");
+        let request = request.into_inner();
+
+        tracing::debug!(request=?request, "Received set request");
+
+        let location = CoreLocation::try_from(request)?;
+
+        self.client
+            .location()
+            .set_location(location)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(TonicResponse::new(grpc::Empty {}))
+    }
+
+    #[cfg(not(feature = "location"))]
+    async fn set(&self, _request: TonicRequest<grpc::Position>) -> TonicResult<grpc::Empty> {
+        return Err(Status::unavailable("location feature is not enabled"));
+    }
+
+    #[cfg(feature = "location")]
+    async fn get(
+        &self,
+        request: TonicRequest<grpc::LocationQuery>,
+    ) -> TonicResult<grpc::OptionalLocation> {
+        self.telemetry.track("location.get");
+        let request = request.into_inner();
+
+        tracing::debug!(request=?request, "Received get request");
+
+        let peer = request
+            .peer
+            .map(PeerId::try_from)
+            .transpose()?
+            .unwrap_or_else(|| self.client.peer_id());
+
+        let location = self
+            .client
+            .location()
+            .get_location(peer)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(TonicResponse::new(location.into()))
+    }
+
+    #[cfg(not(feature = "location"))]
+    async fn get(
+        &self,
+        _request: TonicRequest<grpc::LocationQuery>,
+    ) -> TonicResult<grpc::OptionalLocation> {
+        return Err(Status::unavailable("location feature is not enabled"));
+    }
+
+    #[cfg(feature = "location")]
+    async fn subscribe(
+        &self,
+        request: TonicRequest<grpc::LocationSubscription>,
+    ) -> TonicResult<Self::SubscribeStream> {
+        self.telemetry.track("location.subscribe");
+        let request = request.into_inner();
+
+        tracing::debug!(request=?request, "Received subscribe request");
+
+        let peer = request.peer.map(PeerId::try_from).transpose()?;
+
+        let stream = self
+            .client
+            .location()
+            .subscribe(peer)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map(|location| Ok(location.into()))
+            .boxed();
+
+        Ok(TonicResponse::new(stream))
+    }
+
+    #[cfg(not(feature = "location"))]
+    async fn subscribe(
+        &self,
+        _request: TonicRequest<grpc::LocationSubscription>,
+    ) -> TonicResult<Self::SubscribeStream> {
+        return Err(Status::unavailable("location feature is not enabled"));
+    }
+
+    #[cfg(feature = "location")]
+    async fn get_nearby_peers(
+        &self,
+        request: TonicRequest<grpc::NearbyPeersQuery>,
+    ) -> TonicResult<grpc::NearbyPeers> {
+        self.telemetry.track("location.get_nearby_peers");
+        let request = request.into_inner();
+
+        tracing::debug!(request=?request, "Received get_nearby_peers request");
+
+        let query = NearbyQuery::try_from(request)?;
+
+        let peers = self
+            .client
+            .location()
+            .get_nearby_peers(query)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(TonicResponse::new(peers.into_iter().collect()))
+    }
+
+    #[cfg(not(feature = "location"))]
+    async fn get_nearby_peers(
+        &self,
+        _request: TonicRequest<grpc::NearbyPeersQuery>,
+    ) -> TonicResult<grpc::NearbyPeers> {
+        return Err(Status::unavailable("location feature is not enabled"));
+    }
+}
@@ -1,12 +1,152 @@
-use futures::stream::{StreamExt as _, TryStreamExt as _};
-use hyveos_core::grpc::{self, pub_sub_server::PubSub};
-use hyveos_p2p_stack::Client;
-use libp2p::gossipsub::IdentTopic;
-use tokio_stream::wrappers::BroadcastStream;
+use futures::{
+    pin_mut,
+    stream::{self, StreamExt as _, TryStreamExt as _},
+};
+use hyveos_core::{
+    grpc::{self, pub_sub_server::PubSub},
+    pub_sub::{Message, MessageId, ReceivedMessage, ReliableEvent, ValidationResult},
+    req_resp::TopicQuery,
+};
+use hyveos_p2p_stack::{kad::verify_record, Client, RequestError};
+use libp2p::{
+    gossipsub::{IdentTopic, PublishError},
+    kad::{GetRecordOk, PeerRecord, Quorum, RecordKey},
+};
+use prost::Message as _;
+use tokio::sync::broadcast::{self, error::TryRecvError};
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream, ReceiverStream};
 use tonic::{Request as TonicRequest, Response as TonicResponse, Status};
+use ulid::Ulid;
 
 use crate::{ServerStream, Telemetry, TonicResult};
 
+/// The prefix of all topics used by applications, to separate them from internal topics.
+const TOPIC_PREFIX: &str = "app/";
+
+fn app_topic(topic: &str) -> IdentTopic {
+    IdentTopic::new(format!("{TOPIC_PREFIX}{topic}"))
+}
+
+/// Converts a message received through a topic query, reporting the topic without the prefix,
+/// so it can be matched against the query by the application.
+fn query_message(mut message: ReceivedMessage) -> grpc::PubSubRecvMessage {
+    if let Some(topic) = message.message.topic.strip_prefix(TOPIC_PREFIX) {
+        message.message.topic = topic.to_string();
+    }
+    message.into()
+}
+
+/// The prefix of reliable topics used by applications,
+/// to separate them from regular topics with the same name.
+const RELIABLE_TOPIC_PREFIX: &str = "app-reliable/";
+
+fn app_reliable_topic(topic: &str) -> IdentTopic {
+    IdentTopic::new(format!("{RELIABLE_TOPIC_PREFIX}{topic}"))
+}
+
+/// Converts an event of a reliable topic for applications, reporting the topic without the prefix.
+fn app_reliable_event(mut event: ReliableEvent) -> grpc::PubSubReliableEvent {
+    if let ReliableEvent::Message(message) = &mut event {
+        if let Some(topic) = message.message.topic.strip_prefix(RELIABLE_TOPIC_PREFIX) {
+            message.message.topic = topic.to_string();
+        }
+    }
+    event.into()
+}
+
+/// The DHT key under which the retained message of a topic is stored.
+fn retained_key(topic: &IdentTopic) -> RecordKey {
+    RecordKey::new(&format!("pub_sub/retained/{topic}"))
+}
+
+/// Stores a published message as the retained message of its topic.
+async fn retain_message(
+    client: &Client,
+    topic: &IdentTopic,
+    message_id: MessageId,
+    data: Vec<u8>,
+) -> Result<(), Status> {
+    let message = ReceivedMessage {
+        propagation_source: client.peer_id(),
+        source: Some(client.peer_id()),
+        message_id,
+        message: Message {
+            data,
+            topic: topic.to_string(),
+        },
+    };
+
+    let value = grpc::PubSubRecvMessage::from(message).encode_to_vec();
+
+    client
+        .kad()
+        .put_record(retained_key(topic), value, None, Quorum::One, false)
+        .await
+        .map(|_| ())
+        .map_err(|e| Status::internal(e.to_string()))
+}
+
+/// Looks up the retained message of a topic in the DHT.
+///
+/// Returns `None` if the topic has no retained message or the lookup fails,
+/// so a subscription never fails because of it.
+async fn find_retained_message(client: &Client, topic: &IdentTopic) -> Option<ReceivedMessage> {
+    let records = match client
+        .kad()
+        .get_record(retained_key(topic), Quorum::One)
+        .await
+    {
+        Ok(records) => records,
+        Err(e) => {
+            tracing::warn!(error = ?e, %topic, "Failed to look up retained message");
+            return None;
+        }
+    };
+
+    pin_mut!(records);
+
+    while let Some(res) = records.next().await {
+        let Ok(GetRecordOk::FoundRecord(PeerRecord { peer, record })) = res else {
+            continue;
+        };
+
+        let value = match verify_record(record) {
+            Ok(record) if record.deleted => continue,
+            Ok(record) => record.value,
+            Err(e) => {
+                tracing::warn!(?peer, error = %e, "Ignoring invalid retained message");
+                continue;
+            }
+        };
+
+        match grpc::PubSubRecvMessage::decode(value.as_slice())
+            .map_err(|e| e.to_string())
+            .and_then(|message| ReceivedMessage::try_from(message).map_err(|e| e.to_string()))
+        {
+            Ok(message) => return Some(message),
+            Err(e) => tracing::warn!(?peer, error = %e, "Ignoring malformed retained message"),
+        }
+    }
+
+    None
+}
+
+/// Takes the messages that are already waiting in the receiver.
+fn drain_received(
+    receiver: &mut broadcast::Receiver<ReceivedMessage>,
+) -> Vec<Result<ReceivedMessage, BroadcastStreamRecvError>> {
+    let mut messages = Vec::new();
+    loop {
+        match receiver.try_recv() {
+            Ok(message) => messages.push(Ok(message)),
+            Err(TryRecvError::Lagged(count)) => {
+                messages.push(Err(BroadcastStreamRecvError::Lagged(count)));
+            }
+            Err(TryRecvError::Empty | TryRecvError::Closed) => break messages,
+        }
+    }
+}
+
 pub struct PubSubServer {
     client: Client,
     telemetry: Telemetry,
@@ -21,6 +161,9 @@ impl PubSubServer {
 #[tonic::async_trait] // TODO: rewrite when https://github.com/hyperium/tonic/pull/1697 is merged
 impl PubSub for PubSubServer {
     type SubscribeStream = ServerStream<grpc::PubSubRecvMessage>;
+    type SubscribeQueryStream = ServerStream<grpc::PubSubRecvMessage>;
+    type SubscribeReliableStream = ServerStream<grpc::PubSubReliableEvent>;
+    type ValidateStream = ServerStream<grpc::PubSubRecvMessage>;
 
     async fn subscribe(
         &self,
@@ -31,18 +174,63 @@ impl PubSub for PubSubServer {
 
         tracing::debug!(?request, "Received subscribe request");
 
-        let topic = request.topic;
+        let topic = app_topic(&request.topic);
 
-        let receiver = self
+        let mut receiver = self
             .client
             .gossipsub()
-            .get_topic(IdentTopic::new(format!("app/{topic}")))
+            .get_topic(topic.clone())
             .subscribe()
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
 
+        // The lookup only starts once the subscription exists, so no message published in
+        // between is missed. It runs while the stream is consumed, so it doesn't delay the
+        // response. Messages received meanwhile are held back until the lookup finished, so the
+        // retained message is always delivered first.
+        let client = self.client.clone();
+        let stream = stream::once(async move {
+            let retained = find_retained_message(&client, &topic).await;
+            let buffered = drain_received(&mut receiver);
+
+            // A message from the same publisher that was received meanwhile supersedes it
+            let retained = retained.filter(|retained| {
+                !buffered.iter().any(
+                    |message| matches!(message, Ok(message) if message.source == retained.source),
+                )
+            });
+
+            stream::iter(retained.map(Ok).into_iter().chain(buffered))
+                .chain(BroadcastStream::new(receiver))
+        })
+        .flatten()
+        .map_ok(Into::into)
+        .map_err(|e| Status::internal(e.to_string()))
+        .boxed();
+
+        Ok(TonicResponse::new(stream))
+    }
+
+    async fn subscribe_query(
+        &self,
+        request: TonicRequest<grpc::TopicQuery>,
+    ) -> TonicResult<Self::SubscribeQueryStream> {
+        self.telemetry.track("pub_sub.subscribe_query");
+        let request = request.into_inner();
+
+        tracing::debug!(?request, "Received subscribe_query request");
+
+        let query = TopicQuery::try_from(request)?;
+
+        let receiver = self
+            .client
+            .gossipsub()
+            .subscribe_query(TOPIC_PREFIX, query)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
         let stream = BroadcastStream::new(receiver)
-            .map_ok(Into::into)
+            .map_ok(query_message)
             .map_err(|e| Status::internal(e.to_string()))
             .boxed();
 
@@ -59,14 +247,195 @@ impl PubSub for PubSubServer {
         tracing::debug!(?request, "Received publish request");
 
         let grpc::PubSubMessage {
-            data,
+            data: grpc::Data { data },
             topic: grpc::Topic { topic },
+            retain,
         } = request;
 
+        let topic = app_topic(&topic);
+        let retained_data = retain.unwrap_or_default().then(|| data.clone());
+
+        let message_id = match self
+            .client
+            .gossipsub()
+            .get_topic(topic.clone())
+            .publish(data)
+            .await
+        {
+            Ok(message_id) => message_id,
+            // Without subscribed peers the message can't be published yet, but later
+            // subscribers still receive it as the retained message
+            Err(RequestError::Recveived(PublishError::InsufficientPeers))
+                if retained_data.is_some() =>
+            {
+                MessageId(Ulid::new().to_bytes().to_vec())
+            }
+            Err(e) => return Err(Status::internal(e.to_string())),
+        };
+
+        if let Some(data) = retained_data {
+            retain_message(&self.client, &topic, message_id.clone(), data).await?;
+        }
+
+        Ok(TonicResponse::new(message_id.into()))
+    }
+
+    async fn subscribe_reliable(
+        &self,
+        request: TonicRequest<grpc::Topic>,
+    ) -> TonicResult<Self::SubscribeReliableStream> {
+        self.telemetry.track("pub_sub.subscribe_reliable");
+        let request = request.into_inner();
+
+        tracing::debug!(?request, "Received subscribe_reliable request");
+
+        let receiver = self
+            .client
+            .gossipsub()
+            .get_topic(app_reliable_topic(&request.topic))
+            .subscribe_reliable()
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        // Events dropped because the application didn't keep up can't be recovered
+        let stream = BroadcastStream::new(receiver)
+            .map_ok(app_reliable_event)
+            .map_err(|BroadcastStreamRecvError::Lagged(count)| {
+                Status::data_loss(format!(
+                    "Subscriber lagged behind, {count} events were lost"
+                ))
+            })
+            .boxed();
+
+        Ok(TonicResponse::new(stream))
+    }
+
+    async fn publish_reliable(
+        &self,
+        request: TonicRequest<grpc::PubSubMessage>,
+    ) -> TonicResult<grpc::PubSubMessageId> {
+        self.telemetry.track("pub_sub.publish_reliable");
+        let request = request.into_inner();
+
+        tracing::debug!(?request, "Received publish_reliable request");
+
+        let grpc::PubSubMessage {
+            data: grpc::Data { data },
+            topic: grpc::Topic { topic },
+            retain,
+        } = request;
+
+        if retain.unwrap_or_default() {
+            return Err(Status::invalid_argument(
+                "Reliable topics don't support retained messages",
+            ));
+        }
+
+        self.client
+            .gossipsub()
+            .get_topic(app_reliable_topic(&topic))
+            .publish_reliable(data)
+            .await
+            .map(Into::into)
+            .map(TonicResponse::new)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+
+    async fn validate(
+        &self,
+        request: TonicRequest<grpc::Topic>,
+    ) -> TonicResult<Self::ValidateStream> {
+        self.telemetry.track("pub_sub.validate");
+        let request = request.into_inner();
+
+        tracing::debug!(?request, "Received validate request");
+
+        let topic = request.topic;
+
+        let receiver = self
+            .client
+            .gossipsub()
+            .get_topic(app_topic(&topic))
+            .validate()
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let stream = ReceiverStream::new(receiver)
+            .map(Into::into)
+            .map(Ok)
+            .boxed();
+
+        Ok(TonicResponse::new(stream))
+    }
+
+    async fn report_validation_result(
+        &self,
+        request: TonicRequest<grpc::PubSubValidationResult>,
+    ) -> TonicResult<grpc::Empty> {
+        self.telemetry.track("pub_sub.report_validation_result");
+        let request = request.into_inner();
+
+        tracing::debug!(?request, "Received report_validation_result request");
+
+        let ValidationResult {
+            message_id,
+            acceptance,
+        } = request.try_into()?;
+
+        let found = self
+            .client
+            .gossipsub()
+            .report_validation_result(message_id, acceptance)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        if !found {
+            return Err(Status::not_found(
+                "No message with this ID is waiting for validation",
+            ));
+        }
+
+        Ok(TonicResponse::new(grpc::Empty {}))
+    }
+
+    async fn list_topics(&self, _request: TonicRequest<grpc::Empty>) -> TonicResult<grpc::Topics> {
+        self.telemetry.track("pub_sub.list_topics");
+
+        tracing::debug!("Received list_topics request");
+
+        let topics = self
+            .client
+            .gossipsub()
+            .topics()
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .into_iter()
+            .filter_map(|topic_hash| {
+                topic_hash
+                    .as_str()
+                    .strip_prefix(TOPIC_PREFIX)
+                    .map(|topic| grpc::Topic {
+                        topic: topic.to_string(),
+                    })
+            })
+            .collect();
+
+        Ok(TonicResponse::new(grpc::Topics { topics }))
+    }
+
+    async fn topic_peers(
+        &self,
+        request: TonicRequest<grpc::Topic>,
+    ) -> TonicResult<grpc::PubSubTopicPeers> {
+        self.telemetry.track("pub_sub.topic_peers");
+        let request = request.into_inner();
+
+        tracing::debug!(?request, "Received topic_peers request");
+
         self.client
             .gossipsub()
-            .get_topic(IdentTopic::new(format!("app/{topic}")))
-            .publish(data.into())
+            .get_topic(app_topic(&request.topic))
+            .peers()
             .await
             .map(Into::into)
             .map(TonicResponse::new)
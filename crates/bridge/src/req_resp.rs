@@ -1,11 +1,31 @@
+use std::{collections::HashSet, time::Duration};
+
 use drop_stream::DropStream;
-use futures::StreamExt as _;
-use hyveos_core::grpc::{self, req_resp_server::ReqResp};
-use hyveos_p2p_stack::Client;
+use futures::{
+    future,
+    stream::{self, StreamExt as _},
+};
+use hyveos_core::{
+    grpc::{self, req_resp_server::ReqResp},
+    req_resp::Request,
+};
+use hyveos_p2p_stack::{
+    req_resp::{CallError, ResponseStreamError, MAX_REQUEST_TIMEOUT},
+    Client,
+};
+use libp2p::kad::GetProvidersOk;
+use tokio::time::Instant;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request as TonicRequest, Response as TonicResponse, Status};
 
-use crate::{ServerStream, Telemetry, TonicResult};
+use crate::{kv::convert_key, ServerStream, Telemetry, TonicResult};
+
+/// The number of requests to providers that run at the same time.
+const MAX_CONCURRENT_REQUESTS: usize = 32;
+/// The number of providers an anycast call tries by default.
+const DEFAULT_MAX_ATTEMPTS: usize = 3;
+/// How long the providers of an anycast call are cached by default.
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
 
 pub struct ReqRespServer {
     client: Client,
@@ -21,6 +41,8 @@ impl ReqRespServer {
 #[tonic::async_trait] // TODO: rewrite when https://github.com/hyperium/tonic/pull/1697 is merged
 impl ReqResp for ReqRespServer {
     type RecvStream = ServerStream<grpc::RecvRequest>;
+    type SendStreamingStream = ServerStream<grpc::Response>;
+    type SendToProvidersStream = ServerStream<grpc::PeerResponse>;
 
     async fn send(&self, request: TonicRequest<grpc::SendRequest>) -> TonicResult<grpc::Response> {
         self.telemetry.track("req_resp.send");
@@ -28,11 +50,20 @@ impl ReqResp for ReqRespServer {
 
         tracing::debug!(?request, "Received send request");
 
-        let grpc::SendRequest { peer, msg } = request;
+        let grpc::SendRequest {
+            peer,
+            msg,
+            timeout_ms,
+        } = request;
 
+        // Dropping this future when the call is cancelled also cancels the request
         self.client
             .req_resp()
-            .send_request(peer.try_into()?, msg.into())
+            .send_request(
+                peer.try_into()?,
+                msg.into(),
+                timeout_ms.map(Duration::from_millis),
+            )
             .await
             .map(|res| TonicResponse::new(res.into()))
             .map_err(|e| Status::internal(format!("{e:?}")))
@@ -48,12 +79,13 @@ impl ReqResp for ReqRespServer {
         tracing::debug!(?request, "Received recv request");
 
         let query = request.query.map(TryInto::try_into).transpose()?;
+        let response_timeout = request.response_timeout_ms.map(Duration::from_millis);
 
         let client = self.client.clone();
 
         let (id, receiver) = client
             .req_resp()
-            .subscribe(query)
+            .subscribe(query, response_timeout)
             .await
             .map_err(|e| Status::internal(format!("{e:?}")))?;
 
@@ -83,4 +115,202 @@ impl ReqResp for ReqRespServer {
 
         Ok(TonicResponse::new(grpc::Empty {}))
     }
+
+    async fn send_streaming(
+        &self,
+        request: TonicRequest<grpc::SendRequest>,
+    ) -> TonicResult<Self::SendStreamingStream> {
+        self.telemetry.track("req_resp.send_streaming");
+        let request = request.into_inner();
+
+        tracing::debug!(?request, "Received send streaming request");
+
+        let grpc::SendRequest {
+            peer,
+            msg,
+            timeout_ms,
+        } = request;
+
+        // Dropping the stream when the call is cancelled also cancels the request
+        let stream = self
+            .client
+            .req_resp()
+            .send_streaming_request(
+                peer.try_into()?,
+                msg.into(),
+                timeout_ms.map(Duration::from_millis),
+            )
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map(Into::into)
+            .map(Ok)
+            .boxed();
+
+        Ok(TonicResponse::new(stream))
+    }
+
+    async fn respond_chunk(
+        &self,
+        request: TonicRequest<grpc::SendResponse>,
+    ) -> TonicResult<grpc::Empty> {
+        self.telemetry.track("req_resp.respond_chunk");
+        let response = request.into_inner();
+
+        tracing::debug!(request=?response, "Received respond chunk request");
+
+        self.client
+            .req_resp()
+            .send_response_chunk(response.seq, response.response.try_into()?)
+            .await
+            .map_err(|e| response_stream_status(&e))?;
+
+        Ok(TonicResponse::new(grpc::Empty {}))
+    }
+
+    async fn end_response(
+        &self,
+        request: TonicRequest<grpc::ResponseEnd>,
+    ) -> TonicResult<grpc::Empty> {
+        self.telemetry.track("req_resp.end_response");
+        let request = request.into_inner();
+
+        tracing::debug!(?request, "Received end response request");
+
+        self.client
+            .req_resp()
+            .end_response(request.seq)
+            .await
+            .map_err(|e| response_stream_status(&e))?;
+
+        Ok(TonicResponse::new(grpc::Empty {}))
+    }
+
+    async fn send_to_providers(
+        &self,
+        request: TonicRequest<grpc::SendProvidersRequest>,
+    ) -> TonicResult<Self::SendToProvidersStream> {
+        self.telemetry.track("req_resp.send_to_providers");
+        let request = request.into_inner();
+
+        tracing::debug!(?request, "Received send to providers request");
+
+        let grpc::SendProvidersRequest {
+            key,
+            msg,
+            timeout_ms,
+            max_peers,
+        } = request;
+
+        let timeout = timeout_ms
+            .map_or(MAX_REQUEST_TIMEOUT, Duration::from_millis)
+            .min(MAX_REQUEST_TIMEOUT);
+        let deadline = Instant::now() + timeout;
+        let max_peers = max_peers.map_or(usize::MAX, |max_peers| {
+            max_peers.try_into().unwrap_or(usize::MAX)
+        });
+        let req = Request::from(msg);
+
+        let providers = self
+            .client
+            .kad()
+            .get_providers(convert_key(key)?)
+            .await
+            .map_err(|e| Status::internal(format!("{e:?}")))?
+            .filter_map(|providers| {
+                future::ready(match providers {
+                    Ok(GetProvidersOk::FoundProviders { providers, .. }) => {
+                        Some(stream::iter(providers))
+                    }
+                    Ok(_) => None,
+                    Err(e) => {
+                        tracing::debug!(error = ?e, "Provider lookup failed");
+                        None
+                    }
+                })
+            })
+            .flatten();
+
+        // Providers are reported more than once when several peers know about them
+        let mut seen = HashSet::new();
+        let client = self.client.clone();
+
+        let stream = providers
+            .filter(move |peer| future::ready(seen.insert(*peer)))
+            .take(max_peers)
+            .take_until(tokio::time::sleep_until(deadline))
+            .map(move |peer| {
+                let client = client.clone();
+                let req = req.clone();
+                async move {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    let response = client
+                        .req_resp()
+                        .send_request(peer, req, Some(timeout))
+                        .await
+                        .map_err(|e| Status::internal(format!("{e:?}")))?;
+
+                    Ok(grpc::PeerResponse {
+                        peer: peer.into(),
+                        response: response.into(),
+                    })
+                }
+            })
+            .buffer_unordered(MAX_CONCURRENT_REQUESTS)
+            .boxed();
+
+        Ok(TonicResponse::new(stream))
+    }
+
+    async fn call(
+        &self,
+        request: TonicRequest<grpc::CallRequest>,
+    ) -> TonicResult<grpc::PeerResponse> {
+        self.telemetry.track("req_resp.call");
+        let request = request.into_inner();
+
+        tracing::debug!(?request, "Received call request");
+
+        let grpc::CallRequest {
+            key,
+            msg,
+            timeout_ms,
+            max_attempts,
+            refresh_interval_ms,
+        } = request;
+
+        let max_attempts = max_attempts.map_or(DEFAULT_MAX_ATTEMPTS, |max_attempts| {
+            max_attempts.try_into().unwrap_or(usize::MAX)
+        });
+        let refresh_interval =
+            refresh_interval_ms.map_or(DEFAULT_REFRESH_INTERVAL, Duration::from_millis);
+
+        let (peer_id, response) = self
+            .client
+            .req_resp()
+            .call_provider(
+                convert_key(key)?,
+                msg.into(),
+                timeout_ms.map(Duration::from_millis),
+                max_attempts,
+                refresh_interval,
+            )
+            .await
+            .map_err(|e| match e {
+                CallError::NoProviders => Status::not_found(e.to_string()),
+                CallError::Request(_) => Status::internal(e.to_string()),
+            })?;
+
+        Ok(TonicResponse::new(grpc::PeerResponse {
+            peer: peer_id.into(),
+            response: response.into(),
+        }))
+    }
+}
+
+fn response_stream_status(e: &ResponseStreamError) -> Status {
+    match e {
+        ResponseStreamError::NotFound(_) => Status::not_found(e.to_string()),
+        ResponseStreamError::Closed => Status::cancelled(e.to_string()),
+        ResponseStreamError::Request(_) => Status::internal(e.to_string()),
+    }
 }
@@ -0,0 +1,200 @@
+use drop_stream::DropStream;
+use futures::{
+    future,
+    io::{AsyncReadExt as _, AsyncWriteExt as _},
+    stream::{self, BoxStream, StreamExt as _},
+};
+use hyveos_core::grpc::{
+    self, accept_stream_frame, accepted_stream_frame, forward_stream_frame, open_stream_frame,
+    stream_server::Stream,
+};
+use hyveos_p2p_stack::{streams::PortForwardError, Client};
+use tonic::{Request as TonicRequest, Response as TonicResponse, Status, Streaming};
+
+use crate::{ServerStream, Telemetry, TonicResult};
+
+/// The maximum number of bytes read from a stream at once.
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+pub struct StreamServer {
+    client: Client,
+    telemetry: Telemetry,
+}
+
+impl StreamServer {
+    pub fn new(client: Client, telemetry: Telemetry) -> Self {
+        Self { client, telemetry }
+    }
+}
+
+#[tonic::async_trait] // TODO: rewrite when https://github.com/hyperium/tonic/pull/1697 is merged
+impl Stream for StreamServer {
+    type OpenStream = ServerStream<grpc::Data>;
+    type AcceptStream = ServerStream<grpc::AcceptedStreamFrame>;
+    type ForwardStream = ServerStream<grpc::Data>;
+
+    async fn open(
+        &self,
+        request: TonicRequest<Streaming<grpc::OpenStreamFrame>>,
+    ) -> TonicResult<Self::OpenStream> {
+        self.telemetry.track("stream.open");
+        let mut frames = request.into_inner();
+
+        let Some(open_stream_frame::Frame::Open(request)) =
+            frames.message().await?.and_then(|frame| frame.frame)
+        else {
+            return Err(Status::invalid_argument(
+                "The first frame has to open the stream",
+            ));
+        };
+
+        tracing::debug!(?request, "Received open request");
+
+        let grpc::OpenStreamRequest { peer, protocol } = request;
+
+        let stream = self
+            .client
+            .streams()
+            .open_app_stream(peer.try_into()?, &protocol)
+            .await
+            .map_err(|e| Status::internal(format!("{e:?}")))?;
+
+        let data = frames
+            .map(|frame| match frame?.frame {
+                Some(open_stream_frame::Frame::Data(data)) => Ok(data.data),
+                _ => Err(Status::invalid_argument(
+                    "Only the first frame can open the stream",
+                )),
+            })
+            .boxed();
+
+        let stream = pipe(stream, data).map(|data| data.map(|data| grpc::Data { data }));
+
+        Ok(TonicResponse::new(stream.boxed()))
+    }
+
+    async fn accept(
+        &self,
+        request: TonicRequest<Streaming<grpc::AcceptStreamFrame>>,
+    ) -> TonicResult<Self::AcceptStream> {
+        self.telemetry.track("stream.accept");
+        let mut frames = request.into_inner();
+
+        let Some(accept_stream_frame::Frame::Protocol(protocol)) =
+            frames.message().await?.and_then(|frame| frame.frame)
+        else {
+            return Err(Status::invalid_argument(
+                "The first frame has to name the protocol",
+            ));
+        };
+
+        tracing::debug!(%protocol, "Received accept request");
+
+        let (peer_id, stream) = self
+            .client
+            .streams()
+            .accept_app_stream(&protocol)
+            .await
+            .map_err(|e| Status::internal(format!("{e:?}")))?;
+
+        let data = frames
+            .map(|frame| match frame?.frame {
+                Some(accept_stream_frame::Frame::Data(data)) => Ok(data.data),
+                _ => Err(Status::invalid_argument(
+                    "Only the first frame can name the protocol",
+                )),
+            })
+            .boxed();
+
+        let peer = accepted_stream_frame::Frame::Peer(peer_id.into());
+        let stream = stream::once(future::ready(Ok(peer)))
+            .chain(pipe(stream, data).map(|data| {
+                data.map(|data| accepted_stream_frame::Frame::Data(grpc::Data { data }))
+            }))
+            .map(|frame| frame.map(|frame| grpc::AcceptedStreamFrame { frame: Some(frame) }));
+
+        Ok(TonicResponse::new(stream.boxed()))
+    }
+
+    async fn forward(
+        &self,
+        request: TonicRequest<Streaming<grpc::ForwardStreamFrame>>,
+    ) -> TonicResult<Self::ForwardStream> {
+        self.telemetry.track("stream.forward");
+        let mut frames = request.into_inner();
+
+        let Some(forward_stream_frame::Frame::Forward(request)) =
+            frames.message().await?.and_then(|frame| frame.frame)
+        else {
+            return Err(Status::invalid_argument(
+                "The first frame has to name the peer and port",
+            ));
+        };
+
+        tracing::debug!(?request, "Received forward request");
+
+        let grpc::ForwardRequest { peer, port } = request;
+        let port = u16::try_from(port).map_err(|_| Status::invalid_argument("Invalid port"))?;
+
+        let stream = self
+            .client
+            .streams()
+            .open_port_forward(peer.try_into()?, port)
+            .await
+            .map_err(|e| match e {
+                PortForwardError::Denied(_) => Status::permission_denied(e.to_string()),
+                PortForwardError::Unreachable(_) => Status::unavailable(e.to_string()),
+                e => Status::internal(format!("{e:?}")),
+            })?;
+
+        let data = frames
+            .map(|frame| match frame?.frame {
+                Some(forward_stream_frame::Frame::Data(data)) => Ok(data.data),
+                _ => Err(Status::invalid_argument(
+                    "Only the first frame can name the peer and port",
+                )),
+            })
+            .boxed();
+
+        let stream = pipe(stream, data).map(|data| data.map(|data| grpc::Data { data }));
+
+        Ok(TonicResponse::new(stream.boxed()))
+    }
+}
+
+/// Writes the data sent by the app to the stream and returns the data read from the stream.
+///
+/// The stream is closed for writing once the app stops sending data. When the returned stream
+/// ends or is dropped, the whole stream is closed.
+fn pipe(
+    stream: libp2p::Stream,
+    mut data: BoxStream<'static, tonic::Result<Vec<u8>>>,
+) -> ServerStream<Vec<u8>> {
+    let (reader, mut writer) = stream.split();
+
+    let write_task = tokio::spawn(async move {
+        while let Some(Ok(data)) = data.next().await {
+            if let Err(e) = writer.write_all(&data).await {
+                tracing::debug!(error = ?e, "Failed to write to stream");
+                return;
+            }
+        }
+
+        let _ = writer.close().await;
+    });
+
+    let chunks = stream::unfold(Some(reader), |reader| async move {
+        let mut reader = reader?;
+        let mut buf = vec![0; READ_BUFFER_SIZE];
+        match reader.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(len) => {
+                buf.truncate(len);
+                Some((Ok(buf), Some(reader)))
+            }
+            Err(e) => Some((Err(Status::internal(e.to_string())), None)),
+        }
+    });
+
+    DropStream::new(chunks, move || write_task.abort()).boxed()
+}
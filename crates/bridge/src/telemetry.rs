@@ -1,6 +1,11 @@
 use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use bon::Builder;
+use prometheus_client::{
+    encoding::EncodeLabelSet,
+    metrics::{counter::Counter, family::Family},
+    registry::Registry,
+};
 use serde::Serialize;
 use tokio::{sync::mpsc, time::interval};
 
@@ -106,6 +111,32 @@ impl Client {
     }
 }
 
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct CallLabels {
+    call: String,
+    context: String,
+}
+
+/// Prometheus counters of the calls to the bridge services.
+///
+/// Unlike the telemetry events, these are always recorded, since they never leave the node.
+#[derive(Clone, Default)]
+pub struct BridgeMetrics {
+    calls: Family<CallLabels, Counter>,
+}
+
+impl BridgeMetrics {
+    pub fn new(registry: &mut Registry) -> Self {
+        let metrics = Self::default();
+        registry.sub_registry_with_prefix("bridge").register(
+            "calls",
+            "Number of calls to the bridge services",
+            metrics.calls.clone(),
+        );
+        metrics
+    }
+}
+
 #[derive(Clone)]
 pub struct Telemetry {
     context: Vec<Arc<str>>,
@@ -113,6 +144,7 @@ pub struct Telemetry {
     service: Option<Arc<str>>,
     sender: mpsc::Sender<Event>,
     opt_out: bool,
+    metrics: Option<BridgeMetrics>,
 }
 
 impl Default for Telemetry {
@@ -163,6 +195,7 @@ impl Default for Telemetry {
             service: None,
             sender,
             opt_out: false,
+            metrics: None,
         }
     }
 }
@@ -190,7 +223,23 @@ impl Telemetry {
         self
     }
 
+    #[must_use]
+    pub fn metrics(mut self, metrics: BridgeMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
     pub fn track(&self, event: &str) {
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics
+                .calls
+                .get_or_create(&CallLabels {
+                    call: event.to_string(),
+                    context: self.context.join("/"),
+                })
+                .inc();
+        }
+
         if self.opt_out {
             return;
         }
@@ -1,6 +1,7 @@
-#[cfg(feature = "network")]
-use std::net::SocketAddr;
-use std::path::{Path, PathBuf};
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
 
 use hyveos_core::DAEMON_NAME;
 use serde::{Deserialize, Serialize};
@@ -23,11 +24,26 @@ pub struct Config {
     #[serde(default)]
     pub key_file: Option<PathBuf>,
     #[serde(default)]
+    pub swarm_key_file: Option<PathBuf>,
+    /// If set, only these peers can connect to the runtime.
+    #[serde(default)]
+    pub allowed_peers: Option<Vec<String>>,
+    /// These peers can't connect to the runtime, until they are unblocked.
+    #[serde(default)]
+    pub denied_peers: Vec<String>,
+    #[serde(default)]
     pub random_directory: bool,
     #[serde(default)]
     pub application_management: Option<ApplicationManagementConfig>,
     #[serde(default)]
     pub application_heartbeat_timeout: Option<u64>,
+    /// Whether other nodes can forward TCP connections to local ports of this node.
+    #[serde(default)]
+    pub port_forwarding: Option<PortForwardingConfig>,
+    /// The ports other nodes can forward TCP connections to.
+    /// Has to be set if port forwarding is allowed.
+    #[serde(default)]
+    pub forwardable_ports: Option<Vec<u16>>,
     #[serde(default)]
     pub log_dir: Option<PathBuf>,
     #[serde(default)]
@@ -39,6 +55,13 @@ pub struct Config {
     pub cli_socket_addr: Option<SocketAddr>,
     #[serde(default = "toml_default_true")]
     pub telemetry: bool,
+    #[serde(default)]
+    pub kad_store: KadStoreConfig,
+    #[serde(default)]
+    pub transport: TransportConfig,
+    /// If set, Prometheus metrics are served at `/metrics` on this address.
+    #[serde(default)]
+    pub metrics_addr: Option<SocketAddr>,
 }
 
 fn toml_default_true() -> bool {
@@ -96,6 +119,71 @@ pub enum ApplicationManagementConfig {
     Deny,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum PortForwardingConfig {
+    Allow,
+    #[default]
+    Deny,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct KadStoreConfig {
+    #[serde(default)]
+    pub backend: KadStoreBackend,
+    #[serde(default)]
+    pub max_records: Option<usize>,
+    #[serde(default)]
+    pub max_value_bytes: Option<usize>,
+    #[serde(default)]
+    pub max_providers_per_key: Option<usize>,
+    #[serde(default)]
+    pub max_provided_keys: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum KadStoreBackend {
+    #[default]
+    Memory,
+    Persistent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TransportConfig {
+    /// The enabled transports, most preferred first.
+    #[serde(default = "default_transport_preference")]
+    pub preference: Vec<TransportProtocol>,
+    /// The port to listen on with all transports.
+    #[serde(default)]
+    pub listen_port: Option<u16>,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            preference: default_transport_preference(),
+            listen_port: None,
+        }
+    }
+}
+
+fn default_transport_preference() -> Vec<TransportProtocol> {
+    vec![TransportProtocol::Quic, TransportProtocol::Tcp]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum TransportProtocol {
+    Quic,
+    Tcp,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
 pub enum LogFilter {
@@ -0,0 +1,45 @@
+use libp2p_identity::PeerId;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    grpc,
+};
+
+/// The peers that are permitted to connect to a runtime.
+///
+/// A peer is permitted if it is not blocked and,
+/// if there is an allowlist, is in the allowlist.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PeerGate {
+    /// If set, only these peers can connect.
+    pub allowed: Option<Vec<PeerId>>,
+    /// These peers can't connect.
+    pub blocked: Vec<PeerId>,
+}
+
+impl From<PeerGate> for grpc::PeerGate {
+    fn from(gate: PeerGate) -> Self {
+        Self {
+            allowed: gate.allowed.map(grpc::Peers::from_iter),
+            blocked: gate.blocked.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<grpc::PeerGate> for PeerGate {
+    type Error = Error;
+
+    fn try_from(gate: grpc::PeerGate) -> Result<Self> {
+        Ok(Self {
+            allowed: gate.allowed.map(TryInto::try_into).transpose()?,
+            blocked: gate
+                .blocked
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_>>()?,
+        })
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use libp2p_identity::PeerId;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -151,3 +153,53 @@ impl TryFrom<grpc::MessageDebugEvent> for MessageDebugEvent {
         })
     }
 }
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PendingRequest {
+    pub id: u64,
+    pub peer_id: PeerId,
+    pub topic: Option<String>,
+    pub streaming: bool,
+    pub age: Duration,
+    /// The time until the request is answered with an error, if it has a deadline.
+    pub remaining: Option<Duration>,
+    /// The number of subscriptions that received the request and are still active.
+    pub subscribers: u64,
+}
+
+fn duration_to_millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+impl From<PendingRequest> for grpc::PendingRequest {
+    fn from(request: PendingRequest) -> Self {
+        Self {
+            seq: request.id,
+            peer: request.peer_id.into(),
+            topic: grpc::OptionalTopic {
+                topic: request.topic.map(|topic| grpc::Topic { topic }),
+            },
+            streaming: request.streaming,
+            age_ms: duration_to_millis(request.age),
+            remaining_ms: request.remaining.map(duration_to_millis),
+            subscribers: request.subscribers,
+        }
+    }
+}
+
+impl TryFrom<grpc::PendingRequest> for PendingRequest {
+    type Error = Error;
+
+    fn try_from(request: grpc::PendingRequest) -> Result<Self> {
+        Ok(Self {
+            id: request.seq,
+            peer_id: request.peer.try_into()?,
+            topic: request.topic.topic.map(|topic| topic.topic),
+            streaming: request.streaming,
+            age: Duration::from_millis(request.age_ms),
+            remaining: request.remaining_ms.map(Duration::from_millis),
+            subscribers: request.subscribers,
+        })
+    }
+}
@@ -1,3 +1,11 @@
+use std::{
+    fmt,
+    num::NonZeroU32,
+    str::FromStr,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use libp2p_identity::PeerId;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
@@ -101,3 +109,230 @@ impl From<grpc::DhtKey> for Key {
         }
     }
 }
+
+/// A record retrieved from the DHT, together with its metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Record {
+    pub value: Vec<u8>,
+    /// The peer that originally published the record, if known.
+    pub publisher: Option<PeerId>,
+    /// The time at which the record expires, if it expires.
+    pub expires: Option<SystemTime>,
+    /// Whether the record is owned, in which case `publisher` is the verified owner.
+    pub owned: bool,
+}
+
+impl From<Option<Record>> for grpc::OptionalDhtValue {
+    fn from(record: Option<Record>) -> Self {
+        let Some(Record {
+            value,
+            publisher,
+            expires,
+            owned,
+        }) = record
+        else {
+            return Self {
+                data: None,
+                publisher: None,
+                expires_at: None,
+                owned: None,
+            };
+        };
+
+        Self {
+            data: Some(value.into()),
+            publisher: publisher.map(Into::into),
+            expires_at: expires.map(|expires| {
+                expires
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+            }),
+            owned: Some(owned),
+        }
+    }
+}
+
+impl TryFrom<grpc::OptionalDhtValue> for Option<Record> {
+    type Error = Error;
+
+    fn try_from(value: grpc::OptionalDhtValue) -> Result<Self> {
+        let grpc::OptionalDhtValue {
+            data,
+            publisher,
+            expires_at,
+            owned,
+        } = value;
+
+        let Some(data) = data else {
+            return Ok(None);
+        };
+
+        Ok(Some(Record {
+            value: data.into(),
+            publisher: publisher.map(TryInto::try_into).transpose()?,
+            expires: expires_at
+                .map(|secs| {
+                    UNIX_EPOCH
+                        .checked_add(Duration::from_secs(secs))
+                        .ok_or(Error::InvalidExpiry(secs))
+                })
+                .transpose()?,
+            owned: owned.unwrap_or_default(),
+        }))
+    }
+}
+
+/// The number of peers that have to take part in a DHT operation for it to succeed.
+///
+/// For puts, this is the number of peers that have to store the record.
+/// For gets, this is the number of peers that have to return the record.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Quorum {
+    #[default]
+    One,
+    Majority,
+    All,
+    N(NonZeroU32),
+}
+
+impl From<Quorum> for grpc::DhtQuorum {
+    fn from(quorum: Quorum) -> Self {
+        let quorum = match quorum {
+            Quorum::One => grpc::dht_quorum::Quorum::One(grpc::Empty {}),
+            Quorum::Majority => grpc::dht_quorum::Quorum::Majority(grpc::Empty {}),
+            Quorum::All => grpc::dht_quorum::Quorum::All(grpc::Empty {}),
+            Quorum::N(n) => grpc::dht_quorum::Quorum::N(n.get()),
+        };
+
+        Self {
+            quorum: Some(quorum),
+        }
+    }
+}
+
+impl TryFrom<grpc::DhtQuorum> for Quorum {
+    type Error = Error;
+
+    fn try_from(quorum: grpc::DhtQuorum) -> Result<Self> {
+        Ok(
+            match quorum
+                .quorum
+                .ok_or(Error::InvalidQuorum("Missing quorum".to_string()))?
+            {
+                grpc::dht_quorum::Quorum::One(_) => Self::One,
+                grpc::dht_quorum::Quorum::Majority(_) => Self::Majority,
+                grpc::dht_quorum::Quorum::All(_) => Self::All,
+                grpc::dht_quorum::Quorum::N(n) => Self::N(
+                    NonZeroU32::new(n)
+                        .ok_or(Error::InvalidQuorum("Should not be zero".to_string()))?,
+                ),
+            },
+        )
+    }
+}
+
+impl fmt::Display for Quorum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::One => write!(f, "one"),
+            Self::Majority => write!(f, "majority"),
+            Self::All => write!(f, "all"),
+            Self::N(n) => write!(f, "{n}"),
+        }
+    }
+}
+
+impl FromStr for Quorum {
+    type Err = Error;
+
+    /// Parses a quorum from `one`, `majority`, `all` or a positive number.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::num::NonZeroU32;
+    ///
+    /// use hyveos_core::dht::Quorum;
+    ///
+    /// assert_eq!("majority".parse::<Quorum>().unwrap(), Quorum::Majority);
+    /// assert_eq!(
+    ///     "3".parse::<Quorum>().unwrap(),
+    ///     Quorum::N(NonZeroU32::new(3).unwrap())
+    /// );
+    /// assert!("0".parse::<Quorum>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "one" => Ok(Self::One),
+            "majority" => Ok(Self::Majority),
+            "all" => Ok(Self::All),
+            s => s
+                .parse()
+                .map(Self::N)
+                .map_err(|_| Error::InvalidQuorum(s.to_string())),
+        }
+    }
+}
+
+/// A distinct value found in the DHT, together with the peers holding it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RecordValue {
+    pub value: Vec<u8>,
+    /// The peer that originally published the value, if known.
+    pub publisher: Option<PeerId>,
+    /// The remote peers that returned this value.
+    pub peers: Vec<PeerId>,
+    /// Whether the local node holds this value.
+    pub local: bool,
+    /// Whether the value is owned, in which case `publisher` is the verified owner.
+    pub owned: bool,
+}
+
+impl From<RecordValue> for grpc::DhtValueHolders {
+    fn from(value: RecordValue) -> Self {
+        Self {
+            data: value.value.into(),
+            publisher: value.publisher.map(Into::into),
+            peers: value.peers.into_iter().map(Into::into).collect(),
+            local: value.local,
+            owned: Some(value.owned),
+        }
+    }
+}
+
+impl TryFrom<grpc::DhtValueHolders> for RecordValue {
+    type Error = Error;
+
+    fn try_from(value: grpc::DhtValueHolders) -> Result<Self> {
+        Ok(Self {
+            value: value.data.into(),
+            publisher: value.publisher.map(TryInto::try_into).transpose()?,
+            peers: value
+                .peers
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_>>()?,
+            local: value.local,
+            owned: value.owned.unwrap_or_default(),
+        })
+    }
+}
+
+/// Converts a monotonic deadline, like the expiry of a record, into wall clock time.
+///
+/// Deadlines that can't be represented as wall clock time are clamped to [`UNIX_EPOCH`].
+#[must_use]
+pub fn to_system_time(instant: Instant) -> SystemTime {
+    let now = Instant::now();
+    let system_now = SystemTime::now();
+
+    if instant >= now {
+        system_now + (instant - now)
+    } else {
+        system_now.checked_sub(now - instant).unwrap_or(UNIX_EPOCH)
+    }
+}
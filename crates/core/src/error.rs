@@ -14,6 +14,8 @@ pub enum Error {
     MissingResponse,
     #[error("Event is missing")]
     MissingEvent,
+    #[error("Message acceptance is missing")]
+    MissingAcceptance,
     #[error("Invalid topic: Cannot contain '/'")]
     InvalidTopic,
     #[error("Invalid file hash: Should be 32 bytes")]
@@ -24,6 +26,12 @@ pub enum Error {
     InvalidKey(String),
     #[error("Invalid cid format")]
     InvalidCidFormat,
+    #[error("Invalid quorum: {0}")]
+    InvalidQuorum(String),
+    #[error("Invalid location: {0}")]
+    InvalidLocation(String),
+    #[error("Invalid expiry time: {0} seconds since the epoch")]
+    InvalidExpiry(u64),
 }
 
 impl From<libp2p_identity::ParseError> for Error {
@@ -8,6 +8,7 @@ use ulid::Ulid;
 
 pub use crate::error::{Error, Result};
 
+pub mod admin;
 #[doc(hidden)]
 #[cfg(feature = "app-management")]
 pub mod apps;
@@ -15,6 +16,7 @@ pub mod debug;
 pub mod dht;
 pub mod error;
 pub mod file_transfer;
+pub mod location;
 pub mod neighbours;
 pub mod pub_sub;
 pub mod req_resp;
@@ -0,0 +1,418 @@
+use std::{
+    num::NonZeroUsize,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use libp2p_identity::PeerId;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    grpc,
+};
+
+/// Mean radius of the earth in meters, used for distance calculations.
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// A point on the earth's surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GeoPoint {
+    /// Latitude in degrees (WGS 84).
+    pub latitude: f64,
+    /// Longitude in degrees (WGS 84).
+    pub longitude: f64,
+}
+
+impl GeoPoint {
+    /// Creates a point from its coordinates.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the latitude or longitude are out of range.
+    pub fn new(latitude: f64, longitude: f64) -> Result<Self> {
+        let point = Self {
+            latitude,
+            longitude,
+        };
+        point.validate()?;
+        Ok(point)
+    }
+
+    /// Checks that the coordinates of the point are in range.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the latitude or longitude are out of range.
+    pub fn validate(&self) -> Result<()> {
+        if !(-90.0..=90.0).contains(&self.latitude) {
+            return Err(Error::InvalidLocation(format!(
+                "Latitude {} is not between -90 and 90",
+                self.latitude
+            )));
+        }
+
+        if !(-180.0..=180.0).contains(&self.longitude) {
+            return Err(Error::InvalidLocation(format!(
+                "Longitude {} is not between -180 and 180",
+                self.longitude
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the great-circle distance to another point in meters.
+    ///
+    /// The distance is calculated with the haversine formula on a spherical earth,
+    /// which is accurate to about 0.5%.
+    #[must_use]
+    pub fn distance(&self, other: &Self) -> f64 {
+        let lat1 = self.latitude.to_radians();
+        let lat2 = other.latitude.to_radians();
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+
+        2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin()
+    }
+}
+
+impl From<GeoPoint> for grpc::GeoPoint {
+    fn from(point: GeoPoint) -> Self {
+        Self {
+            latitude: point.latitude,
+            longitude: point.longitude,
+        }
+    }
+}
+
+impl TryFrom<grpc::GeoPoint> for GeoPoint {
+    type Error = Error;
+
+    fn try_from(point: grpc::GeoPoint) -> Result<Self> {
+        Self::new(point.latitude, point.longitude)
+    }
+}
+
+/// A geographic position of a peer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Location {
+    /// Latitude in degrees (WGS 84).
+    pub latitude: f64,
+    /// Longitude in degrees (WGS 84).
+    pub longitude: f64,
+    /// Altitude in meters above the WGS 84 ellipsoid, if known.
+    pub altitude: Option<f64>,
+    /// Horizontal accuracy (radius of uncertainty) in meters, if known.
+    pub accuracy: Option<f64>,
+    /// The time at which the position was measured.
+    pub timestamp: SystemTime,
+}
+
+impl Location {
+    /// Creates a location measured now.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the latitude or longitude are out of range.
+    pub fn new(latitude: f64, longitude: f64) -> Result<Self> {
+        let location = Self {
+            latitude,
+            longitude,
+            altitude: None,
+            accuracy: None,
+            timestamp: SystemTime::now(),
+        };
+        location.validate()?;
+        Ok(location)
+    }
+
+    #[must_use]
+    pub fn with_altitude(mut self, altitude: f64) -> Self {
+        self.altitude = Some(altitude);
+        self
+    }
+
+    #[must_use]
+    pub fn with_accuracy(mut self, accuracy: f64) -> Self {
+        self.accuracy = Some(accuracy);
+        self
+    }
+
+    #[must_use]
+    pub fn with_timestamp(mut self, timestamp: SystemTime) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Returns the point on the earth's surface of the location.
+    #[must_use]
+    pub fn point(&self) -> GeoPoint {
+        GeoPoint {
+            latitude: self.latitude,
+            longitude: self.longitude,
+        }
+    }
+
+    /// Checks that all fields of the location are in range.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the latitude or longitude are out of range,
+    /// or if the altitude or accuracy are not finite.
+    pub fn validate(&self) -> Result<()> {
+        self.point().validate()?;
+
+        if self.altitude.is_some_and(|altitude| !altitude.is_finite()) {
+            return Err(Error::InvalidLocation(
+                "Altitude should be finite".to_string(),
+            ));
+        }
+
+        if self
+            .accuracy
+            .is_some_and(|accuracy| !accuracy.is_finite() || accuracy < 0.0)
+        {
+            return Err(Error::InvalidLocation(
+                "Accuracy should be finite and not negative".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl From<Location> for grpc::Position {
+    fn from(location: Location) -> Self {
+        Self {
+            latitude: location.latitude,
+            longitude: location.longitude,
+            altitude: location.altitude,
+            accuracy: location.accuracy,
+            timestamp: location
+                .timestamp
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis()
+                .try_into()
+                .unwrap_or(u64::MAX),
+        }
+    }
+}
+
+impl TryFrom<grpc::Position> for Location {
+    type Error = Error;
+
+    fn try_from(location: grpc::Position) -> Result<Self> {
+        let location = Self {
+            latitude: location.latitude,
+            longitude: location.longitude,
+            altitude: location.altitude,
+            accuracy: location.accuracy,
+            timestamp: UNIX_EPOCH + Duration::from_millis(location.timestamp),
+        };
+        location.validate()?;
+        Ok(location)
+    }
+}
+
+impl From<Option<Location>> for grpc::OptionalLocation {
+    fn from(location: Option<Location>) -> Self {
+        Self {
+            location: location.map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<grpc::OptionalLocation> for Option<Location> {
+    type Error = Error;
+
+    fn try_from(location: grpc::OptionalLocation) -> Result<Self> {
+        location.location.map(TryInto::try_into).transpose()
+    }
+}
+
+/// The location of a peer, as announced by the peer itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PeerLocation {
+    pub peer_id: PeerId,
+    pub location: Location,
+}
+
+impl From<PeerLocation> for grpc::PeerLocation {
+    fn from(location: PeerLocation) -> Self {
+        Self {
+            peer: location.peer_id.into(),
+            location: location.location.into(),
+        }
+    }
+}
+
+impl TryFrom<grpc::PeerLocation> for PeerLocation {
+    type Error = Error;
+
+    fn try_from(location: grpc::PeerLocation) -> Result<Self> {
+        Ok(Self {
+            peer_id: location.peer.try_into()?,
+            location: location.location.try_into()?,
+        })
+    }
+}
+
+/// A query for the peers near a point.
+///
+/// Without a radius or a limit, all peers with a known location are returned.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NearbyQuery {
+    /// The point to search around. If `None`, the location of the local node is used.
+    pub center: Option<GeoPoint>,
+    /// Only peers within this distance in meters are returned.
+    pub radius: Option<f64>,
+    /// Only this number of nearest peers are returned.
+    pub limit: Option<NonZeroUsize>,
+}
+
+impl NearbyQuery {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn center(mut self, center: GeoPoint) -> Self {
+        self.center = Some(center);
+        self
+    }
+
+    #[must_use]
+    pub fn radius(mut self, radius: f64) -> Self {
+        self.radius = Some(radius);
+        self
+    }
+
+    #[must_use]
+    pub fn limit(mut self, limit: NonZeroUsize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl From<NearbyQuery> for grpc::NearbyPeersQuery {
+    fn from(query: NearbyQuery) -> Self {
+        Self {
+            center: query.center.map(Into::into),
+            radius: query.radius,
+            limit: query
+                .limit
+                .map(|limit| u32::try_from(limit.get()).unwrap_or(u32::MAX)),
+        }
+    }
+}
+
+impl TryFrom<grpc::NearbyPeersQuery> for NearbyQuery {
+    type Error = Error;
+
+    fn try_from(query: grpc::NearbyPeersQuery) -> Result<Self> {
+        if query
+            .radius
+            .is_some_and(|radius| radius.is_nan() || radius < 0.0)
+        {
+            return Err(Error::InvalidLocation(
+                "Radius should not be negative".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            center: query.center.map(TryInto::try_into).transpose()?,
+            radius: query.radius,
+            limit: query
+                .limit
+                .map(|limit| {
+                    usize::try_from(limit)
+                        .ok()
+                        .and_then(NonZeroUsize::new)
+                        .ok_or(Error::InvalidLocation(
+                            "Limit should not be zero".to_string(),
+                        ))
+                })
+                .transpose()?,
+        })
+    }
+}
+
+/// A peer near the queried point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NearbyPeer {
+    pub peer_id: PeerId,
+    pub location: Location,
+    /// Distance to the queried point in meters.
+    pub distance: f64,
+}
+
+impl From<NearbyPeer> for grpc::NearbyPeer {
+    fn from(peer: NearbyPeer) -> Self {
+        Self {
+            peer: peer.peer_id.into(),
+            location: peer.location.into(),
+            distance: peer.distance,
+        }
+    }
+}
+
+impl TryFrom<grpc::NearbyPeer> for NearbyPeer {
+    type Error = Error;
+
+    fn try_from(peer: grpc::NearbyPeer) -> Result<Self> {
+        Ok(Self {
+            peer_id: peer.peer.try_into()?,
+            location: peer.location.try_into()?,
+            distance: peer.distance,
+        })
+    }
+}
+
+impl FromIterator<NearbyPeer> for grpc::NearbyPeers {
+    fn from_iter<I: IntoIterator<Item = NearbyPeer>>(iter: I) -> Self {
+        Self {
+            peers: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<grpc::NearbyPeers> for Vec<NearbyPeer> {
+    type Error = Error;
+
+    fn try_from(peers: grpc::NearbyPeers) -> Result<Self> {
+        peers.peers.into_iter().map(TryInto::try_into).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        let munich = GeoPoint::new(48.137_154, 11.576_124).unwrap();
+        let berlin = GeoPoint::new(52.520_008, 13.404_954).unwrap();
+
+        let distance = munich.distance(&berlin);
+        assert!((distance - 504_000.0).abs() < 2_000.0, "{distance}");
+        assert!(munich.distance(&munich).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_invalid_coordinates_are_rejected() {
+        assert!(GeoPoint::new(91.0, 0.0).is_err());
+        assert!(GeoPoint::new(0.0, -181.0).is_err());
+        assert!(GeoPoint::new(f64::NAN, 0.0).is_err());
+        assert!(Location::new(48.0, 11.0).is_ok());
+    }
+}
@@ -53,6 +53,7 @@ impl From<Message> for grpc::PubSubMessage {
             topic: grpc::Topic {
                 topic: message.topic,
             },
+            retain: None,
         }
     }
 }
@@ -98,3 +99,142 @@ impl TryFrom<grpc::PubSubRecvMessage> for ReceivedMessage {
         })
     }
 }
+
+/// An event of a subscription to a reliable topic.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ReliableEvent {
+    /// A message, delivered in the order in which its publisher published it.
+    Message(ReceivedMessage),
+    /// Messages of a publisher were lost and couldn't be recovered from its history.
+    Lost { publisher: PeerId, count: u64 },
+}
+
+impl From<ReliableEvent> for grpc::PubSubReliableEvent {
+    fn from(event: ReliableEvent) -> Self {
+        let event = match event {
+            ReliableEvent::Message(message) => {
+                grpc::pub_sub_reliable_event::Event::Msg(message.into())
+            }
+            ReliableEvent::Lost { publisher, count } => {
+                grpc::pub_sub_reliable_event::Event::Loss(grpc::PubSubMessageLoss {
+                    publisher: publisher.into(),
+                    count,
+                })
+            }
+        };
+
+        Self { event: Some(event) }
+    }
+}
+
+impl TryFrom<grpc::PubSubReliableEvent> for ReliableEvent {
+    type Error = Error;
+
+    fn try_from(event: grpc::PubSubReliableEvent) -> Result<Self> {
+        Ok(match event.event.ok_or(Error::MissingEvent)? {
+            grpc::pub_sub_reliable_event::Event::Msg(message) => Self::Message(message.try_into()?),
+            grpc::pub_sub_reliable_event::Event::Loss(loss) => Self::Lost {
+                publisher: loss.publisher.try_into()?,
+                count: loss.count,
+            },
+        })
+    }
+}
+
+/// The verdict of a validator about a received message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MessageAcceptance {
+    /// The message is valid, it is delivered to subscribers and forwarded to other peers.
+    Accept,
+    /// The message is invalid, it is dropped and the peer that propagated it is penalised.
+    Reject,
+    /// The message is dropped without penalising the peer that propagated it.
+    Ignore,
+}
+
+impl From<MessageAcceptance> for grpc::pub_sub_validation_result::Acceptance {
+    fn from(acceptance: MessageAcceptance) -> Self {
+        match acceptance {
+            MessageAcceptance::Accept => Self::Accept(grpc::Empty {}),
+            MessageAcceptance::Reject => Self::Reject(grpc::Empty {}),
+            MessageAcceptance::Ignore => Self::Ignore(grpc::Empty {}),
+        }
+    }
+}
+
+impl From<grpc::pub_sub_validation_result::Acceptance> for MessageAcceptance {
+    fn from(acceptance: grpc::pub_sub_validation_result::Acceptance) -> Self {
+        match acceptance {
+            grpc::pub_sub_validation_result::Acceptance::Accept(_) => Self::Accept,
+            grpc::pub_sub_validation_result::Acceptance::Reject(_) => Self::Reject,
+            grpc::pub_sub_validation_result::Acceptance::Ignore(_) => Self::Ignore,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ValidationResult {
+    pub message_id: MessageId,
+    pub acceptance: MessageAcceptance,
+}
+
+impl From<ValidationResult> for grpc::PubSubValidationResult {
+    fn from(result: ValidationResult) -> Self {
+        Self {
+            msg_id: result.message_id.into(),
+            acceptance: Some(result.acceptance.into()),
+        }
+    }
+}
+
+impl TryFrom<grpc::PubSubValidationResult> for ValidationResult {
+    type Error = Error;
+
+    fn try_from(result: grpc::PubSubValidationResult) -> Result<Self> {
+        Ok(Self {
+            message_id: result.msg_id.into(),
+            acceptance: result.acceptance.ok_or(Error::MissingAcceptance)?.into(),
+        })
+    }
+}
+
+/// The peers known to take part in a topic.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TopicPeers {
+    /// All peers that are known to be subscribed to the topic.
+    pub subscribers: Vec<PeerId>,
+    /// The peers in the gossipsub mesh of the topic, to which messages are forwarded directly.
+    pub mesh: Vec<PeerId>,
+}
+
+impl From<TopicPeers> for grpc::PubSubTopicPeers {
+    fn from(peers: TopicPeers) -> Self {
+        Self {
+            subscribers: peers.subscribers.into_iter().map(Into::into).collect(),
+            mesh: peers.mesh.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<grpc::PubSubTopicPeers> for TopicPeers {
+    type Error = Error;
+
+    fn try_from(peers: grpc::PubSubTopicPeers) -> Result<Self> {
+        Ok(Self {
+            subscribers: peers
+                .subscribers
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_>>()?,
+            mesh: peers
+                .mesh
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_>>()?,
+        })
+    }
+}
@@ -19,6 +19,15 @@ pub enum TopicQuery {
 }
 
 impl TopicQuery {
+    /// Creates a query that matches all topics matching the regex.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the regex is invalid.
+    pub fn regex(regex: &str) -> Result<Self> {
+        Ok(Self::Regex(Regex::new(regex)?))
+    }
+
     pub fn matches(&self, topic: impl AsRef<str>) -> bool {
         match self {
             TopicQuery::String(query) => query.as_ref() == topic.as_ref(),
@@ -108,6 +117,8 @@ pub struct InboundRequest {
     pub id: u64,
     pub peer_id: PeerId,
     pub req: Request,
+    /// Whether the requester expects a stream of response chunks instead of a single response.
+    pub streaming: bool,
 }
 
 impl From<InboundRequest> for grpc::RecvRequest {
@@ -116,6 +127,7 @@ impl From<InboundRequest> for grpc::RecvRequest {
             peer: request.peer_id.into(),
             msg: request.req.into(),
             seq: request.id,
+            streaming: Some(request.streaming),
         }
     }
 }
@@ -128,6 +140,7 @@ impl TryFrom<grpc::RecvRequest> for InboundRequest {
             id: request.seq,
             peer_id: request.peer.try_into()?,
             req: request.msg.into(),
+            streaming: request.streaming.unwrap_or_default(),
         })
     }
 }
@@ -142,6 +155,8 @@ pub enum ResponseError {
         .0.as_deref().map_or("the empty topic".to_string(), |topic| format!("topic '{topic:?}'"))
     )]
     TopicNotSubscribed(Option<String>),
+    #[error("Request failed: {0}")]
+    Failed(String),
     #[error("Application error: {0}")]
     App(String),
 }
@@ -164,6 +179,9 @@ impl From<Response> for grpc::Response {
         Self {
             response: Some(match response {
                 Response::Data(data) => grpc::response::Response::Data(grpc::Data { data }),
+                Response::Error(ResponseError::Timeout) => {
+                    grpc::response::Response::Timeout(grpc::Empty {})
+                }
                 Response::Error(e) => grpc::response::Response::Error(e.to_string()),
             }),
         }
@@ -188,6 +206,7 @@ impl TryFrom<grpc::Response> for Response {
         Ok(match response.response.ok_or(Error::MissingResponse)? {
             grpc::response::Response::Data(data) => Self::Data(data.data),
             grpc::response::Response::Error(e) => Self::Error(e.into()),
+            grpc::response::Response::Timeout(_) => Self::Error(ResponseError::Timeout),
         })
     }
 }
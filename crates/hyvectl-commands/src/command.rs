@@ -2,7 +2,9 @@ use std::path::PathBuf;
 
 use clap::{Args, Command, CommandFactory, Parser, Subcommand};
 
-use crate::families::{apps, debug, discovery, file, init, kv, pub_sub, reqres, whoami};
+use crate::families::{
+    apps, debug, discovery, file, forward, init, kv, location, peers, pub_sub, reqres, whoami,
+};
 
 #[derive(Parser)]
 #[command(name = "hyvectl", about = "Hyvectl")]
@@ -36,6 +38,14 @@ pub enum Families {
     /// File Transfer Service
     #[command(subcommand)]
     File(file::File),
+    /// Location Service
+    #[command(subcommand)]
+    Location(location::Location),
+    /// Peer Administration
+    #[command(subcommand)]
+    Peers(peers::Peers),
+    /// Forward a local TCP port to a port on another peer
+    Forward(forward::Forward),
     /// Prints the local Peer-id
     Whoami(whoami::Whoami),
     /// Initialize a new hyveOS instance. This should only be used during installation.
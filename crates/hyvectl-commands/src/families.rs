@@ -2,8 +2,11 @@ pub mod apps;
 pub mod debug;
 pub mod discovery;
 pub mod file;
+pub mod forward;
 pub mod init;
 pub mod kv;
+pub mod location;
+pub mod peers;
 pub mod pub_sub;
 pub mod reqres;
 pub mod whoami;
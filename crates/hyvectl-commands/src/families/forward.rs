@@ -0,0 +1,9 @@
+#[derive(clap::Args, Debug)]
+pub struct Forward {
+    /// Peer to forward the connections to
+    pub peer: String,
+    /// Port on the local host of the peer
+    pub remote_port: u16,
+    /// Local port to accept connections on
+    pub local_port: u16,
+}
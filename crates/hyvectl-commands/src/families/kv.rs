@@ -11,6 +11,15 @@ pub enum Kv {
         /// Topic under which to publish key
         #[arg(long)]
         topic: Option<String>,
+        /// Time-to-live of the record in seconds
+        #[arg(long, value_name = "SECONDS")]
+        ttl: Option<u64>,
+        /// Number of peers that have to store the record (`one`, `majority`, `all` or a number)
+        #[arg(long)]
+        quorum: Option<String>,
+        /// Sign the record, so that other peers can not overwrite or remove it
+        #[arg(long)]
+        owned: bool,
     },
     /// Get the value for the given key
     Get {
@@ -19,5 +28,19 @@ pub enum Kv {
         /// Topic under which to retrieve key
         #[arg(long)]
         topic: Option<String>,
+        /// Number of peers that have to return the record (`one`, `majority`, `all` or a number)
+        #[arg(long)]
+        quorum: Option<String>,
+        /// Show every distinct value found, with the peers holding it
+        #[arg(long)]
+        all: bool,
+    },
+    /// Watch the given key and print every new value
+    Watch {
+        /// Key to watch
+        key: String,
+        /// Topic under which to watch key
+        #[arg(long)]
+        topic: Option<String>,
     },
 }
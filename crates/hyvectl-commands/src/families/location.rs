@@ -0,0 +1,48 @@
+use std::num::NonZeroUsize;
+
+use clap::Subcommand;
+
+#[derive(Subcommand)]
+pub enum Location {
+    /// Set the location of this node
+    #[command(allow_negative_numbers = true)]
+    Set {
+        /// Latitude in degrees
+        latitude: f64,
+        /// Longitude in degrees
+        longitude: f64,
+        /// Altitude in meters
+        #[arg(long)]
+        altitude: Option<f64>,
+        /// Horizontal accuracy in meters
+        #[arg(long)]
+        accuracy: Option<f64>,
+    },
+    /// Get the location of a peer
+    Get {
+        /// Peer to get the location of (defaults to this node)
+        peer: Option<String>,
+    },
+    /// Print location updates of other peers
+    Subscribe {
+        /// Only print updates of the given peer
+        #[arg(long)]
+        peer: Option<String>,
+    },
+    /// List the peers near a point, nearest first
+    #[command(allow_negative_numbers = true)]
+    Nearby {
+        /// Latitude of the point to search around (defaults to the location of this node)
+        #[arg(long, requires = "longitude")]
+        latitude: Option<f64>,
+        /// Longitude of the point to search around (defaults to the location of this node)
+        #[arg(long, requires = "latitude")]
+        longitude: Option<f64>,
+        /// Only list peers within this distance in meters
+        #[arg(long)]
+        radius: Option<f64>,
+        /// Only list this number of nearest peers
+        #[arg(long)]
+        limit: Option<NonZeroUsize>,
+    },
+}
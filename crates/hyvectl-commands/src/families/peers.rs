@@ -0,0 +1,21 @@
+use clap::Subcommand;
+
+#[derive(Subcommand)]
+pub enum Peers {
+    /// Block a peer from connecting to this node until it restarts
+    ///
+    /// To block a peer permanently, add it to `denied-peers` in the config file.
+    Block {
+        /// Peer to block
+        peer: String,
+    },
+    /// Unblock a previously blocked peer until this node restarts
+    ///
+    /// Peers in `denied-peers` in the config file are blocked again on restart.
+    Unblock {
+        /// Peer to unblock
+        peer: String,
+    },
+    /// List the allowed and blocked peers
+    List,
+}
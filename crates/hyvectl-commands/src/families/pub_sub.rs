@@ -8,11 +8,26 @@ pub enum PubSub {
         topic: String,
         /// Message to publish
         message: String,
+        /// Store the message as the retained message of the topic
+        #[arg(long)]
+        retain: bool,
     },
 
     /// Retrieve messages from the given topic
     Get {
         /// Topic in which to listen for messages
         topic: String,
+        /// Interpret the topic as a regex and listen in all matching topics
+        #[arg(long)]
+        regex: bool,
+    },
+
+    /// List the topics this node is subscribed to
+    Topics,
+
+    /// List the peers subscribed to the given topic and the peers in its mesh
+    Peers {
+        /// Topic of which to list the peers
+        topic: String,
     },
 }
@@ -3,14 +3,28 @@ use clap::Subcommand;
 #[derive(Subcommand)]
 pub enum ReqRes {
     /// Send the message to a given peer and returns its response
+    #[command(allow_missing_positional = true)]
     Send {
         /// Target peer
-        peer: String,
+        #[arg(required_unless_present = "providers")]
+        peer: Option<String>,
         /// Request
         request: String,
         /// Topic under which to send the request
         #[arg(long)]
         topic: Option<String>,
+        /// Seconds to wait for the response before giving up
+        #[arg(long)]
+        timeout: Option<u64>,
+        /// Send the request to all providers of this discovery key instead of a single peer
+        #[arg(long, conflicts_with = "peer")]
+        providers: Option<String>,
+        /// Topic under which the discovery key is provided
+        #[arg(long, requires = "providers")]
+        providers_topic: Option<String>,
+        /// Maximum number of providers to send the request to
+        #[arg(long, requires = "providers")]
+        max_peers: Option<usize>,
     },
 
     /// Retrieve a stream of messages from peers
@@ -2,8 +2,11 @@ mod apps;
 pub mod debug;
 mod discovery;
 mod file;
+mod forward;
 pub mod init;
 pub mod kv;
+mod location;
+mod peers;
 pub mod pub_sub;
 pub mod reqres;
 mod whoami;
@@ -0,0 +1,82 @@
+use futures::{channel::mpsc, stream::BoxStream, StreamExt as _};
+use hyvectl_commands::families::forward::Forward;
+use hyveos_sdk::Connection;
+use libp2p_identity::PeerId;
+use tokio::net::TcpListener;
+
+use crate::{boxed_try_stream, error::HyveCtlResult, out::CommandOutput, util::CommandFamily};
+
+impl CommandFamily for Forward {
+    async fn run(
+        self,
+        connection: &Connection,
+    ) -> BoxStream<'static, HyveCtlResult<CommandOutput>> {
+        let stream_service = connection.stream();
+
+        let Forward {
+            peer,
+            remote_port,
+            local_port,
+        } = self;
+
+        boxed_try_stream! {
+            let peer_id = peer.parse::<PeerId>()?;
+            let listener = TcpListener::bind(("localhost", local_port)).await?;
+
+            yield CommandOutput::result()
+                .with_field("local_port", local_port.to_string())
+                .with_field("peer_id", peer_id.to_string())
+                .with_field("remote_port", remote_port.to_string())
+                .with_tty_template("🔀 Forwarding { localhost:{local_port} } to { {peer_id}:{remote_port} }")
+                .with_non_tty_template("{local_port},{peer_id},{remote_port}");
+
+            let (sender, mut closed) = mpsc::unbounded();
+
+            loop {
+                // Outputs can't be yielded from within `select!`
+                let output = tokio::select! {
+                    res = listener.accept() => res.map(|(mut tcp_stream, address)| {
+                        let mut stream_service = stream_service.clone();
+                        let sender = sender.clone();
+
+                        tokio::spawn(async move {
+                            let res = async {
+                                let mut stream = stream_service
+                                    .forward_port(peer_id, remote_port)
+                                    .await
+                                    .map_err(|e| e.to_string())?;
+
+                                tokio::io::copy_bidirectional(&mut tcp_stream, &mut stream)
+                                    .await
+                                    .map_err(|e| e.to_string())
+                            }
+                            .await;
+
+                            let _ = sender.unbounded_send((address, res));
+                        });
+
+                        CommandOutput::result()
+                            .with_field("address", address.to_string())
+                            .with_tty_template("🔗 Accepted connection from { {address} }")
+                            .with_non_tty_template("accepted,{address}")
+                    }),
+                    Some((address, res)) = closed.next() => Ok(match res {
+                        Ok((sent, received)) => CommandOutput::result()
+                            .with_field("address", address.to_string())
+                            .with_field("sent", sent.to_string())
+                            .with_field("received", received.to_string())
+                            .with_tty_template("🔌 Closed connection from { {address} } (sent {sent} bytes, received {received} bytes)")
+                            .with_non_tty_template("closed,{address},{sent},{received}"),
+                        Err(error) => CommandOutput::result()
+                            .with_field("address", address.to_string())
+                            .with_field("error", error)
+                            .with_tty_template("❌ Failed to forward connection from { {address} }: {error}")
+                            .with_non_tty_template("failed,{address},{error}"),
+                    }),
+                }?;
+
+                yield output;
+            }
+        }
+    }
+}
@@ -1,6 +1,11 @@
-use futures::stream::BoxStream;
+use std::time::{Duration, SystemTime};
+
+use futures::{stream::BoxStream, TryStreamExt as _};
 use hyvectl_commands::families::kv::Kv;
-use hyveos_sdk::Connection;
+use hyveos_sdk::{
+    services::kv::{PutOptions, Quorum},
+    Connection, PeerId,
+};
 
 use crate::{boxed_try_stream, error::HyveCtlResult, out::CommandOutput, util::CommandFamily};
 
@@ -12,23 +17,75 @@ impl CommandFamily for Kv {
         let mut kv_service = connection.kv();
 
         match self {
-            Kv::Get { key, topic } => {
+            Kv::Get {
+                key,
+                topic,
+                quorum,
+                all: true,
+            } => {
+                boxed_try_stream! {
+                    let topic = topic.unwrap_or_default();
+                    let quorum = quorum.map(|q| q.parse::<Quorum>()).transpose()?.unwrap_or(Quorum::One);
+
+                    yield CommandOutput::spinner("Fetching values...", &["◐", "◑", "◒", "◓"]);
+
+                    let values = kv_service.get_record_values(&topic, key.clone(), quorum).await?;
+
+                    if values.is_empty() {
+                        yield CommandOutput::result()
+                            .with_field("topic", topic.clone())
+                            .with_field("key", key.clone())
+                            .with_tty_template("🔑 No values found under { {key} }")
+                            .with_non_tty_template("Unable to retrieve key {key} in {topic}");
+                    }
+
+                    for value in values {
+                        let mut holders = value.peers.iter().map(ToString::to_string).collect::<Vec<_>>();
+                        if value.local {
+                            holders.insert(0, "local".to_string());
+                        }
+
+                        yield CommandOutput::result()
+                            .with_field("topic", topic.clone())
+                            .with_field("key", key.clone())
+                            .with_field("value", String::from_utf8(value.value)?)
+                            .with_field("holders", holders.join(","))
+                            .with_tty_template("🔑 { {value} } held by { {holders} }")
+                            .with_non_tty_template("{value},{holders}");
+                    }
+                }
+            }
+            Kv::Get {
+                key,
+                topic,
+                quorum,
+                all: false,
+            } => {
                 boxed_try_stream! {
                     let template = match topic {
                         Some(_) => {"🔑 Retrieved { {value} } under { {key} } in topic { {topic} }"},
                         None => {"🔑 Retrieved { {value} } under { {key} }"}
                     };
 
+                    let record_template = match topic {
+                        Some(_) => {"🔑 Retrieved { {value} } under { {key} } in topic { {topic} }\n   published by { {publisher} }, expires { {expires} }"},
+                        None => {"🔑 Retrieved { {value} } under { {key} }\n   published by { {publisher} }, expires { {expires} }"}
+                    };
+
                     let topic = topic.unwrap_or_default();
 
-                    let result = kv_service.get_record(&topic, key.clone()).await?;
+                    let quorum = quorum.map(|q| q.parse::<Quorum>()).transpose()?.unwrap_or(Quorum::One);
+
+                    let result = kv_service.get_record_with_quorum(&topic, key.clone(), quorum).await?;
 
                     match result {
-                        Some(res) => yield CommandOutput::result()
+                        Some(record) => yield CommandOutput::result()
                             .with_field("topic", topic)
                             .with_field("key", key)
-                            .with_field("value", String::from_utf8(res)?)
-                            .with_tty_template(template)
+                            .with_field("value", String::from_utf8(record.value)?)
+                            .with_field("publisher", format_publisher(record.publisher, record.owned))
+                            .with_field("expires", format_expiry(record.expires))
+                            .with_tty_template(record_template)
                             .with_non_tty_template("{value}"),
                         None => yield CommandOutput::result()
                             .with_field("topic", topic)
@@ -38,7 +95,14 @@ impl CommandFamily for Kv {
                     }
                 }
             }
-            Kv::Put { key, value, topic } => {
+            Kv::Put {
+                key,
+                value,
+                topic,
+                ttl,
+                quorum,
+                owned,
+            } => {
                 boxed_try_stream! {
                     let template = match topic {
                         Some(_) => {"🔑 Added { {value} } under { {key} } in topic { {topic} }"},
@@ -47,7 +111,20 @@ impl CommandFamily for Kv {
 
                     let topic = topic.unwrap_or_default();
 
-                    kv_service.put_record(&topic, key.clone(), value.clone()).await?;
+                    let mut options = PutOptions::new();
+                    if let Some(ttl) = ttl {
+                        options = options.ttl(Duration::from_secs(ttl));
+                    }
+                    if let Some(quorum) = quorum {
+                        options = options.quorum(quorum.parse()?);
+                    }
+                    if owned {
+                        options = options.owned();
+                    }
+
+                    kv_service
+                        .put_record_with_options(&topic, key.clone(), value.clone(), options)
+                        .await?;
 
                     yield CommandOutput::result()
                         .with_field("topic", topic)
@@ -57,6 +134,53 @@ impl CommandFamily for Kv {
                         .with_non_tty_template("{value},{key},{topic}");
                 }
             }
+            Kv::Watch { key, topic } => {
+                boxed_try_stream! {
+                    let topic = topic.unwrap_or_default();
+
+                    yield CommandOutput::spinner("Waiting for changes...", &["◐", "◒", "◑", "◓"]);
+
+                    let mut changes = kv_service.watch(&topic, key.clone()).await?;
+
+                    while let Some(record) = changes.try_next().await? {
+                        match record {
+                            Some(record) => yield CommandOutput::result()
+                                .with_field("topic", topic.clone())
+                                .with_field("key", key.clone())
+                                .with_field("value", String::from_utf8(record.value)?)
+                                .with_field("publisher", format_publisher(record.publisher, record.owned))
+                                .with_tty_template("🔑 { {key} } changed to { {value} }, published by { {publisher} }")
+                                .with_non_tty_template("{value}"),
+                            None => yield CommandOutput::result()
+                                .with_field("topic", topic.clone())
+                                .with_field("key", key.clone())
+                                .with_tty_template("🔑 { {key} } has no value")
+                                .with_non_tty_template(""),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn format_publisher(publisher: Option<PeerId>, owned: bool) -> String {
+    match publisher {
+        Some(publisher) if owned => format!("{publisher} (verified owner)"),
+        Some(publisher) => publisher.to_string(),
+        None => "unknown".to_string(),
+    }
+}
+
+fn format_expiry(expires: Option<SystemTime>) -> String {
+    match expires {
+        Some(expires) => {
+            let remaining = expires
+                .duration_since(SystemTime::now())
+                .unwrap_or_default()
+                .as_secs();
+            format!("in {remaining}s")
         }
+        None => "never".to_string(),
     }
 }
@@ -0,0 +1,134 @@
+use std::time::SystemTime;
+
+use futures::{stream::BoxStream, TryStreamExt as _};
+use hyvectl_commands::families::location::Location as LocationCommand;
+use hyveos_sdk::{
+    services::{GeoPoint, Location, NearbyQuery},
+    Connection, PeerId,
+};
+
+use crate::{boxed_try_stream, error::HyveCtlResult, out::CommandOutput, util::CommandFamily};
+
+impl CommandFamily for LocationCommand {
+    async fn run(
+        self,
+        connection: &Connection,
+    ) -> BoxStream<'static, HyveCtlResult<CommandOutput>> {
+        let mut location_service = connection.location();
+
+        match self {
+            LocationCommand::Set {
+                latitude,
+                longitude,
+                altitude,
+                accuracy,
+            } => {
+                boxed_try_stream! {
+                    let mut location = Location::new(latitude, longitude)?;
+                    if let Some(altitude) = altitude {
+                        location = location.with_altitude(altitude);
+                    }
+                    if let Some(accuracy) = accuracy {
+                        location = location.with_accuracy(accuracy);
+                    }
+
+                    location_service.set(location).await?;
+
+                    yield location_output(location)
+                        .with_tty_template("📍 Location set to { {latitude}, {longitude} }")
+                        .with_non_tty_template("{latitude},{longitude},{altitude},{accuracy}");
+                }
+            }
+            LocationCommand::Get { peer } => {
+                boxed_try_stream! {
+                    let peer_id = peer.map(|peer| peer.parse::<PeerId>()).transpose()?;
+
+                    let peer = peer_id.map_or_else(|| "this node".to_string(), |peer_id| peer_id.to_string());
+
+                    match location_service.get(peer_id).await? {
+                        Some(location) => yield location_output(location)
+                            .with_field("peer", peer)
+                            .with_tty_template("📍 { {peer} } is at { {latitude}, {longitude} }, altitude { {altitude} }, accuracy { {accuracy} }, measured { {age} }")
+                            .with_non_tty_template("{latitude},{longitude},{altitude},{accuracy}"),
+                        None => yield CommandOutput::result()
+                            .with_field("peer", peer)
+                            .with_tty_template("📍 Location of { {peer} } is unknown")
+                            .with_non_tty_template("Unable to retrieve location of {peer}"),
+                    }
+                }
+            }
+            LocationCommand::Subscribe { peer } => {
+                boxed_try_stream! {
+                    let peer_id = peer.map(|peer| peer.parse::<PeerId>()).transpose()?;
+
+                    yield CommandOutput::spinner("Waiting for location updates...", &["◐", "◒", "◑", "◓"]);
+
+                    let mut updates = location_service.subscribe(peer_id).await?;
+
+                    while let Some(update) = updates.try_next().await? {
+                        yield location_output(update.location)
+                            .with_field("peer", update.peer_id.to_string())
+                            .with_tty_template("📍 { {peer} } moved to { {latitude}, {longitude} }")
+                            .with_non_tty_template("{peer},{latitude},{longitude},{altitude},{accuracy}");
+                    }
+                }
+            }
+            LocationCommand::Nearby {
+                latitude,
+                longitude,
+                radius,
+                limit,
+            } => {
+                boxed_try_stream! {
+                    let mut query = NearbyQuery::new();
+                    if let (Some(latitude), Some(longitude)) = (latitude, longitude) {
+                        query = query.center(GeoPoint::new(latitude, longitude)?);
+                    }
+                    if let Some(radius) = radius {
+                        query = query.radius(radius);
+                    }
+                    if let Some(limit) = limit {
+                        query = query.limit(limit);
+                    }
+
+                    let peers = location_service.get_nearby_peers(query).await?;
+
+                    if peers.is_empty() {
+                        yield CommandOutput::result()
+                            .with_tty_template("📍 No peers found nearby")
+                            .with_non_tty_template("");
+                    }
+
+                    for peer in peers {
+                        yield location_output(peer.location)
+                            .with_field("peer", peer.peer_id.to_string())
+                            .with_field("distance", format!("{:.1}m", peer.distance))
+                            .with_tty_template("📍 { {peer} } at { {latitude}, {longitude} }, { {distance} } away")
+                            .with_non_tty_template("{peer},{distance},{latitude},{longitude}");
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn location_output(location: Location) -> CommandOutput {
+    CommandOutput::result()
+        .with_field("latitude", location.latitude.to_string())
+        .with_field("longitude", location.longitude.to_string())
+        .with_field("altitude", format_meters(location.altitude))
+        .with_field("accuracy", format_meters(location.accuracy))
+        .with_field("age", format_age(location.timestamp))
+}
+
+fn format_meters(value: Option<f64>) -> String {
+    value.map_or_else(|| "unknown".to_string(), |value| format!("{value}m"))
+}
+
+fn format_age(timestamp: SystemTime) -> String {
+    let age = SystemTime::now()
+        .duration_since(timestamp)
+        .unwrap_or_default()
+        .as_secs();
+    format!("{age}s ago")
+}
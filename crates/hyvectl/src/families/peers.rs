@@ -0,0 +1,67 @@
+use futures::stream::BoxStream;
+use hyvectl_commands::families::peers::Peers;
+use hyveos_sdk::{Connection, PeerId};
+
+use crate::{boxed_try_stream, error::HyveCtlResult, out::CommandOutput, util::CommandFamily};
+
+impl CommandFamily for Peers {
+    async fn run(
+        self,
+        connection: &Connection,
+    ) -> BoxStream<'static, HyveCtlResult<CommandOutput>> {
+        let mut admin_service = connection.admin();
+
+        match self {
+            Peers::Block { peer } => {
+                boxed_try_stream! {
+                    let peer_id = peer.parse::<PeerId>()?;
+
+                    admin_service.block_peer(peer_id).await?;
+
+                    yield CommandOutput::result()
+                        .with_field("peer", peer_id.to_string())
+                        .with_tty_template("⛔ Blocked { {peer} }")
+                        .with_non_tty_template("{peer}");
+                }
+            }
+            Peers::Unblock { peer } => {
+                boxed_try_stream! {
+                    let peer_id = peer.parse::<PeerId>()?;
+
+                    admin_service.unblock_peer(peer_id).await?;
+
+                    yield CommandOutput::result()
+                        .with_field("peer", peer_id.to_string())
+                        .with_tty_template("✅ Unblocked { {peer} }")
+                        .with_non_tty_template("{peer}");
+                }
+            }
+            Peers::List => {
+                boxed_try_stream! {
+                    let peer_gate = admin_service.get_peer_gate().await?;
+
+                    match peer_gate.allowed {
+                        Some(allowed) => {
+                            for peer_id in allowed {
+                                yield CommandOutput::result()
+                                    .with_field("peer", peer_id.to_string())
+                                    .with_tty_template("✅ { {peer} } is allowed")
+                                    .with_non_tty_template("allowed,{peer}");
+                            }
+                        }
+                        None => yield CommandOutput::result()
+                            .with_tty_template("✅ All peers that are not blocked are allowed")
+                            .with_non_tty_template(""),
+                    }
+
+                    for peer_id in peer_gate.blocked {
+                        yield CommandOutput::result()
+                            .with_field("peer", peer_id.to_string())
+                            .with_tty_template("⛔ { {peer} } is blocked")
+                            .with_non_tty_template("blocked,{peer}");
+                    }
+                }
+            }
+        }
+    }
+}
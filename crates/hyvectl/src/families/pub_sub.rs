@@ -1,6 +1,6 @@
-use futures::{stream::BoxStream, TryStreamExt as _};
+use futures::{stream::BoxStream, StreamExt as _, TryStreamExt as _};
 use hyvectl_commands::families::pub_sub::PubSub;
-use hyveos_core::pub_sub::ReceivedMessage;
+use hyveos_core::{pub_sub::ReceivedMessage, req_resp::TopicQuery};
 use hyveos_sdk::Connection;
 
 use crate::{
@@ -40,9 +40,17 @@ impl CommandFamily for PubSub {
         let mut pub_sub_service = connection.pub_sub();
 
         match self {
-            PubSub::Publish { topic, message } => {
+            PubSub::Publish {
+                topic,
+                message,
+                retain,
+            } => {
                 boxed_try_stream! {
-                    pub_sub_service.publish(&topic, message.clone()).await?;
+                    if retain {
+                        pub_sub_service.publish_retained(&topic, message.clone()).await?;
+                    } else {
+                        pub_sub_service.publish(&topic, message.clone()).await?;
+                    }
 
                     yield CommandOutput::result()
                         .with_field("topic", topic)
@@ -51,17 +59,53 @@ impl CommandFamily for PubSub {
                         .with_non_tty_template("{message},{topic}")
                 }
             }
-            PubSub::Get { topic } => {
+            PubSub::Get { topic, regex } => {
                 boxed_try_stream! {
                     yield CommandOutput::spinner("Waiting for Messages...", &["◐", "◒", "◑", "◓"]);
 
-                    let mut message_stream = pub_sub_service.subscribe(&topic).await?;
+                    let mut message_stream = if regex {
+                        pub_sub_service
+                            .subscribe_query(TopicQuery::regex(&topic)?)
+                            .await?
+                            .boxed()
+                    } else {
+                        pub_sub_service.subscribe(&topic).await?.boxed()
+                    };
 
                     while let Some(event) = message_stream.try_next().await? {
                         yield event.try_into()?
                     }
                 }
             }
+            PubSub::Topics => {
+                boxed_try_stream! {
+                    for topic in pub_sub_service.list_topics().await? {
+                        yield CommandOutput::result()
+                            .with_field("topic", topic)
+                            .with_tty_template("📬 Subscribed to { {topic} }")
+                            .with_non_tty_template("{topic}");
+                    }
+                }
+            }
+            PubSub::Peers { topic } => {
+                boxed_try_stream! {
+                    let peers = pub_sub_service.topic_peers(&topic).await?;
+
+                    for peer_id in peers.subscribers {
+                        let kind = if peers.mesh.contains(&peer_id) {
+                            "mesh"
+                        } else {
+                            "subscriber"
+                        };
+
+                        yield CommandOutput::result()
+                            .with_field("peer", peer_id.to_string())
+                            .with_field("kind", kind.to_string())
+                            .with_tty_template("👥 { {peer} } ({kind})")
+                            .with_non_tty_template("{kind},{peer}");
+                    }
+                }
+            }
         }
     }
 }
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use futures::{stream::BoxStream, TryStreamExt as _};
 use hyvectl_commands::families::reqres::ReqRes;
 use hyveos_core::req_resp::{Response, TopicQuery};
@@ -32,17 +34,68 @@ impl CommandFamily for ReqRes {
                     }
                 }
             }
+            ReqRes::Send {
+                providers: Some(key),
+                request: message,
+                topic,
+                timeout,
+                providers_topic,
+                max_peers,
+                ..
+            } => {
+                boxed_try_stream! {
+                    yield CommandOutput::spinner("Waiting for Responses", &["◐", "◒", "◑", "◓"]);
+
+                    let mut responses = req_res_service
+                        .send_to_providers(
+                            providers_topic.unwrap_or_default(),
+                            key,
+                            message,
+                            topic,
+                            timeout.map(Duration::from_secs),
+                            max_peers,
+                        )
+                        .await?;
+
+                    while let Some((peer_id, response)) = responses.try_next().await? {
+                        let response = match response {
+                            Response::Data(data) => String::from_utf8(data)?,
+                            Response::Error(e) => format!("Error: {e}"),
+                        };
+
+                        yield CommandOutput::result()
+                            .with_field("peer_id", peer_id.to_string())
+                            .with_field("response", response)
+                            .with_tty_template("🗨  { peer: {peer_id}, response: {response} }")
+                            .with_non_tty_template("{peer_id},{response}");
+                    }
+                }
+            }
             ReqRes::Send {
                 peer,
                 request: message,
                 topic,
+                timeout,
+                ..
             } => {
                 boxed_try_stream! {
-                    let peer_id = peer.parse::<PeerId>()?;
+                    let peer_id = peer.unwrap_or_default().parse::<PeerId>()?;
 
                     yield CommandOutput::spinner("Waiting for Response", &["◐", "◒", "◑", "◓"]);
 
-                    let response = req_res_service.send_request(peer_id, message, topic).await?;
+                    let response = match timeout {
+                        Some(timeout) => {
+                            req_res_service
+                                .send_request_with_timeout(
+                                    peer_id,
+                                    message,
+                                    topic,
+                                    Duration::from_secs(timeout),
+                                )
+                                .await?
+                        }
+                        None => req_res_service.send_request(peer_id, message, topic).await?,
+                    };
 
                     let mut output = CommandOutput::result();
 
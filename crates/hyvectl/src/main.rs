@@ -35,6 +35,9 @@ impl CommandFamily for Families {
             Families::ReqRes(cmd) => cmd.run(connection).await,
             Families::Apps(cmd) => cmd.run(connection).await,
             Families::File(cmd) => cmd.run(connection).await,
+            Families::Location(cmd) => cmd.run(connection).await,
+            Families::Peers(cmd) => cmd.run(connection).await,
+            Families::Forward(cmd) => cmd.run(connection).await,
             Families::Whoami(cmd) => cmd.run(connection).await,
             Families::Init(_) => unreachable!(),
         }
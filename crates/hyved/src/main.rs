@@ -1,13 +1,14 @@
-#[cfg(feature = "network")]
-use std::net::SocketAddr;
-use std::{env, path::PathBuf, time::Duration};
+use std::{env, net::SocketAddr, path::PathBuf, time::Duration};
 
 use clap::Parser;
 use dirs::data_local_dir;
 #[cfg(feature = "network")]
 use hyveos_config::parse_socket_addr;
-use hyveos_config::{ApplicationManagementConfig, Config, LogFilter};
-use hyveos_core::DAEMON_NAME;
+use hyveos_config::{
+    ApplicationManagementConfig, Config, KadStoreBackend, LogFilter, PortForwardingConfig,
+    TransportProtocol,
+};
+use hyveos_core::{admin::PeerGate, DAEMON_NAME};
 #[cfg(feature = "batman")]
 use hyveos_ifaddr::if_name_to_index;
 #[cfg(any(feature = "network", feature = "batman"))]
@@ -16,6 +17,8 @@ use hyveos_runtime::{CliConnectionType, Runtime, RuntimeArgs};
 use libp2p::{
     identity::Keypair,
     multiaddr::{Multiaddr, Protocol},
+    pnet::PreSharedKey,
+    PeerId,
 };
 
 const LISTEN_PORT: u16 = 39811;
@@ -81,6 +84,20 @@ pub struct Opts {
     /// Set the path to the keypair file (defaults to `store_directory`/keypair)
     #[clap(short, long, value_name = "FILE")]
     pub key_file: Option<PathBuf>,
+    /// Set the path to the pre-shared key of a private swarm, in the `swarm.key` format used by IPFS.
+    ///
+    /// If set, only nodes with the same key can connect to this node.
+    /// Private swarms use TCP instead of QUIC, so all nodes of a hive have to use the same key.
+    #[clap(long, value_name = "FILE")]
+    pub swarm_key_file: Option<PathBuf>,
+    /// Set the transports to use, most preferred first (defaults to `quic,tcp`).
+    ///
+    /// If dialing a peer with one transport fails, the next transport is tried.
+    #[clap(long, value_enum, value_name = "TRANSPORT,...", value_delimiter = ',')]
+    pub transports: Option<Vec<TransportProtocol>>,
+    /// Set the port to listen on with all transports (defaults to 39811).
+    #[clap(long, value_name = "PORT")]
+    pub listen_port: Option<u16>,
     /// Generate a random subdirectory in `store_directory` to store other runtime data in.
     #[clap(short, long)]
     pub random_directory: bool,
@@ -90,6 +107,12 @@ pub struct Opts {
     /// The application heartbeat timeout in seconds (defaults to 20).
     #[clap(long, value_name = "SECONDS")]
     pub application_heartbeat_timeout: Option<u64>,
+    /// Whether other nodes can forward TCP connections to local ports of this node (defaults to `deny`).
+    #[clap(long, value_enum)]
+    pub port_forwarding: Option<PortForwardingConfig>,
+    /// Set the ports other nodes can forward TCP connections to (required if port forwarding is allowed).
+    #[clap(long, value_name = "PORT,...", value_delimiter = ',')]
+    pub forwardable_ports: Option<Vec<u16>>,
     /// Clean the store directory on startup.
     #[clap(long)]
     pub clean: bool,
@@ -113,6 +136,15 @@ pub struct Opts {
         conflicts_with("cli_socket_path")
     )]
     cli_socket_addr: Option<SocketAddr>,
+    /// Set the storage backend of the DHT records (defaults to `memory`).
+    /// The `persistent` backend keeps records in the local database across restarts.
+    #[clap(long, value_enum)]
+    kad_store: Option<KadStoreBackend>,
+    /// Set the address to serve Prometheus metrics on, at `/metrics`. Metrics are not served if not set.
+    ///
+    /// Example: `[::]:9100`
+    #[clap(long, value_name = "SOCKET_ADDRESS")]
+    metrics_addr: Option<SocketAddr>,
 }
 
 #[cfg(not(feature = "batman"))]
@@ -194,15 +226,22 @@ async fn main() -> anyhow::Result<()> {
         store_directory,
         db_file,
         key_file,
+        swarm_key_file,
+        transports,
+        listen_port,
         random_directory,
         application_management,
         application_heartbeat_timeout,
+        port_forwarding,
+        forwardable_ports,
         clean,
         log_dir,
         log_level,
         cli_socket_path,
         #[cfg(feature = "network")]
         cli_socket_addr,
+        kad_store,
+        metrics_addr,
     } = Opts::parse();
 
     let Config {
@@ -212,18 +251,51 @@ async fn main() -> anyhow::Result<()> {
         store_directory: config_store_directory,
         db_file: config_db_file,
         key_file: config_key_file,
+        swarm_key_file: config_swarm_key_file,
+        allowed_peers: config_allowed_peers,
+        denied_peers: config_denied_peers,
         random_directory: config_random_directory,
         application_management: config_application_management,
         application_heartbeat_timeout: config_application_heartbeat_timeout,
+        port_forwarding: config_port_forwarding,
+        forwardable_ports: config_forwardable_ports,
         log_dir: config_log_dir,
         log_level: config_log_level,
         cli_socket_path: config_cli_socket_path,
         #[cfg(feature = "network")]
             cli_socket_addr: config_cli_socket_addr,
         telemetry,
+        kad_store: mut config_kad_store,
+        transport: config_transport,
+        metrics_addr: config_metrics_addr,
         ..
     } = Config::load(config_file)?;
 
+    let swarm_key = if let Some(swarm_key_file) = swarm_key_file.or(config_swarm_key_file) {
+        Some(
+            tokio::fs::read_to_string(swarm_key_file)
+                .await?
+                .parse::<PreSharedKey>()?,
+        )
+    } else {
+        None
+    };
+
+    let peer_gate = PeerGate {
+        allowed: config_allowed_peers
+            .map(|peers| {
+                peers
+                    .iter()
+                    .map(|peer| peer.parse::<PeerId>())
+                    .collect::<Result<_, _>>()
+            })
+            .transpose()?,
+        blocked: config_denied_peers
+            .iter()
+            .map(|peer| peer.parse::<PeerId>())
+            .collect::<Result<_, _>>()?,
+    };
+
     let listen_addrs = if let Some(addrs) = listen_addrs.map(|e| {
         e.into_iter()
             .map(|if_addr| if_addr.to_multiaddr(true))
@@ -233,9 +305,31 @@ async fn main() -> anyhow::Result<()> {
     } else {
         fallback_listen_addrs(interfaces.or(config_interfaces)).await?
     };
+    let transport_preference = transports.unwrap_or(config_transport.preference);
+    if transport_preference.is_empty() {
+        return Err(anyhow::anyhow!("At least one transport has to be enabled"));
+    }
+    let listen_transports = if swarm_key.is_some() {
+        vec![TransportProtocol::Tcp]
+    } else {
+        transport_preference.clone()
+    };
+    let listen_port = listen_port
+        .or(config_transport.listen_port)
+        .unwrap_or(LISTEN_PORT);
     let listen_addrs = listen_addrs
         .into_iter()
-        .map(|a| a.with(Protocol::Udp(LISTEN_PORT)).with(Protocol::QuicV1))
+        .flat_map(|a| {
+            listen_transports
+                .iter()
+                .map(move |transport| match transport {
+                    TransportProtocol::Quic => a
+                        .clone()
+                        .with(Protocol::Udp(listen_port))
+                        .with(Protocol::QuicV1),
+                    TransportProtocol::Tcp => a.clone().with(Protocol::Tcp(listen_port)),
+                })
+        })
         .collect::<Vec<_>>();
     println!("Listen addresses: {listen_addrs:?}");
 
@@ -297,6 +391,20 @@ async fn main() -> anyhow::Result<()> {
             .unwrap_or(20),
     );
 
+    let port_forwarding = port_forwarding
+        .or(config_port_forwarding)
+        .unwrap_or_default();
+
+    let forwardable_ports = forwardable_ports
+        .or(config_forwardable_ports)
+        .unwrap_or_default();
+
+    if port_forwarding == PortForwardingConfig::Allow && forwardable_ports.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Port forwarding is allowed, but no forwardable ports are set"
+        ));
+    }
+
     let log_dir = log_dir.or(config_log_dir);
     let log_level = log_level.unwrap_or(config_log_level);
 
@@ -335,6 +443,10 @@ async fn main() -> anyhow::Result<()> {
             }),
     );
 
+    if let Some(backend) = kad_store {
+        config_kad_store.backend = backend;
+    }
+
     let args = RuntimeArgs {
         listen_addrs,
         #[cfg(feature = "batman")]
@@ -342,14 +454,21 @@ async fn main() -> anyhow::Result<()> {
         store_directory,
         db_file,
         keypair,
+        swarm_key,
+        transport_preference,
+        peer_gate,
         random_directory,
         apps_management,
         application_heartbeat_timeout,
+        port_forwarding,
+        forwardable_ports,
         clean,
         log_dir,
         log_level,
         cli_connection,
         telemetry,
+        kad_store: config_kad_store,
+        metrics_addr: metrics_addr.or(config_metrics_addr),
     };
 
     Runtime::new(args).await?.run().await
@@ -8,7 +8,7 @@ use tracing::instrument;
 
 pub struct Behaviour<B> {
     inner: B,
-    whitelist: Option<Multiaddr>,
+    whitelist: Vec<Multiaddr>,
 }
 
 impl<B> Deref for Behaviour<B> {
@@ -29,12 +29,17 @@ impl<B> Behaviour<B> {
     pub fn new(inner: B) -> Self {
         Behaviour {
             inner,
-            whitelist: None,
+            whitelist: Vec::new(),
         }
     }
 
-    pub fn with_whitelist(&mut self, whitelist: Multiaddr) {
-        self.whitelist = Some(whitelist);
+    /// Adds an address to the whitelist.
+    ///
+    /// Once the whitelist is not empty, only whitelisted addresses are passed to the inner behaviour.
+    pub fn with_whitelist(&mut self, addr: Multiaddr) {
+        if !self.whitelist.contains(&addr) {
+            self.whitelist.push(addr);
+        }
     }
 }
 
@@ -84,13 +89,9 @@ where
             libp2p::swarm::FromSwarm::NewListenAddr(NewListenAddr { addr, .. })
             | libp2p::swarm::FromSwarm::NewExternalAddrCandidate(NewExternalAddrCandidate {
                 addr,
-            }) => {
-                if let Some(whitelist) = &self.whitelist {
-                    if whitelist != *addr {
-                        tracing::debug!(?addr, ?whitelist, "ignoring address not in whitelist");
-                        return;
-                    }
-                }
+            }) if !self.whitelist.is_empty() && !self.whitelist.contains(*addr) => {
+                tracing::debug!(?addr, whitelist=?self.whitelist, "ignoring address not in whitelist");
+                return;
             }
             _ => {}
         }
@@ -0,0 +1,202 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    task::{Context, Poll, Waker},
+};
+
+use libp2p::{
+    core::{transport::PortUse, Endpoint},
+    swarm::{
+        dummy, CloseConnection, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour,
+        THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+    },
+    Multiaddr, PeerId,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Peer {0} is blocked")]
+    Blocked(PeerId),
+    #[error("Peer {0} is not in the allowlist")]
+    NotAllowed(PeerId),
+}
+
+/// A behaviour that refuses connections from and to peers that are not permitted.
+///
+/// A peer is permitted if it is not blocked and, if there is an allowlist, is in the allowlist.
+/// Blocking a peer closes all existing connections to it.
+/// Peers blocked or unblocked at runtime are not persisted.
+#[derive(Debug, Default)]
+pub struct Behaviour {
+    allowed: Option<HashSet<PeerId>>,
+    blocked: HashSet<PeerId>,
+    close_connections: VecDeque<PeerId>,
+    waker: Option<Waker>,
+}
+
+impl Behaviour {
+    pub fn new(
+        allowed: Option<impl IntoIterator<Item = PeerId>>,
+        blocked: impl IntoIterator<Item = PeerId>,
+    ) -> Self {
+        Self {
+            allowed: allowed.map(|allowed| allowed.into_iter().collect()),
+            blocked: blocked.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the allowlist, if there is one.
+    pub fn allowed_peers(&self) -> Option<&HashSet<PeerId>> {
+        self.allowed.as_ref()
+    }
+
+    pub fn blocked_peers(&self) -> &HashSet<PeerId> {
+        &self.blocked
+    }
+
+    /// Blocks a peer and closes all connections to it.
+    ///
+    /// Returns whether the peer was not blocked before.
+    pub fn block_peer(&mut self, peer_id: PeerId) -> bool {
+        let inserted = self.blocked.insert(peer_id);
+
+        self.close_connections.push_back(peer_id);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+
+        inserted
+    }
+
+    /// Unblocks a peer.
+    ///
+    /// Returns whether the peer was blocked before.
+    pub fn unblock_peer(&mut self, peer_id: PeerId) -> bool {
+        self.blocked.remove(&peer_id)
+    }
+
+    fn check(&self, peer_id: PeerId) -> Result<(), ConnectionDenied> {
+        if self.blocked.contains(&peer_id) {
+            tracing::debug!(peer=%peer_id, "Denying connection to blocked peer");
+            return Err(ConnectionDenied::new(Error::Blocked(peer_id)));
+        }
+
+        if self
+            .allowed
+            .as_ref()
+            .is_some_and(|allowed| !allowed.contains(&peer_id))
+        {
+            tracing::debug!(peer=%peer_id, "Denying connection to peer not in allowlist");
+            return Err(ConnectionDenied::new(Error::NotAllowed(peer_id)));
+        }
+
+        Ok(())
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type ToSwarm = void::Void;
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        _: &[Multiaddr],
+        _: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        if let Some(peer_id) = maybe_peer {
+            self.check(peer_id)?;
+        }
+
+        Ok(Vec::new())
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        peer_id: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.check(peer_id)?;
+
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        peer_id: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+        _: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.check(peer_id)?;
+
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, _: FromSwarm) {}
+
+    fn on_connection_handler_event(
+        &mut self,
+        _: PeerId,
+        _: ConnectionId,
+        _: THandlerOutEvent<Self>,
+    ) {
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        if let Some(peer_id) = self.close_connections.pop_front() {
+            return Poll::Ready(ToSwarm::CloseConnection {
+                peer_id,
+                connection: CloseConnection::All,
+            });
+        }
+
+        self.waker = Some(cx.waker().clone());
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocked_beats_allowlist() {
+        let peer = PeerId::random();
+        let gate = Behaviour::new(Some([peer]), [peer]);
+
+        assert!(gate.check(peer).is_err());
+    }
+
+    #[test]
+    fn test_allowlist_only() {
+        let allowed = PeerId::random();
+        let gate = Behaviour::new(Some([allowed]), []);
+
+        assert!(gate.check(allowed).is_ok());
+        assert!(gate.check(PeerId::random()).is_err());
+    }
+
+    #[test]
+    fn test_unblock() {
+        let peer = PeerId::random();
+        let mut gate = Behaviour::new(None::<[PeerId; 0]>, []);
+        assert!(gate.check(peer).is_ok());
+
+        assert!(gate.block_peer(peer));
+        assert!(!gate.block_peer(peer));
+        assert!(gate.check(peer).is_err());
+
+        assert!(gate.unblock_peer(peer));
+        assert!(!gate.unblock_peer(peer));
+        assert!(gate.check(peer).is_ok());
+    }
+}
@@ -1,12 +1,25 @@
-use std::{error::Error, marker::PhantomData, time::Duration};
+use std::{
+    error::Error,
+    io,
+    marker::PhantomData,
+    task::{Context, Poll},
+    time::Duration,
+};
 
-use futures::stream::StreamExt as _;
+use futures::{future, stream::StreamExt as _};
+use hyveos_core::admin::PeerGate;
 use libp2p::{
+    core::transport::TransportError,
     identity::Keypair,
     kad::Mode,
-    swarm::{NetworkBehaviour, SwarmEvent},
+    metrics::Recorder as _,
+    swarm::{
+        dial_opts::{DialOpts, PeerCondition},
+        DialError, NetworkBehaviour, SwarmEvent,
+    },
     Multiaddr, PeerId, Swarm, SwarmBuilder,
 };
+use prometheus_client::registry::Registry;
 use tokio::sync::mpsc;
 
 #[cfg(feature = "location")]
@@ -17,7 +30,9 @@ use crate::{
     behaviour::{MyBehaviour, MyBehaviourEvent},
     client::Client,
     command::Command,
-    subactors::{apps, file_transfer, gossipsub, kad, ping, req_resp, round_trip},
+    metrics::Metrics,
+    subactors::{apps, file_transfer, gate, gossipsub, kad, ping, req_resp, round_trip, streams},
+    transport::{self, Transport, TransportConfig},
 };
 
 const CHANNEL_CAP: usize = 10;
@@ -28,7 +43,7 @@ pub trait SubActor: Default {
     type Event;
     type EventError: Error;
 
-    fn new(_peer_id: PeerId) -> Self {
+    fn new(_keypair: &Keypair) -> Self {
         Default::default()
     }
 
@@ -47,6 +62,13 @@ pub trait SubActor: Default {
     ) -> Result<(), Self::EventError> {
         Ok(())
     }
+
+    /// Polls for events the subactor generates itself, e.g. from timers.
+    ///
+    /// The returned events are passed to [`SubActor::handle_event`].
+    fn poll_event(&mut self, _cx: &mut Context<'_>) -> Poll<Self::Event> {
+        Poll::Pending
+    }
 }
 
 impl SubActor for () {
@@ -68,12 +90,16 @@ pub struct Actor<
     ReqResp,
     Apps,
     FileTransfer,
+    Streams,
+    Gate,
     Debug,
     EventError,
     CommandError,
 > {
     swarm: Swarm<MyBehaviour>,
     receiver: mpsc::Receiver<Command>,
+    transports: Vec<Transport>,
+    metrics: Metrics,
     kad: Kad,
     #[cfg_attr(not(feature = "mdns"), allow(dead_code))]
     mdns: Mdns,
@@ -88,6 +114,8 @@ pub struct Actor<
     req_resp: ReqResp,
     apps: Apps,
     file_transfer: FileTransfer,
+    streams: Streams,
+    gate: Gate,
     #[cfg_attr(not(feature = "batman"), allow(dead_code))]
     debug: Debug,
     _phantom: PhantomData<EventError>,
@@ -266,6 +294,8 @@ impl<
         ReqResp,
         Apps,
         FileTransfer,
+        Streams,
+        Gate,
         Debug,
         EventError,
         CommandError,
@@ -282,6 +312,8 @@ impl<
         ReqResp,
         Apps,
         FileTransfer,
+        Streams,
+        Gate,
         Debug,
         EventError,
         CommandError,
@@ -289,7 +321,7 @@ impl<
 where
     Kad: SubActor<SubCommand = kad::Command, Event = libp2p::kad::Event>,
     Mdns: MdnsActor,
-    Gossipsub: SubActor<SubCommand = gossipsub::Command, Event = libp2p::gossipsub::Event>,
+    Gossipsub: SubActor<SubCommand = gossipsub::Command, Event = gossipsub::Event>,
     RoundTrip: SubActor<
         SubCommand = round_trip::Command,
         Event = <round_trip::Behaviour as NetworkBehaviour>::ToSwarm,
@@ -328,6 +360,18 @@ where
         CommandError = void::Void,
         EventError = void::Void,
     >,
+    Streams: SubActor<
+        SubCommand = streams::Command,
+        Event = void::Void,
+        CommandError = void::Void,
+        EventError = void::Void,
+    >,
+    Gate: SubActor<
+        SubCommand = gate::Command,
+        Event = void::Void,
+        CommandError = void::Void,
+        EventError = void::Void,
+    >,
     Debug: DebugActor,
     EventError: Error
         + From<<Kad as SubActor>::EventError>
@@ -338,11 +382,25 @@ where
         + From<<Gossipsub as SubActor>::CommandError>
         + From<<Location as SubActor>::CommandError>,
 {
-    pub fn build(keypair: Keypair) -> (Client, Self) {
-        let swarm = SwarmBuilder::with_existing_identity(keypair)
+    pub fn build(
+        keypair: Keypair,
+        kad_store: kad::StoreConfig,
+        transport_config: TransportConfig,
+        peer_gate: PeerGate,
+        registry: &mut Registry,
+    ) -> (Client, Self) {
+        if let Some(swarm_key) = &transport_config.swarm_key {
+            tracing::info!(fingerprint = %swarm_key.fingerprint(), "Using private swarm");
+        }
+        let transports = transport_config.transports();
+        tracing::info!(?transports, "Using transports");
+
+        let swarm = SwarmBuilder::with_existing_identity(keypair.clone())
             .with_tokio()
-            .with_quic()
-            .with_behaviour(MyBehaviour::new)
+            .with_other_transport(|keypair| transport::build(keypair, transport_config))
+            .expect("Failed to build transport")
+            .with_bandwidth_metrics(registry)
+            .with_behaviour(|keypair| MyBehaviour::new(keypair, kad_store, peer_gate, registry))
             .expect("Failed to build swarm")
             .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
             .build();
@@ -353,18 +411,22 @@ where
             Self {
                 swarm,
                 receiver,
-                kad: SubActor::new(peer_id),
-                mdns: SubActor::new(peer_id),
-                gossipsub: SubActor::new(peer_id),
-                round_trip: SubActor::new(peer_id),
-                location: SubActor::new(peer_id),
-                ping: SubActor::new(peer_id),
-                identify: SubActor::new(peer_id),
-                neighbours: SubActor::new(peer_id),
-                req_resp: SubActor::new(peer_id),
-                apps: SubActor::new(peer_id),
-                file_transfer: SubActor::new(peer_id),
-                debug: SubActor::new(peer_id),
+                transports,
+                metrics: Metrics::new(registry),
+                kad: SubActor::new(&keypair),
+                mdns: SubActor::new(&keypair),
+                gossipsub: SubActor::new(&keypair),
+                round_trip: SubActor::new(&keypair),
+                location: SubActor::new(&keypair),
+                ping: SubActor::new(&keypair),
+                identify: SubActor::new(&keypair),
+                neighbours: SubActor::new(&keypair),
+                req_resp: SubActor::new(&keypair),
+                apps: SubActor::new(&keypair),
+                file_transfer: SubActor::new(&keypair),
+                streams: SubActor::new(&keypair),
+                gate: SubActor::new(&keypair),
+                debug: SubActor::new(&keypair),
                 _phantom: PhantomData,
                 _command: PhantomData,
             },
@@ -376,8 +438,16 @@ where
         listen_addrs: impl Iterator<Item = Multiaddr>,
         batman_addr: Option<Multiaddr>,
     ) {
+        let listen_addrs = listen_addrs.collect::<Vec<_>>();
         if let Some(batman_addr) = batman_addr {
-            self.swarm.behaviour_mut().kad.with_whitelist(batman_addr);
+            // The batman interface is listened on with every enabled transport
+            let batman_ip = batman_addr.iter().next();
+            for addr in listen_addrs
+                .iter()
+                .filter(|addr| addr.iter().next() == batman_ip)
+            {
+                self.swarm.behaviour_mut().kad.with_whitelist(addr.clone());
+            }
         }
         self.swarm.behaviour_mut().kad.set_mode(Some(Mode::Server));
         for addr in listen_addrs {
@@ -404,6 +474,11 @@ where
                         }
                     },
                     None => break,
+                },
+                event = future::poll_fn(|cx| self.gossipsub.poll_event(cx)) => {
+                    if let Err(e) = self.gossipsub.handle_event(event, self.swarm.behaviour_mut()) {
+                        tracing::error!("Error handling gossipsub event: {e:?}");
+                    }
                 }
             }
         }
@@ -414,14 +489,24 @@ where
         &mut self,
         swarm_event: SwarmEvent<MyBehaviourEvent>,
     ) -> Result<(), EventError> {
+        self.metrics.record(&swarm_event);
+
         match swarm_event {
-            SwarmEvent::Behaviour(MyBehaviourEvent::Kad(event)) => self
-                .kad
-                .handle_event(event, self.swarm.behaviour_mut())
-                .map_err(Into::into),
-            SwarmEvent::Behaviour(MyBehaviourEvent::Gossipsub(event)) => self
+            SwarmEvent::Behaviour(MyBehaviourEvent::Kad(event)) => {
+                self.metrics.record(&event);
+                self.kad
+                    .handle_event(event, self.swarm.behaviour_mut())
+                    .map_err(Into::into)
+            }
+            SwarmEvent::Behaviour(MyBehaviourEvent::Gossipsub(event)) => {
+                self.metrics.record(&event);
+                self.gossipsub
+                    .handle_event(event.into(), self.swarm.behaviour_mut())
+                    .map_err(Into::into)
+            }
+            SwarmEvent::Behaviour(MyBehaviourEvent::GossipsubHistory(event)) => self
                 .gossipsub
-                .handle_event(event, self.swarm.behaviour_mut())
+                .handle_event(event.into(), self.swarm.behaviour_mut())
                 .map_err(Into::into),
             SwarmEvent::Behaviour(MyBehaviourEvent::RoundTrip(event)) => self
                 .round_trip
@@ -436,10 +521,12 @@ where
                 .ping
                 .handle_event(ping, self.swarm.behaviour_mut())
                 .map_err(|e| void::unreachable(e)),
-            SwarmEvent::Behaviour(MyBehaviourEvent::Identify(event)) => self
-                .identify
-                .handle_event(event, self.swarm.behaviour_mut())
-                .map_err(|e| void::unreachable(e)),
+            SwarmEvent::Behaviour(MyBehaviourEvent::Identify(event)) => {
+                self.metrics.record(&event);
+                self.identify
+                    .handle_event(event, self.swarm.behaviour_mut())
+                    .map_err(|e| void::unreachable(e))
+            }
             #[cfg(feature = "batman")]
             SwarmEvent::Behaviour(MyBehaviourEvent::BatmanNeighbours(event)) => self
                 .neighbours
@@ -462,15 +549,63 @@ where
                 .file_transfer
                 .handle_event((), self.swarm.behaviour_mut())
                 .map_err(|e| void::unreachable(e)),
+            SwarmEvent::Behaviour(MyBehaviourEvent::Gate(event)) => self
+                .gate
+                .handle_event(event, self.swarm.behaviour_mut())
+                .map_err(|e| void::unreachable(e)),
             #[cfg(feature = "batman")]
             SwarmEvent::Behaviour(MyBehaviourEvent::Debug(event)) => self
                 .debug
                 .handle_event(event, self.swarm.behaviour_mut())
                 .map_err(|e| void::unreachable(e)),
+            SwarmEvent::OutgoingConnectionError {
+                peer_id: Some(peer_id),
+                error: DialError::Transport(errors),
+                ..
+            } => {
+                self.dial_fallback(peer_id, &errors);
+                Ok(())
+            }
+            SwarmEvent::ConnectionEstablished { .. } | SwarmEvent::ConnectionClosed { .. } => {
+                self.metrics
+                    .set_connected_peers(self.swarm.connected_peers().count());
+                Ok(())
+            }
             _ => Ok(()),
         }
     }
 
+    /// Dials a peer again with the next preferred transport after dialing it failed.
+    ///
+    /// The fallback addresses use the same IP and port as the failed addresses,
+    /// so peers are expected to listen on the same port with all transports.
+    fn dial_fallback(
+        &mut self,
+        peer_id: PeerId,
+        errors: &[(Multiaddr, TransportError<io::Error>)],
+    ) {
+        let addresses = errors
+            .iter()
+            .flat_map(|(addr, _)| transport::fallback_addresses(&self.transports, addr))
+            .filter(|addr| errors.iter().all(|(failed, _)| failed != addr))
+            .collect::<Vec<_>>();
+
+        if addresses.is_empty() {
+            return;
+        }
+
+        tracing::debug!(%peer_id, ?addresses, "Dialing failed, falling back to next transport");
+
+        let opts = DialOpts::peer_id(peer_id)
+            .addresses(addresses)
+            .condition(PeerCondition::DisconnectedAndNotDialing)
+            .build();
+
+        if let Err(e) = self.swarm.dial(opts) {
+            tracing::debug!(%peer_id, "Failed to dial fallback addresses: {e}");
+        }
+    }
+
     fn handle_command(&mut self, command: Command) -> Result<(), CommandError> {
         match command {
             Command::Kad(command) => self
@@ -511,6 +646,14 @@ where
                 .file_transfer
                 .handle_command(command, self.swarm.behaviour_mut())
                 .map_err(|e| void::unreachable(e)),
+            Command::Streams(command) => self
+                .streams
+                .handle_command(command, self.swarm.behaviour_mut())
+                .map_err(|e| void::unreachable(e)),
+            Command::Gate(command) => self
+                .gate
+                .handle_command(command, self.swarm.behaviour_mut())
+                .map_err(|e| void::unreachable(e)),
             #[cfg(feature = "batman")]
             Command::Debug(command) => self
                 .debug
@@ -1,25 +1,29 @@
-use libp2p::{
-    gossipsub, identify,
-    identity::Keypair,
-    kad::{self, store::MemoryStore},
-    swarm::NetworkBehaviour,
-};
+use hyveos_core::admin::PeerGate;
+use libp2p::{gossipsub, identify, identity::Keypair, kad, swarm::NetworkBehaviour};
+use prometheus_client::registry::Registry;
 
 #[cfg(feature = "batman")]
 use crate::subactors::debug;
 #[cfg(feature = "location")]
 use crate::subactors::location;
-use crate::subactors::{apps, file_transfer, ping, req_resp, round_trip};
+use crate::subactors::{
+    apps, file_transfer, gate,
+    gossipsub::reliable,
+    kad::{Store, StoreConfig},
+    ping, req_resp, round_trip,
+};
 
 #[derive(NetworkBehaviour)]
 pub struct MyBehaviour {
+    pub gate: gate::Behaviour,
     #[cfg(feature = "batman")]
     pub batman_neighbours: hyveos_libp2p_batman_adv::Behaviour,
     pub identify: identify::Behaviour,
-    pub kad: hyveos_libp2p_addr_filter::Behaviour<kad::Behaviour<MemoryStore>>,
+    pub kad: hyveos_libp2p_addr_filter::Behaviour<kad::Behaviour<Store>>,
     #[cfg(feature = "mdns")]
     pub mdns: libp2p::mdns::tokio::Behaviour,
     pub gossipsub: gossipsub::Behaviour,
+    pub gossipsub_history: reliable::Behaviour,
     pub req_resp: req_resp::Behaviour,
     pub ping: ping::Behaviour,
     pub round_trip: round_trip::Behaviour,
@@ -32,18 +36,25 @@ pub struct MyBehaviour {
 }
 
 impl MyBehaviour {
-    pub fn new(keypair: &Keypair) -> Self {
+    pub fn new(
+        keypair: &Keypair,
+        kad_store: StoreConfig,
+        peer_gate: PeerGate,
+        registry: &mut Registry,
+    ) -> Self {
         let public = keypair.public();
         let peer_id = public.to_peer_id();
         Self {
+            gate: gate::new(peer_gate),
             #[cfg(feature = "batman")]
             batman_neighbours: hyveos_libp2p_batman_adv::Behaviour::new(
                 hyveos_libp2p_batman_adv::Config::default(),
                 peer_id,
             ),
-            kad: hyveos_libp2p_addr_filter::Behaviour::new(kad::Behaviour::new(
+            kad: hyveos_libp2p_addr_filter::Behaviour::new(kad::Behaviour::with_config(
                 peer_id,
-                MemoryStore::new(peer_id),
+                Store::new(peer_id, kad_store),
+                kad_config(),
             )),
             #[cfg(feature = "mdns")]
             mdns: libp2p::mdns::tokio::Behaviour::new(
@@ -54,11 +65,14 @@ impl MyBehaviour {
                 peer_id,
             )
             .expect("Failed to create mdns behaviour"),
-            gossipsub: gossipsub::Behaviour::new(
+            gossipsub: gossipsub::Behaviour::new_with_metrics(
                 gossipsub::MessageAuthenticity::Signed(keypair.clone()),
-                gossipsub::Config::default(),
+                gossipsub_config(),
+                registry.sub_registry_with_prefix("libp2p_gossipsub"),
+                gossipsub::MetricsConfig::default(),
             )
             .expect("Failed to create gossipsub behaviour"),
+            gossipsub_history: reliable::new(),
             identify: identify::Behaviour::new(identify::Config::new(
                 "/industries/id/1.0.0".into(),
                 public,
@@ -75,3 +89,20 @@ impl MyBehaviour {
         }
     }
 }
+
+/// Messages are only forwarded after they were validated,
+/// so that applications can register validators for topics in the gossipsub subactor.
+fn gossipsub_config() -> gossipsub::Config {
+    gossipsub::ConfigBuilder::default()
+        .validate_messages()
+        .build()
+        .expect("Failed to build gossipsub config")
+}
+
+/// Inbound records are not stored automatically,
+/// so that the kad subactor can validate owned records first.
+fn kad_config() -> kad::Config {
+    let mut config = kad::Config::new(kad::PROTOCOL_NAME);
+    config.set_record_filtering(kad::StoreInserts::FilterBoth);
+    config
+}
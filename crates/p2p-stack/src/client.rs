@@ -3,11 +3,13 @@ use std::marker::PhantomData;
 use libp2p::PeerId;
 use tokio::sync::{mpsc, oneshot};
 
+#[cfg(feature = "location")]
+use crate::subactors::location;
 #[cfg(feature = "batman")]
 use crate::subactors::{debug, neighbours};
 use crate::{
     command::{Command, RecvResult},
-    subactors::{apps, file_transfer, gossipsub, kad, ping, req_resp, round_trip},
+    subactors::{apps, file_transfer, gate, gossipsub, kad, ping, req_resp, round_trip, streams},
 };
 
 #[derive(Clone)]
@@ -59,6 +61,11 @@ impl Client {
         self.special()
     }
 
+    #[cfg(feature = "location")]
+    pub fn location(&self) -> location::Client {
+        self.special()
+    }
+
     pub fn req_resp(&self) -> req_resp::Client {
         self.special()
     }
@@ -71,6 +78,14 @@ impl Client {
         self.special()
     }
 
+    pub fn streams(&self) -> streams::Client {
+        self.special()
+    }
+
+    pub fn gate(&self) -> gate::Client {
+        self.special()
+    }
+
     #[cfg(feature = "batman")]
     pub fn debug(&self) -> debug::Client {
         self.special()
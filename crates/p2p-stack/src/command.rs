@@ -2,7 +2,9 @@ use tokio::sync::{mpsc, oneshot};
 
 #[cfg(feature = "location")]
 use crate::subactors::location;
-use crate::subactors::{apps, file_transfer, gossipsub, kad, ping, req_resp, round_trip};
+use crate::subactors::{
+    apps, file_transfer, gate, gossipsub, kad, ping, req_resp, round_trip, streams,
+};
 #[cfg(feature = "batman")]
 use crate::subactors::{debug, neighbours};
 
@@ -34,6 +36,8 @@ pub enum Command {
     ReqResp(req_resp::Command),
     Apps(apps::Command),
     FileTransfer(file_transfer::Command),
+    Streams(streams::Command),
+    Gate(gate::Command),
     #[cfg(feature = "batman")]
     Debug(debug::Command),
 }
@@ -2,7 +2,7 @@ use std::collections::HashSet;
 
 use futures::stream::StreamExt as _;
 use hyveos_core::{
-    debug::{MeshTopologyEvent, MessageDebugEvent, MessageDebugEventType},
+    debug::{MeshTopologyEvent, MessageDebugEvent, MessageDebugEventType, PendingRequest},
     neighbours,
     pub_sub::ReceivedMessage,
 };
@@ -28,6 +28,7 @@ pub enum Command {
     UnsubscribeNeighbourEvents,
     SubscribeMessageEvents(oneshot::Sender<broadcast::Receiver<MessageDebugEvent>>),
     UnsubscribeMessageEvents,
+    GetPendingRequests(oneshot::Sender<Vec<PendingRequest>>),
 }
 
 pub struct DebugClient {
@@ -115,6 +116,14 @@ impl DebugClient {
                 GossipsubMessage::SubscribeMessages(peer_id)
             }
             Command::UnsubscribeMessageEvents => GossipsubMessage::UnsubscribeMessages(peer_id),
+            Command::GetPendingRequests(sender) => {
+                // Pending requests are local, so other peers don't need to know about this
+                if let Ok(requests) = self.client.req_resp().pending_requests().await {
+                    let _ = sender.send(requests);
+                }
+
+                return;
+            }
         };
 
         let message = cbor4ii::serde::to_vec(Vec::new(), &message).unwrap();
@@ -1,4 +1,8 @@
-pub use crate::{actor::Actor, client::Client};
+pub use crate::{
+    actor::Actor,
+    client::{Client, RequestError},
+    transport::{Transport, TransportConfig},
+};
 #[cfg(feature = "batman")]
 pub use crate::{
     debug_client::{Command as DebugClientCommand, DebugClient},
@@ -9,7 +13,10 @@ mod actor;
 mod behaviour;
 mod client;
 mod command;
+mod metrics;
+mod providers;
 mod subactors;
+mod transport;
 
 #[cfg(feature = "batman")]
 mod debug_client;
@@ -18,10 +25,27 @@ pub mod file_transfer {
     pub use crate::subactors::file_transfer::ClientError;
 }
 
+pub mod streams {
+    pub use crate::subactors::streams::{AppStreamError, PortForwardError};
+}
+
+pub mod kad {
+    pub use crate::subactors::kad::{
+        verify_record, PutError, StoreBackend, StoreChange, StoreConfig, StoreTable,
+        ValidationError, VerifiedRecord, WatchError, WatchEvent,
+    };
+}
+
 pub mod apps {
     pub use crate::subactors::apps::ActorToClient;
 }
 
+pub mod req_resp {
+    pub use crate::subactors::req_resp::{
+        CallError, ResponseStreamError, StreamRequestError, MAX_REQUEST_TIMEOUT,
+    };
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum EventError {
     #[error("Kad error: {0}")]
@@ -88,6 +112,8 @@ pub type FullActor = Actor<
     subactors::req_resp::Actor,
     subactors::apps::Actor,
     subactors::file_transfer::Actor,
+    subactors::streams::Actor,
+    subactors::gate::Actor,
     DebugActor,
     EventError,
     CommandError,
@@ -0,0 +1,40 @@
+use libp2p::metrics::Recorder;
+use prometheus_client::{metrics::gauge::Gauge, registry::Registry};
+
+/// Prometheus metrics of the swarm and its behaviours.
+pub(crate) struct Metrics {
+    libp2p: libp2p::metrics::Metrics,
+    connected_peers: Gauge,
+}
+
+impl Metrics {
+    pub(crate) fn new(registry: &mut Registry) -> Self {
+        let libp2p = libp2p::metrics::Metrics::new(registry);
+
+        let connected_peers = Gauge::default();
+        registry.sub_registry_with_prefix("p2p").register(
+            "connected_peers",
+            "Number of peers with at least one established connection",
+            connected_peers.clone(),
+        );
+
+        Self {
+            libp2p,
+            connected_peers,
+        }
+    }
+
+    pub(crate) fn set_connected_peers(&self, count: usize) {
+        self.connected_peers
+            .set(i64::try_from(count).unwrap_or(i64::MAX));
+    }
+}
+
+impl<Event> Recorder<Event> for Metrics
+where
+    libp2p::metrics::Metrics: Recorder<Event>,
+{
+    fn record(&self, event: &Event) {
+        self.libp2p.record(event);
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::HashSet;
+
+use futures::StreamExt as _;
+use libp2p::{
+    kad::{GetProvidersOk, RecordKey},
+    PeerId,
+};
+use tokio::sync::mpsc;
+
+#[cfg(feature = "batman")]
+use crate::subactors::neighbours;
+use crate::{
+    client::{RequestError, SpecialClient},
+    command::Command,
+    subactors::kad,
+};
+
+/// Looks up the providers of a key and tells apart the ones that are batman neighbours.
+#[derive(Clone)]
+pub struct ProviderLookup {
+    kademlia: kad::Client,
+    #[cfg(feature = "batman")]
+    neighbours: neighbours::Client,
+}
+
+impl ProviderLookup {
+    pub fn new(sender: &mpsc::Sender<Command>, peer_id: PeerId) -> Self {
+        Self {
+            kademlia: SpecialClient::new(sender.clone(), peer_id).into(),
+            #[cfg(feature = "batman")]
+            neighbours: SpecialClient::new(sender.clone(), peer_id).into(),
+        }
+    }
+
+    #[cfg(feature = "batman")]
+    async fn get_neighbours(&self) -> Result<impl Iterator<Item = PeerId>, RequestError> {
+        Ok(self.neighbours.get_resolved().await?.into_keys())
+    }
+
+    #[cfg(not(feature = "batman"))]
+    #[cfg_attr(not(feature = "batman"), allow(clippy::unused_async))]
+    async fn get_neighbours(&self) -> Result<impl Iterator<Item = PeerId>, RequestError> {
+        Ok(std::iter::empty())
+    }
+
+    /// Returns the providers of a key, split into neighbours and other peers.
+    pub async fn get_all_providers(
+        &self,
+        key: RecordKey,
+    ) -> Result<(Vec<PeerId>, Vec<PeerId>), RequestError> {
+        let all_providers = async {
+            let mut providers = self.kademlia.get_providers(key).await?;
+            let mut ret = HashSet::new();
+            while let Some(providers) = providers.next().await {
+                match providers {
+                    Ok(GetProvidersOk::FoundProviders { providers, .. }) => {
+                        ret.extend(providers);
+                    }
+                    Err(e) => {
+                        tracing::info!(e = ?e, "Error getting providers");
+                    }
+                    _ => {}
+                }
+            }
+            Ok::<_, RequestError>(ret.into_iter().collect::<Vec<PeerId>>())
+        };
+        let neighbours =
+            async { Ok::<HashSet<PeerId>, RequestError>(self.get_neighbours().await?.collect()) };
+
+        let (all_providers, neighbours) = tokio::try_join!(all_providers, neighbours)?;
+
+        Ok(all_providers
+            .into_iter()
+            .partition(|peer| neighbours.contains(peer)))
+    }
+}
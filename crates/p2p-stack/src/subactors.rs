@@ -1,11 +1,13 @@
 pub mod apps;
 pub mod file_transfer;
+pub mod gate;
 pub mod gossipsub;
 pub mod identify;
 pub mod kad;
 pub mod ping;
 pub mod req_resp;
 pub mod round_trip;
+pub mod streams;
 
 #[cfg(feature = "batman")]
 pub mod debug;
@@ -1,5 +1,4 @@
 use std::{
-    collections::HashSet,
     io,
     path::{Path, PathBuf},
     pin::Pin,
@@ -17,7 +16,7 @@ use futures::{
 };
 use hyveos_core::file_transfer::{Cid, DownloadEvent};
 use libp2p::{
-    kad::{AddProviderError, GetProvidersOk, RecordKey},
+    kad::{AddProviderError, RecordKey},
     PeerId, StreamProtocol,
 };
 use libp2p_stream::{Behaviour, Control, OpenStreamError};
@@ -37,17 +36,17 @@ use tokio_util::compat::FuturesAsyncReadCompatExt;
 use ulid::Ulid;
 
 pub use self::provider::FileTransferProvider;
-#[cfg(feature = "batman")]
-use crate::subactors::neighbours;
 use crate::{
     actor::SubActor,
     behaviour::MyBehaviour,
     client::{RequestError, SpecialClient},
     impl_from_special_command,
+    providers::ProviderLookup,
     subactors::{file_transfer::ack::ack_reader, kad},
 };
 
 mod ack;
+mod chunked;
 mod provider;
 
 /// The top k providers to query for a file.
@@ -182,8 +181,7 @@ enum Response {
 pub struct Client {
     inner: SpecialClient<Command>,
     kademlia: kad::Client,
-    #[cfg(feature = "batman")]
-    neighbours: neighbours::Client,
+    providers: ProviderLookup,
     directory: Arc<OnceCell<PathBuf>>,
 }
 
@@ -192,8 +190,7 @@ impl From<SpecialClient<Command>> for Client {
         Self {
             kademlia: SpecialClient::new(inner.sender.clone(), inner.peer_id).into(),
             directory: Arc::new(OnceCell::new()),
-            #[cfg(feature = "batman")]
-            neighbours: SpecialClient::new(inner.sender.clone(), inner.peer_id).into(),
+            providers: ProviderLookup::new(&inner.sender, inner.peer_id),
             inner,
         }
     }
@@ -213,6 +210,8 @@ pub enum ClientError {
     Codec(#[from] CborCodecError),
     #[error("No providers found")]
     NoProviders,
+    #[error("All providers failed")]
+    ProvidersFailed,
     #[error("File download didn't finish")]
     DownloadDidNotFinish,
     #[error("Hash mismatch: expected `{expected:?}`, actual `{actual:?}`")]
@@ -328,54 +327,6 @@ impl Client {
         }
     }
 
-    #[cfg(feature = "batman")]
-    async fn get_neighbours(&self) -> Result<impl Iterator<Item = PeerId>, RequestError> {
-        Ok(self.neighbours.get_resolved().await?.into_keys())
-    }
-
-    #[cfg(not(feature = "batman"))]
-    #[cfg_attr(not(feature = "batman"), allow(clippy::unused_async))]
-    async fn get_neighbours(&self) -> Result<impl Iterator<Item = PeerId>, RequestError> {
-        Ok(std::iter::empty())
-    }
-
-    async fn get_all_providers(&self, cid: Cid) -> Result<(Vec<PeerId>, Vec<PeerId>), ClientError> {
-        let all_providers = async {
-            let mut providers = self
-                .kademlia
-                .get_providers(cid.to_key())
-                .await
-                .map_err(ClientError::Request)?;
-            let mut ret = HashSet::new();
-            while let Some(providers) = providers.next().await {
-                match providers {
-                    Ok(GetProvidersOk::FoundProviders { providers, .. }) => {
-                        ret.extend(providers);
-                    }
-                    Err(e) => {
-                        tracing::info!(e = ?e, "Error getting providers");
-                    }
-                    _ => {}
-                }
-            }
-            Ok::<_, ClientError>(ret.into_iter().collect::<Vec<PeerId>>())
-        };
-        let neighbours = async {
-            Ok::<HashSet<PeerId>, ClientError>(
-                self.get_neighbours()
-                    .await
-                    .map_err(ClientError::Request)?
-                    .collect(),
-            )
-        };
-
-        let (all_providers, neighbours) = tokio::try_join!(all_providers, neighbours)?;
-
-        Ok(all_providers
-            .into_iter()
-            .partition(|peer| neighbours.contains(peer)))
-    }
-
     async fn get_best_provider(
         &self,
         cid: Cid,
@@ -470,9 +421,38 @@ impl Client {
             return Ok(stream::once(future::ready(Ok(DownloadEvent::Ready(path)))).boxed());
         }
 
-        let (neighbours, non_neighbours) = self.get_all_providers(cid).await?;
+        let (neighbours, non_neighbours) = self
+            .providers
+            .get_all_providers(cid.to_key())
+            .await
+            .map_err(ClientError::Request)?;
 
         let control = self.get_control().await.map_err(ClientError::Request)?;
+
+        let chunked_providers = neighbours
+            .iter()
+            .chain(non_neighbours.iter().take(TOP_K))
+            .copied();
+        if let Some(download) =
+            chunked::Download::prepare(cid, chunked_providers, control.clone()).await
+        {
+            return self
+                .download_chunked(cid, download, neighbours, non_neighbours, control)
+                .await;
+        }
+
+        self.download_whole(cid, neighbours, non_neighbours, control)
+            .await
+    }
+
+    /// Downloads the file as a whole from the best provider.
+    async fn download_whole(
+        &self,
+        cid: Cid,
+        neighbours: Vec<PeerId>,
+        non_neighbours: Vec<PeerId>,
+        control: Control,
+    ) -> Result<BoxStream<'static, Result<DownloadEvent, ClientError>>, ClientError> {
         let (parts, length) = match self
             .get_best_provider(cid, neighbours.into_iter(), control.clone())
             .await
@@ -552,6 +532,90 @@ impl Client {
         Ok(UnboundedReceiverStream::new(receiver).boxed())
     }
 
+    /// Downloads the file in chunks from several providers at once.
+    ///
+    /// If the chunked download fails, the file is downloaded as a whole instead.
+    async fn download_chunked(
+        &self,
+        cid: Cid,
+        download: chunked::Download,
+        neighbours: Vec<PeerId>,
+        non_neighbours: Vec<PeerId>,
+        control: Control,
+    ) -> Result<BoxStream<'static, Result<DownloadEvent, ClientError>>, ClientError> {
+        let path = self.get_directory().await?.join(cid.to_path());
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .await?;
+        file.set_len(download.length()).await?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn({
+            let this = self.clone();
+            async move {
+                let res = async {
+                    download
+                        .run(&mut file, |progress| {
+                            let _ = sender.send(Ok(DownloadEvent::Progress(progress)));
+                        })
+                        .await?;
+
+                    let hash = chunked::hash_file(&mut file).await?;
+                    if hash != cid.hash {
+                        tracing::warn!(actual = ?hash, correct = ?cid.hash, "Hash mismatch");
+                        return Err(ClientError::HashMismatch {
+                            expected: cid.hash,
+                            actual: hash,
+                        });
+                    }
+                    file.sync_all().await?;
+                    file.shutdown().await?;
+
+                    this.provide_cid(cid).await?;
+
+                    Ok(DownloadEvent::Ready(path.clone()))
+                }
+                .await;
+
+                let e = match res {
+                    Ok(event) => {
+                        let _ = sender.send(Ok(event));
+                        return;
+                    }
+                    Err(e) => e,
+                };
+
+                // A partial file must not be served or mistaken for a finished download
+                drop(file);
+                if let Err(e) = tokio::fs::remove_file(&path).await {
+                    tracing::warn!(error = ?e, "Failed to remove partial download");
+                }
+
+                tracing::info!(error = ?e, "Chunked download failed, downloading the whole file");
+                match this
+                    .download_whole(cid, neighbours, non_neighbours, control)
+                    .await
+                {
+                    Ok(mut events) => {
+                        while let Some(event) = events.next().await {
+                            let _ = sender.send(event);
+                        }
+                    }
+                    Err(e) => {
+                        let _ = sender.send(Err(e));
+                    }
+                }
+            }
+        });
+
+        Ok(UnboundedReceiverStream::new(receiver).boxed())
+    }
+
     pub async fn list(&self) -> Result<impl Stream<Item = io::Result<Cid>>, ClientError> {
         let m = tokio::fs::read_dir(self.get_directory().await?).await?;
         Ok(ReadDirStream::new(m).filter_map(|entry| async move {
@@ -0,0 +1,664 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::SeekFrom,
+    time::{Duration, Instant},
+};
+
+use asynchronous_codec::{CborCodec, CborCodecError, Framed};
+use futures::{
+    future::BoxFuture,
+    stream::{FuturesUnordered, StreamExt as _},
+    FutureExt as _, SinkExt as _,
+};
+use hyveos_core::file_transfer::Cid;
+use libp2p::{PeerId, StreamProtocol};
+use libp2p_stream::{Control, OpenStreamError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use tokio::{
+    fs::File,
+    io::{self, AsyncRead, AsyncReadExt as _, AsyncSeekExt as _, AsyncWriteExt as _},
+};
+use tokio_util::compat::FuturesAsyncReadCompatExt as _;
+
+use super::ClientError;
+
+/// The protocol for downloading a file in chunks from several providers at once.
+///
+/// Each stream carries a single [`ChunkRequest`], so that chunks can be requested from
+/// different providers independently.
+pub(super) const CHUNKED_PROTOCOL: StreamProtocol = StreamProtocol::new("/file-transfer/0.2.0");
+
+/// The size of all chunks of a file, except for the last one.
+pub(super) const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// The largest file that is downloaded in chunks. The file is allocated up front with the length
+/// from the manifest, so the length can't be left to the providers.
+const MAX_FILE_LENGTH: u64 = 1 << 40;
+
+/// The time a provider has to send the chunk manifest before the download starts without it.
+const MANIFEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The number of chunks requested from a provider at the same time.
+const REQUESTS_PER_PROVIDER: usize = 2;
+
+/// The number of providers that fetch the same chunk at most, once no chunk is left unassigned.
+const MAX_FETCHERS_PER_CHUNK: usize = 2;
+
+/// The number of failed chunks after which a provider isn't used anymore.
+const MAX_FAILURES: usize = 3;
+
+/// The time a chunk may take at least before a provider is considered too slow.
+const MIN_CHUNK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many times slower than the average a chunk may be before a provider is considered too
+/// slow.
+const SLOW_FACTOR: u32 = 4;
+
+/// The hashes of all chunks of a file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(super) struct Manifest {
+    length: u64,
+    chunk_hashes: Vec<[u8; 32]>,
+}
+
+impl Manifest {
+    pub(super) async fn from_reader(mut reader: impl AsyncRead + Unpin) -> io::Result<Self> {
+        let mut length = 0;
+        let mut chunk_hashes = Vec::new();
+        let mut buffer = Vec::new();
+
+        loop {
+            buffer.clear();
+            let read = (&mut reader)
+                .take(CHUNK_SIZE)
+                .read_to_end(&mut buffer)
+                .await?;
+            if read == 0 {
+                break;
+            }
+
+            length += read as u64;
+            chunk_hashes.push(Sha256::digest(&buffer).into());
+        }
+
+        Ok(Self {
+            length,
+            chunk_hashes,
+        })
+    }
+
+    /// Whether the chunk hashes cover exactly the length of the file, and the file isn't too
+    /// large to allocate.
+    fn is_valid(&self) -> bool {
+        self.length <= MAX_FILE_LENGTH
+            && self.length <= self.chunk_hashes.len() as u64 * CHUNK_SIZE
+            && self.chunk_hashes.len() as u64 == self.length.div_ceil(CHUNK_SIZE)
+    }
+
+    fn chunk_length(&self, index: u64) -> u64 {
+        CHUNK_SIZE.min(self.length - index * CHUNK_SIZE)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(super) struct ManifestInfo {
+    pub(super) total_streams: u64,
+    pub(super) streams_on_cid: u64,
+    pub(super) manifest: Manifest,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub(super) enum ChunkRequest {
+    GetManifest(Cid),
+    GetChunk { cid: Cid, index: u64 },
+}
+
+/// A response to a [`ChunkRequest`].
+///
+/// A [`ChunkResponse::Chunk`] is followed by the raw bytes of the chunk.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(super) enum ChunkResponse {
+    Manifest(Option<ManifestInfo>),
+    Chunk(Option<u64>),
+}
+
+#[derive(Debug, thiserror::Error)]
+enum ChunkError {
+    #[error("Open stream error: `{0}`")]
+    OpenStream(#[from] OpenStreamError),
+    #[error("IO error: `{0}`")]
+    Io(#[from] io::Error),
+    #[error("Codec error: `{0}`")]
+    Codec(#[from] CborCodecError),
+    #[error("Chunk not available")]
+    Missing,
+    #[error("Chunk hash mismatch")]
+    HashMismatch,
+    #[error("Chunk timed out")]
+    TimedOut,
+}
+
+impl ChunkError {
+    /// Whether the provider can't be trusted to deliver any further chunks.
+    fn is_fatal(&self) -> bool {
+        matches!(self, Self::Missing | Self::HashMismatch)
+    }
+}
+
+#[derive(Default)]
+struct ProviderState {
+    in_flight: usize,
+    failures: usize,
+}
+
+impl ProviderState {
+    fn is_usable(&self) -> bool {
+        self.failures < MAX_FAILURES
+    }
+}
+
+/// Decides which provider fetches which chunk.
+///
+/// Providers are referred to by their index, chunks are handed out in order.
+struct Scheduler {
+    providers: Vec<ProviderState>,
+    pending: VecDeque<u64>,
+    /// The chunks being fetched, with the order in which they were first requested and the
+    /// providers fetching them.
+    in_flight: HashMap<u64, (u64, Vec<usize>)>,
+    requested: u64,
+    remaining: u64,
+    total_time: Duration,
+    fetched: u32,
+}
+
+impl Scheduler {
+    fn new(providers: usize, chunks: u64) -> Self {
+        Self {
+            providers: (0..providers).map(|_| ProviderState::default()).collect(),
+            pending: (0..chunks).collect(),
+            in_flight: HashMap::new(),
+            requested: 0,
+            remaining: chunks,
+            total_time: Duration::ZERO,
+            fetched: 0,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// The time a chunk may take before its provider is considered too slow.
+    fn timeout(&self) -> Duration {
+        if self.fetched == 0 {
+            MIN_CHUNK_TIMEOUT
+        } else {
+            MIN_CHUNK_TIMEOUT.max(self.total_time / self.fetched * SLOW_FACTOR)
+        }
+    }
+
+    /// Hands out chunks to all providers that have capacity left and returns them as
+    /// `(provider, chunk)` pairs.
+    ///
+    /// Returns `None` if chunks are missing, but no provider is left to fetch them.
+    fn assign(&mut self) -> Option<Vec<(usize, u64)>> {
+        let mut assignments = Vec::new();
+
+        for (provider, state) in self.providers.iter_mut().enumerate() {
+            while state.is_usable() && state.in_flight < REQUESTS_PER_PROVIDER {
+                let index = match self.pending.pop_front() {
+                    Some(index) => index,
+                    // Help with the chunk that has been in flight the longest
+                    None => {
+                        let Some(index) = self
+                            .in_flight
+                            .iter()
+                            .filter(|(_, (_, fetchers))| {
+                                fetchers.len() < MAX_FETCHERS_PER_CHUNK
+                                    && !fetchers.contains(&provider)
+                            })
+                            .min_by_key(|(_, (requested, _))| *requested)
+                            .map(|(index, _)| *index)
+                        else {
+                            break;
+                        };
+                        index
+                    }
+                };
+
+                state.in_flight += 1;
+                let requested = &mut self.requested;
+                self.in_flight
+                    .entry(index)
+                    .or_insert_with(|| {
+                        *requested += 1;
+                        (*requested, Vec::new())
+                    })
+                    .1
+                    .push(provider);
+                assignments.push((provider, index));
+            }
+        }
+
+        let is_usable = self.providers.iter().any(ProviderState::is_usable);
+        if self.in_flight.is_empty() && !self.is_done() && !is_usable {
+            return None;
+        }
+
+        Some(assignments)
+    }
+
+    /// Records that a provider fetched a chunk.
+    ///
+    /// Returns `false` if another provider was faster with the same chunk.
+    fn succeeded(&mut self, provider: usize, index: u64, elapsed: Duration) -> bool {
+        self.finished(provider, index);
+        self.total_time += elapsed;
+        self.fetched += 1;
+
+        if self.in_flight.remove(&index).is_some() {
+            self.remaining -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Records that a provider failed to fetch a chunk.
+    ///
+    /// The chunk is handed out again if no other provider is fetching it.
+    fn failed(&mut self, provider: usize, index: u64, error: &ChunkError) {
+        let fetchers = self.finished(provider, index);
+
+        let state = &mut self.providers[provider];
+        state.failures = if error.is_fatal() {
+            MAX_FAILURES
+        } else {
+            state.failures + 1
+        };
+
+        if fetchers == Some(0) {
+            self.in_flight.remove(&index);
+            self.pending.push_front(index);
+        }
+    }
+
+    /// Returns the number of providers that are still fetching the chunk, if it is in flight.
+    fn finished(&mut self, provider: usize, index: u64) -> Option<usize> {
+        self.providers[provider].in_flight -= 1;
+
+        self.in_flight.get_mut(&index).map(|(_, fetchers)| {
+            fetchers.retain(|fetcher| *fetcher != provider);
+            fetchers.len()
+        })
+    }
+}
+
+type FetchResult = (usize, u64, Duration, Result<Vec<u8>, ChunkError>);
+
+/// A download of a file in chunks from all providers that agree on its chunk hashes.
+///
+/// Chunks are handed out to providers as they finish their previous ones, so faster providers
+/// serve more chunks. Providers that time out or fail repeatedly are dropped, and their chunks
+/// are handed to the remaining ones. Once no chunk is left unassigned, idle providers fetch the
+/// chunks that are still in flight as well, so that a slow provider doesn't hold up the end.
+pub(super) struct Download {
+    cid: Cid,
+    manifest: Manifest,
+    providers: Vec<PeerId>,
+    control: Control,
+}
+
+impl Download {
+    /// Asks the providers for the chunk hashes of the file.
+    ///
+    /// Only the providers that answer within [`MANIFEST_TIMEOUT`] are used, so a provider that
+    /// doesn't answer can't stall the download. A provider that is still hashing a large file
+    /// keeps the manifest once it's done, so it can be used by later downloads.
+    ///
+    /// Returns `None` if none of the providers supports chunked downloads or has the file.
+    pub(super) async fn prepare(
+        cid: Cid,
+        providers: impl Iterator<Item = PeerId>,
+        control: Control,
+    ) -> Option<Self> {
+        let mut infos = providers
+            .map(|peer_id| {
+                let control = control.clone();
+                async move {
+                    let manifest = get_manifest(control, peer_id, cid);
+                    (
+                        peer_id,
+                        tokio::time::timeout(MANIFEST_TIMEOUT, manifest).await,
+                    )
+                }
+            })
+            .collect::<FuturesUnordered<_>>()
+            .filter_map(|(peer_id, res)| async move {
+                match res {
+                    Ok(Ok(Some(info))) if info.manifest.is_valid() => Some((peer_id, info)),
+                    Ok(Ok(_)) => None,
+                    Ok(Err(e)) => {
+                        tracing::debug!(%peer_id, error = ?e, "Error getting chunk manifest");
+                        None
+                    }
+                    Err(_) => {
+                        tracing::debug!(%peer_id, "Timed out getting chunk manifest");
+                        None
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+            .await;
+
+        // Use the manifest most providers agree on. The file hash is checked once the download
+        // finished, so a wrong manifest can't go unnoticed.
+        let manifest = infos
+            .iter()
+            .max_by_key(|(_, info)| {
+                infos
+                    .iter()
+                    .filter(|(_, other)| other.manifest == info.manifest)
+                    .count()
+            })?
+            .1
+            .manifest
+            .clone();
+
+        infos.retain(|(_, info)| info.manifest == manifest);
+        infos.sort_by_key(|(_, info)| info.total_streams + info.streams_on_cid);
+
+        Some(Self {
+            cid,
+            manifest,
+            providers: infos.into_iter().map(|(peer_id, _)| peer_id).collect(),
+            control,
+        })
+    }
+
+    pub(super) fn length(&self) -> u64 {
+        self.manifest.length
+    }
+
+    /// Downloads all chunks into the file and reports the progress in percent.
+    ///
+    /// The chunks are verified against the manifest, but the caller has to verify the hash of
+    /// the whole file.
+    pub(super) async fn run(
+        self,
+        file: &mut File,
+        mut progress: impl FnMut(u64),
+    ) -> Result<(), ClientError> {
+        let Self {
+            cid,
+            manifest,
+            providers,
+            control,
+        } = self;
+
+        tracing::debug!(%cid, providers = providers.len(), "Starting chunked download");
+
+        let mut scheduler = Scheduler::new(providers.len(), manifest.chunk_hashes.len() as u64);
+        let mut fetches = FuturesUnordered::<BoxFuture<'static, FetchResult>>::new();
+        let mut downloaded = 0;
+
+        while !scheduler.is_done() {
+            let timeout = scheduler.timeout();
+
+            let assignments = scheduler.assign().ok_or(ClientError::ProvidersFailed)?;
+            for (provider, index) in assignments {
+                let fetch = fetch_chunk(
+                    control.clone(),
+                    providers[provider],
+                    cid,
+                    index,
+                    manifest.chunk_length(index),
+                    manifest.chunk_hashes[usize::try_from(index).expect("Chunk index fits")],
+                );
+                fetches.push(
+                    async move {
+                        let started = Instant::now();
+                        let res = tokio::time::timeout(timeout, fetch)
+                            .await
+                            .unwrap_or(Err(ChunkError::TimedOut));
+                        (provider, index, started.elapsed(), res)
+                    }
+                    .boxed(),
+                );
+            }
+
+            let Some((provider, index, elapsed, res)) = fetches.next().await else {
+                return Err(ClientError::ProvidersFailed);
+            };
+
+            match res {
+                Ok(data) => {
+                    if scheduler.succeeded(provider, index, elapsed) {
+                        file.seek(SeekFrom::Start(index * CHUNK_SIZE)).await?;
+                        file.write_all(&data).await?;
+
+                        downloaded += data.len() as u64;
+                        progress(downloaded * 100 / manifest.length);
+                    }
+                }
+                Err(e) => {
+                    tracing::info!(peer_id = %providers[provider], index, error = ?e, "Failed to fetch chunk");
+                    scheduler.failed(provider, index, &e);
+                }
+            }
+        }
+
+        file.flush().await?;
+
+        Ok(())
+    }
+}
+
+async fn get_manifest(
+    mut control: Control,
+    peer_id: PeerId,
+    cid: Cid,
+) -> Result<Option<ManifestInfo>, ClientError> {
+    let stream = control.open_stream(peer_id, CHUNKED_PROTOCOL).await?;
+    let mut framed = Framed::new(stream, CborCodec::<ChunkRequest, ChunkResponse>::new());
+    framed.send(ChunkRequest::GetManifest(cid)).await?;
+
+    match framed.next().await {
+        Some(Ok(ChunkResponse::Manifest(info))) => Ok(info),
+        Some(Err(e)) => Err(e.into()),
+        _ => Ok(None),
+    }
+}
+
+async fn fetch_chunk(
+    mut control: Control,
+    peer_id: PeerId,
+    cid: Cid,
+    index: u64,
+    length: u64,
+    hash: [u8; 32],
+) -> Result<Vec<u8>, ChunkError> {
+    let stream = control.open_stream(peer_id, CHUNKED_PROTOCOL).await?;
+    let mut framed = Framed::new(stream, CborCodec::<ChunkRequest, ChunkResponse>::new());
+    framed.send(ChunkRequest::GetChunk { cid, index }).await?;
+
+    match framed.next().await {
+        Some(Ok(ChunkResponse::Chunk(Some(chunk_length)))) if chunk_length == length => {}
+        Some(Err(e)) => return Err(e.into()),
+        _ => return Err(ChunkError::Missing),
+    }
+
+    let mut parts = framed.into_parts();
+    let mut data = Vec::new();
+    parts
+        .read_buffer
+        .as_mut()
+        .chain(parts.io.compat())
+        .take(length)
+        .read_to_end(&mut data)
+        .await?;
+
+    if data.len() as u64 != length {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    if <[u8; 32]>::from(Sha256::digest(&data)) != hash {
+        return Err(ChunkError::HashMismatch);
+    }
+
+    Ok(data)
+}
+
+pub(super) async fn hash_file(file: &mut File) -> io::Result<[u8; 32]> {
+    file.seek(SeekFrom::Start(0)).await?;
+
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+
+    Ok(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(clippy::cast_possible_truncation)]
+    #[tokio::test]
+    async fn test_manifest() {
+        let data = (0..CHUNK_SIZE * 5 / 2)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+
+        let manifest = Manifest::from_reader(&data[..]).await.unwrap();
+
+        assert!(manifest.is_valid());
+        assert_eq!(manifest.length, data.len() as u64);
+        assert_eq!(manifest.chunk_hashes.len(), 3);
+        assert_eq!(manifest.chunk_length(2), CHUNK_SIZE / 2);
+        for (chunk, hash) in data.chunks(CHUNK_SIZE as usize).zip(&manifest.chunk_hashes) {
+            assert_eq!(<[u8; 32]>::from(Sha256::digest(chunk)), *hash);
+        }
+
+        let empty = Manifest::from_reader(&[][..]).await.unwrap();
+        assert!(empty.is_valid());
+        assert!(empty.chunk_hashes.is_empty());
+
+        let truncated = Manifest {
+            length: CHUNK_SIZE * 3 + 1,
+            chunk_hashes: manifest.chunk_hashes.clone(),
+        };
+        assert!(!truncated.is_valid());
+
+        let too_large = Manifest {
+            length: MAX_FILE_LENGTH + 1,
+            chunk_hashes: vec![
+                [0; 32];
+                usize::try_from((MAX_FILE_LENGTH + 1).div_ceil(CHUNK_SIZE)).unwrap()
+            ],
+        };
+        assert!(!too_large.is_valid());
+    }
+
+    #[test]
+    fn test_scheduler_rebalances_to_faster_providers() {
+        let mut scheduler = Scheduler::new(2, 10);
+
+        assert_eq!(
+            scheduler.assign().unwrap(),
+            [(0, 0), (0, 1), (1, 2), (1, 3)]
+        );
+
+        // The first provider finishes its chunks while the second one is still busy
+        assert!(scheduler.succeeded(0, 0, Duration::from_millis(10)));
+        assert!(scheduler.succeeded(0, 1, Duration::from_millis(10)));
+        assert_eq!(scheduler.assign().unwrap(), [(0, 4), (0, 5)]);
+
+        assert!(scheduler.succeeded(0, 4, Duration::from_millis(10)));
+        assert_eq!(scheduler.assign().unwrap(), [(0, 6)]);
+    }
+
+    #[test]
+    fn test_scheduler_fetches_tail_twice() {
+        let mut scheduler = Scheduler::new(2, 3);
+
+        // Once all chunks are assigned, the oldest chunk in flight is fetched by another provider
+        assert_eq!(
+            scheduler.assign().unwrap(),
+            [(0, 0), (0, 1), (1, 2), (1, 0)]
+        );
+
+        assert!(scheduler.succeeded(1, 0, Duration::from_millis(10)));
+        assert!(!scheduler.succeeded(0, 0, Duration::from_millis(10)));
+
+        // Each chunk is fetched by at most two providers, never twice by the same one
+        assert_eq!(scheduler.assign().unwrap(), [(0, 2), (1, 1)]);
+
+        assert!(scheduler.succeeded(0, 1, Duration::from_millis(10)));
+        assert!(scheduler.succeeded(1, 2, Duration::from_millis(10)));
+        assert!(!scheduler.succeeded(0, 2, Duration::from_millis(10)));
+        assert!(!scheduler.succeeded(1, 1, Duration::from_millis(10)));
+        assert!(scheduler.is_done());
+    }
+
+    #[test]
+    fn test_scheduler_drops_failing_providers() {
+        let mut scheduler = Scheduler::new(2, 10);
+        assert_eq!(
+            scheduler.assign().unwrap(),
+            [(0, 0), (0, 1), (1, 2), (1, 3)]
+        );
+
+        // Timeouts are tolerated until a provider reaches the maximum number of failures
+        scheduler.failed(0, 0, &ChunkError::TimedOut);
+        for _ in 1..MAX_FAILURES {
+            assert_eq!(scheduler.assign().unwrap(), [(0, 0)]);
+            scheduler.failed(0, 0, &ChunkError::TimedOut);
+        }
+        assert!(!scheduler.providers[0].is_usable());
+        assert_eq!(scheduler.assign().unwrap(), []);
+
+        // A wrong chunk drops the provider right away
+        scheduler.failed(1, 2, &ChunkError::HashMismatch);
+        assert!(!scheduler.providers[1].is_usable());
+
+        // The chunks of dropped providers are handed out again
+        assert_eq!(
+            scheduler
+                .pending
+                .iter()
+                .take(2)
+                .copied()
+                .collect::<Vec<_>>(),
+            [2, 0]
+        );
+    }
+
+    #[test]
+    fn test_scheduler_fails_without_providers() {
+        let mut scheduler = Scheduler::new(2, 3);
+        assert_eq!(
+            scheduler.assign().unwrap(),
+            [(0, 0), (0, 1), (1, 2), (1, 0)]
+        );
+
+        scheduler.failed(0, 0, &ChunkError::Missing);
+        // The other provider is still fetching the chunk
+        assert_eq!(scheduler.pending.len(), 0);
+        scheduler.failed(0, 1, &ChunkError::Missing);
+        assert_eq!(scheduler.assign().unwrap(), []);
+
+        scheduler.failed(1, 2, &ChunkError::HashMismatch);
+        scheduler.failed(1, 0, &ChunkError::HashMismatch);
+
+        assert_eq!(scheduler.assign(), None);
+    }
+}
@@ -1,21 +1,30 @@
 use std::{
-    io,
+    io::{self, SeekFrom},
     path::{Path, PathBuf},
     sync::{atomic::AtomicU64, Arc},
+    time::SystemTime,
 };
 
 use asynchronous_codec::{CborCodec, Framed};
-use futures::{sink::SinkExt as _, stream::StreamExt as _};
+use futures::{
+    sink::SinkExt as _,
+    stream::{self, StreamExt as _},
+};
 use hyveos_core::file_transfer::Cid;
 use libp2p::Stream;
 use libp2p_stream::Control;
 use tokio::{
     fs::{try_exists, File},
-    io::{split, AsyncReadExt as _},
+    io::{split, AsyncReadExt as _, AsyncSeekExt as _, AsyncWriteExt as _},
 };
 use tokio_util::compat::FuturesAsyncReadCompatExt as _;
 
-use super::{ack::ack_writer, bar_style, CidExt as _, ExistenceInfo, STREAM_PROTOCOL};
+use super::{
+    ack::ack_writer,
+    bar_style,
+    chunked::{ChunkRequest, ChunkResponse, Manifest, ManifestInfo, CHUNKED_PROTOCOL, CHUNK_SIZE},
+    CidExt as _, ExistenceInfo, STREAM_PROTOCOL,
+};
 use crate::subactors::file_transfer::{Request, Response};
 
 pub struct FileTransferProvider {
@@ -36,25 +45,41 @@ impl FileTransferProvider {
     }
 
     pub async fn run(mut self) {
-        let mut streams = self
+        let whole_streams = self
             .control
             .accept(STREAM_PROTOCOL)
             .expect("Already registered stream (likely two file transfer providers)");
+        let chunked_streams = self
+            .control
+            .accept(CHUNKED_PROTOCOL)
+            .expect("Already registered stream (likely two file transfer providers)");
+
+        let mut streams = stream::select(
+            whole_streams.map(|(_peer_id, stream)| (stream, false)),
+            chunked_streams.map(|(_peer_id, stream)| (stream, true)),
+        );
 
         let stream_handler = Arc::new(StreamHandler {
             directory: self.directory,
             total_streams: self.total_streams,
             streams_per_cid: self.streams_per_cid,
+            manifests: dashmap::DashMap::new(),
         });
 
-        while let Some((_peer_id, stream)) = streams.next().await {
+        while let Some((stream, chunked)) = streams.next().await {
             let local_stream_handler = Arc::clone(&stream_handler);
             tokio::spawn(async move {
                 let mut cid = None;
-                if let Err(e) = local_stream_handler
-                    .handle_stream_inner(stream, &mut cid)
-                    .await
-                {
+                let res = if chunked {
+                    local_stream_handler
+                        .handle_chunked_stream_inner(stream, &mut cid)
+                        .await
+                } else {
+                    local_stream_handler
+                        .handle_stream_inner(stream, &mut cid)
+                        .await
+                };
+                if let Err(e) = res {
                     tracing::trace!(error = ?e, "Error handling stream");
                 }
                 if let Some(cid) = cid {
@@ -85,6 +110,8 @@ struct StreamHandler {
     directory: PathBuf,
     total_streams: AtomicU64,
     streams_per_cid: dashmap::DashMap<Cid, usize>,
+    /// The chunk manifests of files, together with the modification time they were computed at.
+    manifests: dashmap::DashMap<Cid, (SystemTime, Manifest)>,
 }
 
 impl StreamHandler {
@@ -175,4 +202,88 @@ impl StreamHandler {
             ))?,
         }
     }
+
+    async fn handle_chunked_stream_inner(
+        &self,
+        stream: Stream,
+        streaming_cid: &mut Option<Cid>,
+    ) -> anyhow::Result<()> {
+        let mut framed = Framed::new(stream, CborCodec::<ChunkResponse, ChunkRequest>::new());
+        let request = match framed.next().await {
+            Some(Ok(request)) => request,
+            Some(Err(e)) => Err(e)?,
+            None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "No request"))?,
+        };
+
+        match request {
+            ChunkRequest::GetManifest(cid) => {
+                let info = match get_file(&self.directory, cid).await? {
+                    Some(file) => Some(ManifestInfo {
+                        total_streams: self
+                            .total_streams
+                            .load(std::sync::atomic::Ordering::Relaxed),
+                        streams_on_cid: self.streams_per_cid.get(&cid).map_or(0, |e| *e) as u64,
+                        manifest: self.get_manifest(cid, file).await?,
+                    }),
+                    None => None,
+                };
+
+                framed.send(ChunkResponse::Manifest(info)).await?;
+                framed.close().await?;
+            }
+            ChunkRequest::GetChunk { cid, index } => {
+                let Some(mut file) = get_file(&self.directory, cid).await? else {
+                    framed.send(ChunkResponse::Chunk(None)).await?;
+                    return Ok(());
+                };
+
+                let length = file.metadata().await?.len();
+                let offset = index.saturating_mul(CHUNK_SIZE);
+                if offset >= length {
+                    framed.send(ChunkResponse::Chunk(None)).await?;
+                    return Ok(());
+                }
+                let chunk_length = CHUNK_SIZE.min(length - offset);
+
+                *streaming_cid = Some(cid);
+
+                self.total_streams
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                self.streams_per_cid
+                    .entry(cid)
+                    .and_modify(|e| *e += 1)
+                    .or_insert(1);
+
+                file.seek(SeekFrom::Start(offset)).await?;
+                framed
+                    .send(ChunkResponse::Chunk(Some(chunk_length)))
+                    .await?;
+
+                let mut framed_parts = framed.into_parts();
+                let mut file = framed_parts
+                    .write_buffer
+                    .as_mut()
+                    .chain(file.take(chunk_length));
+                let mut writer = (&mut framed_parts.io).compat();
+                tokio::io::copy(&mut file, &mut writer).await?;
+                writer.shutdown().await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_manifest(&self, cid: Cid, file: File) -> io::Result<Manifest> {
+        let modified = file.metadata().await?.modified()?;
+        if let Some(entry) = self.manifests.get(&cid) {
+            let (computed, manifest) = entry.value();
+            if *computed == modified {
+                return Ok(manifest.clone());
+            }
+        }
+
+        let manifest = Manifest::from_reader(file).await?;
+        self.manifests.insert(cid, (modified, manifest.clone()));
+        Ok(manifest)
+    }
 }
@@ -0,0 +1,115 @@
+use hyveos_core::admin::PeerGate;
+use libp2p::PeerId;
+use tokio::sync::oneshot;
+
+use crate::{
+    actor::SubActor,
+    behaviour::MyBehaviour,
+    client::{RequestError, SpecialClient},
+    impl_from_special_command,
+};
+
+pub type Behaviour = hyveos_libp2p_peer_gate::Behaviour;
+
+pub fn new(peer_gate: PeerGate) -> Behaviour {
+    Behaviour::new(peer_gate.allowed, peer_gate.blocked)
+}
+
+#[derive(Debug)]
+pub enum Command {
+    BlockPeer {
+        peer_id: PeerId,
+        sender: oneshot::Sender<bool>,
+    },
+    UnblockPeer {
+        peer_id: PeerId,
+        sender: oneshot::Sender<bool>,
+    },
+    GetPeerGate(oneshot::Sender<PeerGate>),
+}
+
+impl_from_special_command!(Gate);
+
+#[derive(Debug, Default)]
+pub struct Actor {}
+
+impl SubActor for Actor {
+    type SubCommand = Command;
+    type Event = void::Void;
+    type EventError = void::Void;
+    type CommandError = void::Void;
+
+    fn handle_command(
+        &mut self,
+        command: Self::SubCommand,
+        behaviour: &mut MyBehaviour,
+    ) -> Result<(), Self::CommandError> {
+        match command {
+            Command::BlockPeer { peer_id, sender } => {
+                tracing::info!(peer=%peer_id, "Blocking peer");
+                let _ = sender.send(behaviour.gate.block_peer(peer_id));
+            }
+            Command::UnblockPeer { peer_id, sender } => {
+                tracing::info!(peer=%peer_id, "Unblocking peer");
+                let _ = sender.send(behaviour.gate.unblock_peer(peer_id));
+            }
+            Command::GetPeerGate(sender) => {
+                let peer_gate = PeerGate {
+                    allowed: behaviour
+                        .gate
+                        .allowed_peers()
+                        .map(|allowed| allowed.iter().copied().collect()),
+                    blocked: behaviour.gate.blocked_peers().iter().copied().collect(),
+                };
+                let _ = sender.send(peer_gate);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct Client {
+    inner: SpecialClient<Command>,
+}
+
+impl From<SpecialClient<Command>> for Client {
+    fn from(inner: SpecialClient<Command>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Client {
+    /// Blocks a peer and closes all connections to it.
+    ///
+    /// Returns whether the peer was not blocked before.
+    pub async fn block_peer(&self, peer_id: PeerId) -> Result<bool, RequestError> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .send(Command::BlockPeer { peer_id, sender })
+            .await
+            .map_err(RequestError::Send)?;
+        receiver.await.map_err(RequestError::Oneshot)
+    }
+
+    /// Unblocks a peer.
+    ///
+    /// Returns whether the peer was blocked before.
+    pub async fn unblock_peer(&self, peer_id: PeerId) -> Result<bool, RequestError> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .send(Command::UnblockPeer { peer_id, sender })
+            .await
+            .map_err(RequestError::Send)?;
+        receiver.await.map_err(RequestError::Oneshot)
+    }
+
+    pub async fn get_peer_gate(&self) -> Result<PeerGate, RequestError> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .send(Command::GetPeerGate(sender))
+            .await
+            .map_err(RequestError::Send)?;
+        receiver.await.map_err(RequestError::Oneshot)
+    }
+}
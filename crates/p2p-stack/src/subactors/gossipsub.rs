@@ -1,7 +1,7 @@
 #[cfg(feature = "batman")]
 pub use self::client::TopicHandle;
 pub use self::{
-    actor::{Actor, CommandError, EventError},
+    actor::{Actor, CommandError, EventError, ValidatorError},
     client::Client,
     command::Command,
 };
@@ -9,3 +9,23 @@ pub use self::{
 mod actor;
 mod client;
 mod command;
+pub mod reliable;
+
+pub enum Event {
+    Gossipsub(libp2p::gossipsub::Event),
+    History(reliable::Event),
+    /// The validator of the message didn't report a result in time.
+    ValidationExpired(hyveos_core::pub_sub::MessageId),
+}
+
+impl From<libp2p::gossipsub::Event> for Event {
+    fn from(event: libp2p::gossipsub::Event) -> Self {
+        Self::Gossipsub(event)
+    }
+}
+
+impl From<reliable::Event> for Event {
+    fn from(event: reliable::Event) -> Self {
+        Self::History(event)
+    }
+}
@@ -1,20 +1,66 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    task::{Context, Poll},
+    time::Duration,
+};
 
 use hyveos_core::{
     debug::MessageDebugEventType,
-    pub_sub::{Message, MessageId, ReceivedMessage},
+    pub_sub::{Message, MessageAcceptance, MessageId, ReceivedMessage, ReliableEvent, TopicPeers},
+    req_resp::TopicQuery,
+};
+use libp2p::gossipsub::{
+    self, Behaviour, Event, IdentTopic, PublishError, SubscriptionError, TopicHash,
 };
-use libp2p::gossipsub::{Behaviour, Event, IdentTopic, PublishError, SubscriptionError, TopicHash};
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc};
+use tokio_util::time::{delay_queue, DelayQueue};
 
-use super::Command;
+use super::{
+    reliable::{self, Reliable},
+    Command,
+};
 use crate::{actor::SubActor, behaviour::MyBehaviour};
 
 const CHANNEL_CAP: usize = 10;
+const VALIDATOR_CHANNEL_CAP: usize = 64;
+/// Messages that aren't validated in time are ignored. Gossipsub only keeps messages around for
+/// validation for a few heartbeats anyway.
+const VALIDATION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A subscription to all topics that match a query.
+#[derive(Debug)]
+struct QuerySubscription {
+    /// Only topics with this prefix are matched, the query is matched against the rest.
+    prefix: String,
+    query: TopicQuery,
+    sender: broadcast::Sender<ReceivedMessage>,
+}
+
+impl QuerySubscription {
+    fn matches(&self, topic_hash: &TopicHash) -> bool {
+        topic_hash
+            .as_str()
+            .strip_prefix(self.prefix.as_str())
+            .is_some_and(|topic| self.query.matches(topic))
+    }
+}
+
+/// A received message that is waiting for the result of its topic's validator.
+#[derive(Debug)]
+struct PendingValidation {
+    topic_hash: TopicHash,
+    message: ReceivedMessage,
+    deadline: delay_queue::Key,
+}
 
 #[derive(Debug, Default)]
 pub struct Actor {
     topic_subscriptions: HashMap<TopicHash, (IdentTopic, broadcast::Sender<ReceivedMessage>)>,
+    query_subscriptions: Vec<QuerySubscription>,
+    validators: HashMap<TopicHash, mpsc::Sender<ReceivedMessage>>,
+    pending_validations: HashMap<MessageId, PendingValidation>,
+    validation_deadlines: DelayQueue<MessageId>,
+    reliable: Reliable,
     debug_sender: Option<broadcast::Sender<MessageDebugEventType>>,
 }
 
@@ -28,12 +74,28 @@ pub enum CommandError {
     MessageIdFailed(Result<MessageId, PublishError>),
     #[error("Send subscription failed: `{0:?}`")]
     SubscriptionFailed(Result<broadcast::Receiver<ReceivedMessage>, SubscriptionError>),
+    #[error("Send reliable subscription failed: `{0:?}`")]
+    ReliableSubscriptionFailed(Result<broadcast::Receiver<ReliableEvent>, SubscriptionError>),
+    #[error("Send validator failed: `{0:?}`")]
+    ValidatorFailed(Result<mpsc::Receiver<ReceivedMessage>, ValidatorError>),
+    #[error("Send validation result failed")]
+    ValidationResultFailed,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ValidatorError {
+    #[error("Subscription error: {0}")]
+    Subscription(#[from] SubscriptionError),
+    #[error("A validator is already registered for this topic")]
+    AlreadyRegistered,
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum EventError {
     #[error("Publish error: {0}")]
     Publish(#[from] PublishError),
+    #[error("Subscription error: {0}")]
+    Subscription(#[from] SubscriptionError),
     #[error("Message without topic: `{0}`")]
     MessageWithoutTopic(TopicHash),
     #[error("Broadcast error: `{0}`")]
@@ -43,7 +105,7 @@ pub enum EventError {
 impl SubActor for Actor {
     type SubCommand = Command;
     type CommandError = CommandError;
-    type Event = Event;
+    type Event = super::Event;
     type EventError = EventError;
 
     fn handle_command(
@@ -51,7 +113,7 @@ impl SubActor for Actor {
         command: Self::SubCommand,
         behaviour: &mut MyBehaviour,
     ) -> Result<(), Self::CommandError> {
-        self.garbage_collect(&mut behaviour.gossipsub)?;
+        self.garbage_collect(&mut behaviour.gossipsub);
         match command {
             Command::PublishMessage {
                 topic,
@@ -86,6 +148,57 @@ impl SubActor for Actor {
             } => send_subscription
                 .send(self.get_sub(&mut behaviour.gossipsub, topic))
                 .map_err(CommandError::SubscriptionFailed),
+            Command::SubscribeQuery {
+                prefix,
+                query,
+                send_subscription,
+            } => send_subscription
+                .send(self.subscribe_query(&mut behaviour.gossipsub, prefix, query))
+                .map_err(CommandError::SubscriptionFailed),
+            Command::PublishReliable {
+                topic,
+                data,
+                send_message_id,
+            } => send_message_id
+                .send(self.reliable.publish(&mut behaviour.gossipsub, topic, data))
+                .map_err(CommandError::MessageIdFailed),
+            Command::SubscribeReliable {
+                topic,
+                send_subscription,
+            } => send_subscription
+                .send(self.subscribe_reliable(&mut behaviour.gossipsub, topic))
+                .map_err(CommandError::ReliableSubscriptionFailed),
+            Command::Validate {
+                topic,
+                send_validator,
+            } => send_validator
+                .send(self.register_validator(&mut behaviour.gossipsub, topic))
+                .map_err(CommandError::ValidatorFailed),
+            Command::ReportValidationResult {
+                message_id,
+                acceptance,
+                send_found,
+            } => {
+                let found = self.report_validation_result(behaviour, &message_id, acceptance)?;
+                send_found
+                    .send(found)
+                    .map_err(|_| CommandError::ValidationResultFailed)
+            }
+            Command::ListTopics(sender) => {
+                let _ = sender.send(behaviour.gossipsub.topics().cloned().collect());
+                Ok(())
+            }
+            Command::TopicPeers { topic, send_peers } => {
+                let subscribers = behaviour
+                    .gossipsub
+                    .all_peers()
+                    .filter(|(_, topics)| topics.contains(&&topic))
+                    .map(|(peer_id, _)| *peer_id)
+                    .collect();
+                let mesh = behaviour.gossipsub.mesh_peers(&topic).copied().collect();
+                let _ = send_peers.send(TopicPeers { subscribers, mesh });
+                Ok(())
+            }
             Command::DebugSubscribe(sender) => {
                 let receiver = self
                     .debug_sender
@@ -102,13 +215,50 @@ impl SubActor for Actor {
         event: Self::Event,
         behaviour: &mut MyBehaviour,
     ) -> Result<(), Self::EventError> {
+        match event {
+            super::Event::Gossipsub(event) => self.handle_gossipsub_event(event, behaviour),
+            super::Event::History(event) => {
+                self.reliable.handle_event(
+                    event,
+                    &behaviour.gossipsub,
+                    &mut behaviour.gossipsub_history,
+                );
+                Ok(())
+            }
+            super::Event::ValidationExpired(message_id) => {
+                if let Some(pending) = self.pending_validations.remove(&message_id) {
+                    tracing::debug!(topic = %pending.topic_hash, "Validation timed out");
+                    Self::report_ignored(&mut behaviour.gossipsub, &pending.message);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<Self::Event> {
+        match self.validation_deadlines.poll_expired(cx) {
+            Poll::Ready(Some(expired)) => {
+                Poll::Ready(super::Event::ValidationExpired(expired.into_inner()))
+            }
+            // An empty queue is polled again after the next command or event
+            Poll::Ready(None) | Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Actor {
+    fn handle_gossipsub_event(
+        &mut self,
+        event: Event,
+        behaviour: &mut MyBehaviour,
+    ) -> Result<(), EventError> {
         match event {
             Event::Message {
                 propagation_source,
                 message_id,
                 message,
             } => {
-                self.garbage_collect(&mut behaviour.gossipsub)?;
+                self.garbage_collect(&mut behaviour.gossipsub);
                 let topic_hash = message.topic.clone();
                 let received_message = ReceivedMessage {
                     propagation_source,
@@ -119,23 +269,242 @@ impl SubActor for Actor {
                         topic: message.topic.into_string(),
                     },
                 };
-                self.topic_subscriptions.get(&topic_hash).map_or_else(
-                    || Err(EventError::MessageWithoutTopic(topic_hash)),
-                    |(_, sender)| {
-                        sender
-                            .send(received_message)
-                            .map_err(Box::new)
-                            .map_err(EventError::Broadcast)
-                            .map(|_| ())
-                    },
+
+                if let Some(validator) = self.validators.get(&topic_hash) {
+                    match validator.try_send(received_message.clone()) {
+                        Ok(()) => {
+                            let message_id = received_message.message_id.clone();
+                            let deadline = self
+                                .validation_deadlines
+                                .insert(message_id.clone(), VALIDATION_TIMEOUT);
+                            self.pending_validations.insert(
+                                message_id,
+                                PendingValidation {
+                                    topic_hash,
+                                    message: received_message,
+                                    deadline,
+                                },
+                            );
+                            return Ok(());
+                        }
+                        Err(mpsc::error::TrySendError::Full(_)) => {
+                            tracing::warn!(
+                                topic=%topic_hash,
+                                "Validator is lagging behind, ignoring message"
+                            );
+                            Self::report(
+                                &mut behaviour.gossipsub,
+                                &received_message,
+                                MessageAcceptance::Ignore,
+                            )?;
+                            return Ok(());
+                        }
+                        Err(mpsc::error::TrySendError::Closed(_)) => {
+                            self.validators.remove(&topic_hash);
+                        }
+                    }
+                }
+
+                Self::report(
+                    &mut behaviour.gossipsub,
+                    &received_message,
+                    MessageAcceptance::Accept,
+                )?;
+                self.deliver(
+                    &mut behaviour.gossipsub_history,
+                    topic_hash,
+                    received_message,
                 )
             }
+            Event::Subscribed { topic, .. } => {
+                self.garbage_collect(&mut behaviour.gossipsub);
+                if self
+                    .query_subscriptions
+                    .iter()
+                    .any(|subscription| subscription.matches(&topic))
+                {
+                    self.join_topic(&mut behaviour.gossipsub, topic)?;
+                }
+                Ok(())
+            }
             _ => Ok(()),
         }
     }
-}
 
-impl Actor {
+    fn deliver(
+        &mut self,
+        history: &mut reliable::Behaviour,
+        topic_hash: TopicHash,
+        received_message: ReceivedMessage,
+    ) -> Result<(), EventError> {
+        let Some((_, sender)) = self.topic_subscriptions.get(&topic_hash) else {
+            return Err(EventError::MessageWithoutTopic(topic_hash));
+        };
+
+        if self.reliable.is_subscribed(&topic_hash) {
+            self.reliable
+                .receive(history, &topic_hash, received_message.clone());
+        }
+
+        for subscription in &self.query_subscriptions {
+            if subscription.matches(&topic_hash) {
+                // Query subscriptions without receivers are removed in `garbage_collect`
+                let _ = subscription.sender.send(received_message.clone());
+            }
+        }
+
+        // A validated, queried or reliable topic can be subscribed to without any exact subscribers
+        if sender.receiver_count() == 0 {
+            return Ok(());
+        }
+
+        sender
+            .send(received_message)
+            .map_err(Box::new)
+            .map_err(EventError::Broadcast)
+            .map(|_| ())
+    }
+
+    fn report(
+        behaviour: &mut Behaviour,
+        received_message: &ReceivedMessage,
+        acceptance: MessageAcceptance,
+    ) -> Result<bool, PublishError> {
+        let acceptance = match acceptance {
+            MessageAcceptance::Accept => gossipsub::MessageAcceptance::Accept,
+            MessageAcceptance::Reject => gossipsub::MessageAcceptance::Reject,
+            MessageAcceptance::Ignore => gossipsub::MessageAcceptance::Ignore,
+        };
+
+        behaviour.report_message_validation_result(
+            &gossipsub::MessageId(received_message.message_id.0.clone()),
+            &received_message.propagation_source,
+            acceptance,
+        )
+    }
+
+    /// Drops a message without penalising anyone.
+    fn report_ignored(behaviour: &mut Behaviour, received_message: &ReceivedMessage) {
+        if let Err(e) = Self::report(behaviour, received_message, MessageAcceptance::Ignore) {
+            tracing::warn!(error = %e, "Failed to report ignored message");
+        }
+    }
+
+    /// Subscribes to all topics that start with `prefix` and match `query` on the rest.
+    ///
+    /// Topics are learned from the subscriptions of other peers.
+    fn subscribe_query(
+        &mut self,
+        behaviour: &mut Behaviour,
+        prefix: String,
+        query: TopicQuery,
+    ) -> Result<broadcast::Receiver<ReceivedMessage>, SubscriptionError> {
+        let (sender, receiver) = broadcast::channel(CHANNEL_CAP);
+        let subscription = QuerySubscription {
+            prefix,
+            query,
+            sender,
+        };
+
+        let known_topics = behaviour
+            .all_peers()
+            .flat_map(|(_, topics)| topics)
+            .filter(|topic_hash| subscription.matches(topic_hash))
+            .cloned()
+            .collect::<HashSet<_>>();
+
+        self.query_subscriptions.push(subscription);
+
+        for topic_hash in known_topics {
+            self.join_topic(behaviour, topic_hash)?;
+        }
+
+        Ok(receiver)
+    }
+
+    /// Subscribes to a topic on the behaviour without creating a local subscriber.
+    ///
+    /// The subscription is kept alive by validators and query subscriptions,
+    /// see `garbage_collect`.
+    fn join_topic(
+        &mut self,
+        behaviour: &mut Behaviour,
+        topic_hash: TopicHash,
+    ) -> Result<(), SubscriptionError> {
+        if !self.topic_subscriptions.contains_key(&topic_hash) {
+            drop(self.get_sub(behaviour, IdentTopic::new(topic_hash.into_string()))?);
+        }
+        Ok(())
+    }
+
+    /// Subscribes to a reliable topic.
+    ///
+    /// The subscription on the behaviour is kept alive by the reliable subscription,
+    /// see `garbage_collect`.
+    fn subscribe_reliable(
+        &mut self,
+        behaviour: &mut Behaviour,
+        topic: IdentTopic,
+    ) -> Result<broadcast::Receiver<ReliableEvent>, SubscriptionError> {
+        let topic_hash = topic.hash();
+        self.join_topic(behaviour, topic_hash.clone())?;
+
+        Ok(self.reliable.subscribe(topic_hash))
+    }
+
+    fn register_validator(
+        &mut self,
+        behaviour: &mut Behaviour,
+        topic: IdentTopic,
+    ) -> Result<mpsc::Receiver<ReceivedMessage>, ValidatorError> {
+        let topic_hash = topic.hash();
+        if self
+            .validators
+            .get(&topic_hash)
+            .is_some_and(|validator| !validator.is_closed())
+        {
+            return Err(ValidatorError::AlreadyRegistered);
+        }
+
+        self.join_topic(behaviour, topic_hash.clone())?;
+
+        let (sender, receiver) = mpsc::channel(VALIDATOR_CHANNEL_CAP);
+        self.validators.insert(topic_hash, sender);
+
+        Ok(receiver)
+    }
+
+    fn report_validation_result(
+        &mut self,
+        behaviour: &mut MyBehaviour,
+        message_id: &MessageId,
+        acceptance: MessageAcceptance,
+    ) -> Result<bool, CommandError> {
+        let Some(PendingValidation {
+            topic_hash,
+            message: received_message,
+            deadline,
+        }) = self.pending_validations.remove(message_id)
+        else {
+            return Ok(false);
+        };
+        self.validation_deadlines.try_remove(&deadline);
+
+        Self::report(&mut behaviour.gossipsub, &received_message, acceptance)?;
+
+        if acceptance == MessageAcceptance::Accept {
+            if let Err(e) = self.deliver(
+                &mut behaviour.gossipsub_history,
+                topic_hash,
+                received_message,
+            ) {
+                tracing::warn!("Failed to deliver validated message: {e}");
+            }
+        }
+
+        Ok(true)
+    }
+
     pub fn get_sub(
         &mut self,
         behaviour: &mut Behaviour,
@@ -158,9 +527,43 @@ impl Actor {
         Ok(receiver)
     }
 
-    pub fn garbage_collect(&mut self, behaviour: &mut Behaviour) -> Result<(), PublishError> {
-        for (topic, sender) in self.topic_subscriptions.values() {
-            if sender.receiver_count() == 0 {
+    pub fn garbage_collect(&mut self, behaviour: &mut Behaviour) {
+        self.validators
+            .retain(|_, validator| !validator.is_closed());
+
+        // Messages of validators that went away are dropped without penalising anyone,
+        // messages that aren't validated in time are dropped by the validation deadlines
+        let orphaned = self
+            .pending_validations
+            .iter()
+            .filter(|(_, pending)| !self.validators.contains_key(&pending.topic_hash))
+            .map(|(message_id, _)| message_id.clone())
+            .collect::<Vec<_>>();
+        for message_id in orphaned {
+            if let Some(pending) = self.pending_validations.remove(&message_id) {
+                self.validation_deadlines.try_remove(&pending.deadline);
+                Self::report_ignored(behaviour, &pending.message);
+            }
+        }
+
+        self.query_subscriptions
+            .retain(|subscription| subscription.sender.receiver_count() > 0);
+        self.reliable.garbage_collect();
+
+        let validators = &self.validators;
+        let query_subscriptions = &self.query_subscriptions;
+        let reliable = &self.reliable;
+        let is_unused = |topic_hash: &TopicHash, sender: &broadcast::Sender<ReceivedMessage>| {
+            sender.receiver_count() == 0
+                && !validators.contains_key(topic_hash)
+                && !reliable.is_subscribed(topic_hash)
+                && !query_subscriptions
+                    .iter()
+                    .any(|subscription| subscription.matches(topic_hash))
+        };
+
+        for (topic_hash, (topic, sender)) in &self.topic_subscriptions {
+            if is_unused(topic_hash, sender) {
                 tracing::info!("Unsubscribing from topic: {:?}", topic);
                 if !behaviour.unsubscribe(topic) {
                     tracing::warn!("There existed a sender but no subscription on the behviour. Inconsistency error.");
@@ -168,7 +571,6 @@ impl Actor {
             }
         }
         self.topic_subscriptions
-            .retain(|_, (_, sender)| sender.receiver_count() > 0);
-        Ok(())
+            .retain(|topic_hash, (_, sender)| !is_unused(topic_hash, sender));
     }
 }
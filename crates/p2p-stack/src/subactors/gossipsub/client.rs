@@ -1,13 +1,15 @@
 use hyveos_core::{
     debug::MessageDebugEventType,
-    pub_sub::{MessageId, ReceivedMessage},
+    pub_sub::{MessageAcceptance, MessageId, ReceivedMessage, ReliableEvent, TopicPeers},
+    req_resp::TopicQuery,
 };
-use libp2p::gossipsub::{IdentTopic, PublishError, SubscriptionError};
-use tokio::sync::{broadcast, oneshot};
+use libp2p::gossipsub::{IdentTopic, PublishError, SubscriptionError, TopicHash};
+use tokio::sync::{broadcast, mpsc, oneshot};
 
-use super::Command;
+use super::{Command, ValidatorError};
 use crate::client::{RequestError, SpecialClient};
 
+#[derive(Clone)]
 pub struct Client {
     inner: SpecialClient<Command>,
 }
@@ -24,6 +26,57 @@ impl Client {
         TopicHandle { commander, topic }
     }
 
+    /// Subscribes to all topics that start with `prefix` and match `query` on the rest.
+    ///
+    /// Matching topics are joined as soon as another peer is known to be subscribed to them.
+    pub async fn subscribe_query(
+        &self,
+        prefix: impl Into<String>,
+        query: TopicQuery,
+    ) -> Result<broadcast::Receiver<ReceivedMessage>, RequestError<SubscriptionError>> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .request(
+                Command::SubscribeQuery {
+                    prefix: prefix.into(),
+                    query,
+                    send_subscription: sender,
+                },
+                receiver,
+            )
+            .await
+    }
+
+    /// Reports the verdict of a validator about a message received from [`TopicHandle::validate`].
+    ///
+    /// Returns whether the message was still waiting for validation.
+    pub async fn report_validation_result(
+        &self,
+        message_id: MessageId,
+        acceptance: MessageAcceptance,
+    ) -> Result<bool, RequestError> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .send(Command::ReportValidationResult {
+                message_id,
+                acceptance,
+                send_found: sender,
+            })
+            .await
+            .map_err(RequestError::Send)?;
+        receiver.await.map_err(RequestError::Oneshot)
+    }
+
+    /// Returns the topics this node is subscribed to, including internal topics.
+    pub async fn topics(&self) -> Result<Vec<TopicHash>, RequestError> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .send(Command::ListTopics(sender))
+            .await
+            .map_err(RequestError::Send)?;
+        receiver.await.map_err(RequestError::Oneshot)
+    }
+
     pub async fn debug_subscribe(
         &self,
     ) -> Result<broadcast::Receiver<MessageDebugEventType>, RequestError> {
@@ -70,4 +123,77 @@ impl TopicHandle {
             )
             .await
     }
+
+    /// Publishes a message in the topic as a reliable topic.
+    ///
+    /// The message is kept in a bounded history, from which subscribers fetch it if they miss it.
+    pub async fn publish_reliable(
+        &self,
+        data: Vec<u8>,
+    ) -> Result<MessageId, RequestError<PublishError>> {
+        let (sender, receiver) = oneshot::channel();
+        self.commander
+            .request(
+                Command::PublishReliable {
+                    topic: self.topic.clone(),
+                    data,
+                    send_message_id: sender,
+                },
+                receiver,
+            )
+            .await
+    }
+
+    /// Subscribes to the topic as a reliable topic.
+    ///
+    /// Messages of each publisher are received in order,
+    /// messages that can't be recovered are reported as lost.
+    pub async fn subscribe_reliable(
+        &self,
+    ) -> Result<broadcast::Receiver<ReliableEvent>, RequestError<SubscriptionError>> {
+        let (sender, receiver) = oneshot::channel();
+        self.commander
+            .request(
+                Command::SubscribeReliable {
+                    topic: self.topic.clone(),
+                    send_subscription: sender,
+                },
+                receiver,
+            )
+            .await
+    }
+
+    /// Registers a validator for the topic.
+    ///
+    /// Received messages are only delivered to subscribers and forwarded to other peers
+    /// after the validator accepted them with [`Client::report_validation_result`].
+    /// Dropping the receiver unregisters the validator.
+    pub async fn validate(
+        &self,
+    ) -> Result<mpsc::Receiver<ReceivedMessage>, RequestError<ValidatorError>> {
+        let (sender, receiver) = oneshot::channel();
+        self.commander
+            .request(
+                Command::Validate {
+                    topic: self.topic.clone(),
+                    send_validator: sender,
+                },
+                receiver,
+            )
+            .await
+    }
+
+    /// Returns the peers that are known to be subscribed to the topic
+    /// and the peers in its gossipsub mesh.
+    pub async fn peers(&self) -> Result<TopicPeers, RequestError> {
+        let (sender, receiver) = oneshot::channel();
+        self.commander
+            .send(Command::TopicPeers {
+                topic: self.topic.hash(),
+                send_peers: sender,
+            })
+            .await
+            .map_err(RequestError::Send)?;
+        receiver.await.map_err(RequestError::Oneshot)
+    }
 }
@@ -1,10 +1,12 @@
 use hyveos_core::{
     debug::MessageDebugEventType,
-    pub_sub::{MessageId, ReceivedMessage},
+    pub_sub::{MessageAcceptance, MessageId, ReceivedMessage, ReliableEvent, TopicPeers},
+    req_resp::TopicQuery,
 };
-use libp2p::gossipsub::{IdentTopic, PublishError, SubscriptionError};
-use tokio::sync::{broadcast, oneshot};
+use libp2p::gossipsub::{IdentTopic, PublishError, SubscriptionError, TopicHash};
+use tokio::sync::{broadcast, mpsc, oneshot};
 
+use super::ValidatorError;
 use crate::impl_from_special_command;
 
 pub enum Command {
@@ -18,6 +20,36 @@ pub enum Command {
         send_subscription:
             oneshot::Sender<Result<broadcast::Receiver<ReceivedMessage>, SubscriptionError>>,
     },
+    SubscribeQuery {
+        prefix: String,
+        query: TopicQuery,
+        send_subscription:
+            oneshot::Sender<Result<broadcast::Receiver<ReceivedMessage>, SubscriptionError>>,
+    },
+    PublishReliable {
+        topic: IdentTopic,
+        data: Vec<u8>,
+        send_message_id: oneshot::Sender<Result<MessageId, PublishError>>,
+    },
+    SubscribeReliable {
+        topic: IdentTopic,
+        send_subscription:
+            oneshot::Sender<Result<broadcast::Receiver<ReliableEvent>, SubscriptionError>>,
+    },
+    Validate {
+        topic: IdentTopic,
+        send_validator: oneshot::Sender<Result<mpsc::Receiver<ReceivedMessage>, ValidatorError>>,
+    },
+    ReportValidationResult {
+        message_id: MessageId,
+        acceptance: MessageAcceptance,
+        send_found: oneshot::Sender<bool>,
+    },
+    ListTopics(oneshot::Sender<Vec<TopicHash>>),
+    TopicPeers {
+        topic: TopicHash,
+        send_peers: oneshot::Sender<TopicPeers>,
+    },
     DebugSubscribe(oneshot::Sender<broadcast::Receiver<MessageDebugEventType>>),
 }
 
@@ -0,0 +1,689 @@
+//! Reliable topics on top of gossipsub.
+//!
+//! Messages in reliable topics are wrapped in an envelope with a sequence number per publisher.
+//! Subscribers deliver the messages of each publisher in order and fetch missed messages from
+//! the bounded history of the publisher. Messages that can't be recovered are reported as lost.
+//!
+//! A subscriber starts following a publisher with the first message it receives from it, so
+//! messages published before that aren't replayed. The history is only served to peers that are
+//! subscribed to the topic.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    io,
+};
+
+use hyveos_core::pub_sub::{MessageId, ReceivedMessage, ReliableEvent};
+use libp2p::{
+    gossipsub::{self, IdentTopic, PublishError, TopicHash},
+    request_response::{
+        cbor, Config, Event as HistoryEvent, Message, OutboundRequestId, ProtocolSupport,
+    },
+    PeerId, StreamProtocol,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+/// The number of messages a publisher keeps per reliable topic to answer history requests.
+const HISTORY_CAP: usize = 256;
+const CHANNEL_CAP: usize = 64;
+
+/// The number of message bytes sent in a history response at most, well below the response size
+/// limit of the history protocol. Subscribers request the remaining messages afterwards.
+const MAX_RESPONSE_BYTES: usize = 4 * 1024 * 1024;
+
+/// The envelope of a message in a reliable topic.
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    session: u64,
+    seq: u64,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    seq: u64,
+    message_id: Vec<u8>,
+    data: Vec<u8>,
+}
+
+/// A request for the messages with sequence numbers in `from..to` of a publisher session.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryRequest {
+    topic: String,
+    session: u64,
+    from: u64,
+    to: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryResponse {
+    /// All requested messages before this sequence number are no longer in the history.
+    first_available: u64,
+    entries: Vec<HistoryEntry>,
+}
+
+pub type Behaviour = cbor::Behaviour<HistoryRequest, HistoryResponse>;
+pub type Event = HistoryEvent<HistoryRequest, HistoryResponse>;
+
+pub fn new() -> Behaviour {
+    Behaviour::new(
+        [(
+            StreamProtocol::new("/pub_sub/history/1.0.0"),
+            ProtocolSupport::Full,
+        )],
+        Config::default(),
+    )
+}
+
+/// The messages published by the local node in a reliable topic.
+///
+/// The session is chosen randomly, so subscribers notice when the node restarts
+/// and its sequence numbers start over.
+#[derive(Debug)]
+struct History {
+    session: u64,
+    next_seq: u64,
+    entries: VecDeque<HistoryEntry>,
+}
+
+impl History {
+    fn new() -> Self {
+        Self {
+            session: rand::random(),
+            next_seq: 0,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Returns the messages with sequence numbers in `from..to` that are still in the history.
+    ///
+    /// The response holds at least one message and at most [`MAX_RESPONSE_BYTES`] of messages,
+    /// starting at the oldest requested one.
+    fn respond(&self, session: u64, from: u64, to: u64) -> HistoryResponse {
+        if session != self.session {
+            return HistoryResponse {
+                first_available: to,
+                entries: Vec::new(),
+            };
+        }
+
+        let oldest = self
+            .entries
+            .front()
+            .map_or(self.next_seq, |entry| entry.seq);
+
+        let mut size = 0;
+        let entries = self
+            .entries
+            .iter()
+            .filter(|entry| (from..to).contains(&entry.seq))
+            .take_while(|entry| {
+                let first = size == 0;
+                size += entry.message_id.len() + entry.data.len();
+                first || size <= MAX_RESPONSE_BYTES
+            })
+            .cloned()
+            .collect();
+
+        HistoryResponse {
+            first_available: from.max(oldest).min(to),
+            entries,
+        }
+    }
+}
+
+/// The receiving state of a subscriber for the messages of one publisher.
+#[derive(Debug)]
+struct PublisherState {
+    session: u64,
+    next_seq: u64,
+    /// Messages received ahead of `next_seq`.
+    buffer: BTreeMap<u64, ReceivedMessage>,
+    fetching: bool,
+}
+
+#[derive(Debug)]
+struct Subscription {
+    sender: broadcast::Sender<ReliableEvent>,
+    publishers: HashMap<PeerId, PublisherState>,
+}
+
+impl Subscription {
+    fn emit(&self, event: ReliableEvent) {
+        // Subscriptions without receivers are removed in `garbage_collect`
+        let _ = self.sender.send(event);
+    }
+
+    /// Delivers all buffered messages of a publisher that are next in order.
+    fn flush(&mut self, publisher: PeerId) {
+        let Some(state) = self.publishers.get_mut(&publisher) else {
+            return;
+        };
+
+        while let Some(entry) = state.buffer.first_entry() {
+            if *entry.key() != state.next_seq {
+                break;
+            }
+            let message = entry.remove();
+            state.next_seq += 1;
+            let _ = self.sender.send(ReliableEvent::Message(message));
+        }
+    }
+
+    /// Gives up on the messages of a publisher up to the first buffered one.
+    fn skip_gap(&mut self, publisher: PeerId) {
+        let Some(state) = self.publishers.get_mut(&publisher) else {
+            return;
+        };
+        let Some(&first_buffered) = state.buffer.keys().next() else {
+            return;
+        };
+
+        let count = first_buffered - state.next_seq;
+        state.next_seq = first_buffered;
+        self.emit(ReliableEvent::Lost { publisher, count });
+        self.flush(publisher);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Reliable {
+    histories: HashMap<TopicHash, History>,
+    subscriptions: HashMap<TopicHash, Subscription>,
+    requests: HashMap<OutboundRequestId, (TopicHash, PeerId, u64)>,
+}
+
+impl Reliable {
+    pub fn publish(
+        &mut self,
+        gossipsub: &mut gossipsub::Behaviour,
+        topic: IdentTopic,
+        data: Vec<u8>,
+    ) -> Result<MessageId, PublishError> {
+        let history = self
+            .histories
+            .entry(topic.hash())
+            .or_insert_with(History::new);
+
+        let envelope = Envelope {
+            session: history.session,
+            seq: history.next_seq,
+            data,
+        };
+        let encoded = cbor4ii::serde::to_vec(Vec::new(), &envelope)
+            .map_err(|e| PublishError::TransformFailed(io::Error::other(format!("{e:?}"))))?;
+
+        let message_id = gossipsub.publish(topic, encoded)?;
+
+        history.entries.push_back(HistoryEntry {
+            seq: envelope.seq,
+            message_id: message_id.0.clone(),
+            data: envelope.data,
+        });
+        if history.entries.len() > HISTORY_CAP {
+            history.entries.pop_front();
+        }
+        history.next_seq += 1;
+
+        Ok(MessageId(message_id.0))
+    }
+
+    pub fn subscribe(&mut self, topic_hash: TopicHash) -> broadcast::Receiver<ReliableEvent> {
+        self.subscriptions
+            .entry(topic_hash)
+            .or_insert_with(|| Subscription {
+                sender: broadcast::channel(CHANNEL_CAP).0,
+                publishers: HashMap::new(),
+            })
+            .sender
+            .subscribe()
+    }
+
+    pub fn is_subscribed(&self, topic_hash: &TopicHash) -> bool {
+        self.subscriptions.contains_key(topic_hash)
+    }
+
+    pub fn garbage_collect(&mut self) {
+        self.subscriptions
+            .retain(|_, subscription| subscription.sender.receiver_count() > 0);
+    }
+
+    /// Handles a message received in a reliable topic the local node is subscribed to.
+    pub fn receive(
+        &mut self,
+        history: &mut Behaviour,
+        topic_hash: &TopicHash,
+        mut message: ReceivedMessage,
+    ) {
+        let Some(subscription) = self.subscriptions.get_mut(topic_hash) else {
+            return;
+        };
+        let Some(publisher) = message.source else {
+            tracing::debug!(topic=%topic_hash, "Ignoring reliable message without source");
+            return;
+        };
+        let Envelope { session, seq, data } =
+            match cbor4ii::serde::from_slice(&message.message.data) {
+                Ok(envelope) => envelope,
+                Err(e) => {
+                    tracing::debug!(error = ?e, "Ignoring malformed reliable message");
+                    return;
+                }
+            };
+        message.message.data = data;
+
+        let state = subscription
+            .publishers
+            .entry(publisher)
+            .or_insert_with(|| PublisherState {
+                session,
+                next_seq: seq,
+                buffer: BTreeMap::new(),
+                fetching: false,
+            });
+
+        // The publisher restarted, so its earlier messages can't be recovered anymore
+        if state.session != session {
+            *state = PublisherState {
+                session,
+                next_seq: seq,
+                buffer: BTreeMap::new(),
+                fetching: false,
+            };
+        }
+
+        // Messages before `next_seq` were already delivered or given up on
+        if seq < state.next_seq {
+            return;
+        }
+
+        state.buffer.insert(seq, message);
+        subscription.flush(publisher);
+
+        self.fetch_missing(history, topic_hash, publisher);
+    }
+
+    /// Requests the missing messages before the first buffered message of a publisher,
+    /// unless a request is already running.
+    fn fetch_missing(
+        &mut self,
+        history: &mut Behaviour,
+        topic_hash: &TopicHash,
+        publisher: PeerId,
+    ) {
+        let Some(state) = self
+            .subscriptions
+            .get_mut(topic_hash)
+            .and_then(|subscription| subscription.publishers.get_mut(&publisher))
+        else {
+            return;
+        };
+        if state.fetching {
+            return;
+        }
+        let Some(&first_buffered) = state.buffer.keys().next() else {
+            return;
+        };
+
+        tracing::debug!(
+            topic=%topic_hash,
+            %publisher,
+            from=state.next_seq,
+            to=first_buffered,
+            "Fetching missed reliable messages"
+        );
+
+        let request_id = history.send_request(
+            &publisher,
+            HistoryRequest {
+                topic: topic_hash.to_string(),
+                session: state.session,
+                from: state.next_seq,
+                to: first_buffered,
+            },
+        );
+        state.fetching = true;
+        self.requests
+            .insert(request_id, (topic_hash.clone(), publisher, state.session));
+    }
+
+    pub fn handle_event(
+        &mut self,
+        event: Event,
+        gossipsub: &gossipsub::Behaviour,
+        history: &mut Behaviour,
+    ) {
+        match event {
+            HistoryEvent::Message {
+                peer,
+                message:
+                    Message::Request {
+                        request, channel, ..
+                    },
+            } => {
+                let HistoryRequest {
+                    topic,
+                    session,
+                    from,
+                    to,
+                } = request;
+                let topic_hash = TopicHash::from_raw(topic);
+
+                let is_subscribed = gossipsub.all_peers().any(|(subscriber, topics)| {
+                    *subscriber == peer && topics.contains(&&topic_hash)
+                });
+                if !is_subscribed {
+                    // Dropping the channel lets the request fail on the requesting side
+                    tracing::debug!(
+                        %peer,
+                        topic=%topic_hash,
+                        "Ignoring history request from peer not subscribed to the topic"
+                    );
+                    return;
+                }
+
+                let response = match self.histories.get(&topic_hash) {
+                    Some(topic_history) => topic_history.respond(session, from, to),
+                    None => HistoryResponse {
+                        first_available: to,
+                        entries: Vec::new(),
+                    },
+                };
+
+                if history.send_response(channel, response).is_err() {
+                    tracing::debug!(%peer, "Failed to respond to history request");
+                }
+            }
+            HistoryEvent::Message {
+                message:
+                    Message::Response {
+                        request_id,
+                        response,
+                    },
+                ..
+            } => self.handle_response(history, request_id, Some(response)),
+            HistoryEvent::OutboundFailure {
+                request_id, error, ..
+            } => {
+                tracing::debug!(error = %error, "Failed to fetch missed reliable messages");
+                self.handle_response(history, request_id, None);
+            }
+            e => {
+                tracing::debug!("Unhandled event: {e:?}");
+            }
+        }
+    }
+
+    fn handle_response(
+        &mut self,
+        history: &mut Behaviour,
+        request_id: OutboundRequestId,
+        response: Option<HistoryResponse>,
+    ) {
+        let Some((topic_hash, publisher, session)) = self.requests.remove(&request_id) else {
+            return;
+        };
+        let Some(subscription) = self.subscriptions.get_mut(&topic_hash) else {
+            return;
+        };
+        // The response belongs to a session the publisher restarted since
+        let Some(state) = subscription
+            .publishers
+            .get_mut(&publisher)
+            .filter(|state| state.session == session)
+        else {
+            return;
+        };
+        state.fetching = false;
+        let next_seq = state.next_seq;
+
+        if let Some(HistoryResponse {
+            first_available,
+            entries,
+        }) = response
+        {
+            if first_available > state.next_seq {
+                let count = first_available - state.next_seq;
+                state.next_seq = first_available;
+                subscription.emit(ReliableEvent::Lost { publisher, count });
+            }
+
+            let Some(state) = subscription.publishers.get_mut(&publisher) else {
+                return;
+            };
+            for HistoryEntry {
+                seq,
+                message_id,
+                data,
+            } in entries
+            {
+                if seq >= state.next_seq {
+                    state.buffer.entry(seq).or_insert_with(|| ReceivedMessage {
+                        propagation_source: publisher,
+                        source: Some(publisher),
+                        message_id: MessageId(message_id),
+                        message: hyveos_core::pub_sub::Message {
+                            data,
+                            topic: topic_hash.to_string(),
+                        },
+                    });
+                }
+            }
+            subscription.flush(publisher);
+        }
+
+        // Without any progress, the rest of the gap can't be recovered
+        if subscription
+            .publishers
+            .get(&publisher)
+            .is_some_and(|state| state.next_seq == next_seq)
+        {
+            subscription.skip_gap(publisher);
+        }
+
+        self.fetch_missing(history, &topic_hash, publisher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SESSION: u64 = 1;
+
+    fn history_entries(history: &mut History, count: usize, size: usize) {
+        for _ in 0..count {
+            history.entries.push_back(HistoryEntry {
+                seq: history.next_seq,
+                message_id: Vec::new(),
+                data: vec![0; size],
+            });
+            history.next_seq += 1;
+        }
+    }
+
+    fn message(publisher: PeerId, session: u64, seq: u64) -> ReceivedMessage {
+        let envelope = Envelope {
+            session,
+            seq,
+            data: seq.to_be_bytes().to_vec(),
+        };
+        ReceivedMessage {
+            propagation_source: publisher,
+            source: Some(publisher),
+            message_id: MessageId(seq.to_be_bytes().to_vec()),
+            message: hyveos_core::pub_sub::Message {
+                data: cbor4ii::serde::to_vec(Vec::new(), &envelope).unwrap(),
+                topic: "topic".to_string(),
+            },
+        }
+    }
+
+    fn entry(seq: u64) -> HistoryEntry {
+        HistoryEntry {
+            seq,
+            message_id: seq.to_be_bytes().to_vec(),
+            data: seq.to_be_bytes().to_vec(),
+        }
+    }
+
+    /// Returns the delivered messages as their sequence numbers and lost messages as their count.
+    fn events(receiver: &mut broadcast::Receiver<ReliableEvent>) -> Vec<Result<u64, u64>> {
+        std::iter::from_fn(|| receiver.try_recv().ok())
+            .map(|event| match event {
+                ReliableEvent::Message(message) => {
+                    Ok(u64::from_be_bytes(message.message.data.try_into().unwrap()))
+                }
+                ReliableEvent::Lost { count, .. } => Err(count),
+            })
+            .collect()
+    }
+
+    /// Returns the only running history request.
+    fn request(reliable: &Reliable) -> OutboundRequestId {
+        assert_eq!(reliable.requests.len(), 1);
+        *reliable.requests.keys().next().unwrap()
+    }
+
+    fn setup() -> (
+        Reliable,
+        Behaviour,
+        TopicHash,
+        PeerId,
+        broadcast::Receiver<ReliableEvent>,
+    ) {
+        let mut reliable = Reliable::default();
+        let topic_hash = TopicHash::from_raw("topic");
+        let receiver = reliable.subscribe(topic_hash.clone());
+        (reliable, new(), topic_hash, PeerId::random(), receiver)
+    }
+
+    #[test]
+    fn test_in_order_delivery() {
+        let (mut reliable, mut history, topic_hash, publisher, mut receiver) = setup();
+
+        for seq in 3..6 {
+            reliable.receive(&mut history, &topic_hash, message(publisher, SESSION, seq));
+        }
+        // Duplicates are ignored
+        reliable.receive(&mut history, &topic_hash, message(publisher, SESSION, 4));
+
+        assert_eq!(events(&mut receiver), [Ok(3), Ok(4), Ok(5)]);
+        assert!(reliable.requests.is_empty());
+    }
+
+    #[test]
+    fn test_gap_fill() {
+        let (mut reliable, mut history, topic_hash, publisher, mut receiver) = setup();
+
+        reliable.receive(&mut history, &topic_hash, message(publisher, SESSION, 0));
+        reliable.receive(&mut history, &topic_hash, message(publisher, SESSION, 3));
+        reliable.receive(&mut history, &topic_hash, message(publisher, SESSION, 4));
+        assert_eq!(events(&mut receiver), [Ok(0)]);
+
+        let response = HistoryResponse {
+            first_available: 1,
+            entries: vec![entry(1), entry(2)],
+        };
+        reliable.handle_response(&mut history, request(&reliable), Some(response));
+
+        assert_eq!(events(&mut receiver), [Ok(1), Ok(2), Ok(3), Ok(4)]);
+        assert!(reliable.requests.is_empty());
+    }
+
+    #[test]
+    fn test_partial_history() {
+        let (mut reliable, mut history, topic_hash, publisher, mut receiver) = setup();
+
+        reliable.receive(&mut history, &topic_hash, message(publisher, SESSION, 0));
+        reliable.receive(&mut history, &topic_hash, message(publisher, SESSION, 6));
+
+        // The oldest messages were dropped from the history, the rest didn't fit the response
+        let response = HistoryResponse {
+            first_available: 3,
+            entries: vec![entry(3), entry(4)],
+        };
+        reliable.handle_response(&mut history, request(&reliable), Some(response));
+        assert_eq!(events(&mut receiver), [Ok(0), Err(2), Ok(3), Ok(4)]);
+
+        let response = HistoryResponse {
+            first_available: 5,
+            entries: vec![entry(5)],
+        };
+        reliable.handle_response(&mut history, request(&reliable), Some(response));
+        assert_eq!(events(&mut receiver), [Ok(5), Ok(6)]);
+
+        // A failed request gives up on the gap
+        reliable.receive(&mut history, &topic_hash, message(publisher, SESSION, 9));
+        reliable.handle_response(&mut history, request(&reliable), None);
+        assert_eq!(events(&mut receiver), [Err(2), Ok(9)]);
+        assert!(reliable.requests.is_empty());
+    }
+
+    #[test]
+    fn test_session_change() {
+        let (mut reliable, mut history, topic_hash, publisher, mut receiver) = setup();
+
+        reliable.receive(&mut history, &topic_hash, message(publisher, SESSION, 0));
+        reliable.receive(&mut history, &topic_hash, message(publisher, SESSION, 2));
+        let stale = request(&reliable);
+
+        // The publisher restarted, so the gap of the old session is dropped
+        reliable.receive(
+            &mut history,
+            &topic_hash,
+            message(publisher, SESSION + 1, 0),
+        );
+        reliable.receive(
+            &mut history,
+            &topic_hash,
+            message(publisher, SESSION + 1, 1),
+        );
+        assert_eq!(events(&mut receiver), [Ok(0), Ok(0), Ok(1)]);
+
+        // A late response for the old session is ignored
+        let response = HistoryResponse {
+            first_available: 1,
+            entries: vec![entry(1), entry(2), entry(3)],
+        };
+        reliable.handle_response(&mut history, stale, Some(response));
+        assert!(events(&mut receiver).is_empty());
+        assert!(reliable.requests.is_empty());
+    }
+
+    #[test]
+    fn test_respond() {
+        let mut history = History::new();
+        history_entries(&mut history, 4, 16);
+
+        let response = history.respond(history.session, 1, 3);
+        assert_eq!(response.first_available, 1);
+        assert_eq!(
+            response.entries.iter().map(|e| e.seq).collect::<Vec<_>>(),
+            [1, 2]
+        );
+
+        let response = history.respond(history.session + 1, 1, 3);
+        assert_eq!(response.first_available, 3);
+        assert!(response.entries.is_empty());
+    }
+
+    #[test]
+    fn test_respond_limits_size() {
+        let mut history = History::new();
+        history_entries(&mut history, HISTORY_CAP, 64 * 1024);
+
+        let response = history.respond(history.session, 0, history.next_seq);
+        let size = response.entries.iter().map(|e| e.data.len()).sum::<usize>();
+        assert!(size <= MAX_RESPONSE_BYTES);
+        assert!(size + 64 * 1024 > MAX_RESPONSE_BYTES);
+        assert_eq!(response.entries[0].seq, 0);
+
+        // A single message is always sent, even if it's larger than the limit
+        let mut history = History::new();
+        history_entries(&mut history, 2, MAX_RESPONSE_BYTES + 1);
+        let response = history.respond(history.session, 0, 2);
+        assert_eq!(response.entries.len(), 1);
+    }
+}
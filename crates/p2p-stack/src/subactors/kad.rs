@@ -1,9 +1,16 @@
 pub use self::{
-    actor::{Actor, CommandError, EventError},
+    actor::{Actor, CommandError, EventError, PutError},
     client::Client,
     command::Command,
+    owned::{verify_record, ValidationError, VerifiedRecord},
+    store::{Store, StoreBackend, StoreChange, StoreConfig, StoreTable},
+    watch::{WatchError, WatchEvent},
 };
 
 mod actor;
 mod client;
 mod command;
+mod owned;
+mod store;
+mod tombstone;
+mod watch;
@@ -1,13 +1,24 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, num::NonZeroUsize};
 
-use libp2p::kad::{
-    store, AddProviderError, AddProviderOk, BootstrapError, BootstrapOk, Event, GetProvidersError,
-    GetProvidersOk, GetRecordError, GetRecordOk, NoKnownPeers, ProgressStep, PutRecordError,
-    PutRecordOk, QueryId, QueryResult,
+use libp2p::{
+    identity::{Keypair, SigningError},
+    kad::{
+        store::{self, RecordStore as _},
+        AddProviderError, AddProviderOk, BootstrapError, BootstrapOk, Event, GetProvidersError,
+        GetProvidersOk, GetRecordError, GetRecordOk, InboundRequest, NoKnownPeers, PeerRecord,
+        ProgressStep, PutRecordError, PutRecordOk, QueryId, QueryResult, Quorum, Record, RecordKey,
+        K_VALUE,
+    },
+    PeerId,
 };
-use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{broadcast, mpsc::error::TrySendError};
 
-use super::Command;
+use super::{
+    owned::{self, OwnedValue, ValidationError},
+    tombstone::{is_tombstone, outlives, tombstone_value},
+    watch::{encode_notification, watch_topic},
+    Command,
+};
 use crate::{
     actor::SubActor,
     behaviour::MyBehaviour,
@@ -17,13 +28,66 @@ use crate::{
 pub type QueryTracker<T, E> = HashMap<QueryId, SendResult<T, E>>;
 pub type QueryMultipleTracker<T, E> = HashMap<QueryId, SendMultipleResult<T, E>>;
 
+const WATCH_CHANNEL_CAP: usize = 10;
+
 #[derive(Default)]
 pub struct Actor {
-    put_record: QueryTracker<PutRecordOk, PutRecordError>,
+    keypair: Option<Keypair>,
+    put_record: QueryTracker<PutRecordOk, PutError>,
     get_record: QueryMultipleTracker<GetRecordOk, GetRecordError>,
+    get_record_quorum: HashMap<QueryId, RecordQuorum>,
     bootstrap: QueryMultipleTracker<BootstrapOk, BootstrapError>,
     get_providers: QueryMultipleTracker<GetProvidersOk, GetProvidersError>,
     start_providing: QueryTracker<AddProviderOk, AddProviderError>,
+    record_watchers: HashMap<RecordKey, broadcast::Sender<Record>>,
+    delete_record: HashMap<QueryId, PendingDelete>,
+    delete_record_put: HashMap<QueryId, DeleteAcks>,
+}
+
+/// A delete that is looking up the replicas of the record to delete.
+struct PendingDelete {
+    key: RecordKey,
+    sender: SendResult<usize, PutError>,
+    /// The remote peers holding the record.
+    replicas: Vec<PeerId>,
+    /// Whether the local node holds the record.
+    local: bool,
+    /// The replica of the record that expires last.
+    record: Option<Record>,
+}
+
+/// A delete that is sending the tombstone to the replicas of the deleted record.
+struct DeleteAcks {
+    sender: SendResult<usize, PutError>,
+    /// Whether the tombstone replaced the local replica.
+    local: usize,
+    /// The number of remote replicas the tombstone was sent to.
+    remote: usize,
+}
+
+struct RecordQuorum {
+    key: RecordKey,
+    required: NonZeroUsize,
+    found: usize,
+}
+
+fn quorum_size(quorum: Quorum) -> NonZeroUsize {
+    match quorum {
+        Quorum::One => NonZeroUsize::MIN,
+        Quorum::Majority => NonZeroUsize::new(K_VALUE.get() / 2 + 1).unwrap_or(NonZeroUsize::MIN),
+        Quorum::All => K_VALUE,
+        Quorum::N(n) => n.min(K_VALUE),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PutError {
+    #[error(transparent)]
+    Kad(#[from] PutRecordError),
+    #[error("Record rejected: {0}")]
+    Rejected(#[from] ValidationError),
+    #[error("Failed to sign owned record: {0}")]
+    Signing(#[from] SigningError),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -41,7 +105,9 @@ pub enum EventError {
     #[error("Sending result of get record failed trying to send result: {0:?}")]
     GetRecordSendError(TrySendError<Result<GetRecordOk, GetRecordError>>),
     #[error("Sending result of put record failed trying to send result: {0:?}")]
-    PutRecordSendError(Result<PutRecordOk, PutRecordError>),
+    PutRecordSendError(Result<PutRecordOk, PutError>),
+    #[error("Sending result of delete record failed trying to send result: {0:?}")]
+    DeleteRecordSendError(Result<usize, PutError>),
     #[error("Sending result of bootstrap failed trying to send result: {0:?}")]
     BootstrapSendError(TrySendError<Result<BootstrapOk, BootstrapError>>),
     #[error("Sending result of get providers failed trying to send result: {0:?}")]
@@ -71,15 +137,44 @@ impl SubActor for Actor {
     type Event = Event;
     type EventError = EventError;
 
+    fn new(keypair: &Keypair) -> Self {
+        Self {
+            keypair: Some(keypair.clone()),
+            ..Default::default()
+        }
+    }
+
     fn handle_event(
         &mut self,
         event: Self::Event,
-        _: &mut MyBehaviour,
+        behaviour: &mut MyBehaviour,
     ) -> Result<(), Self::EventError> {
         match event {
             Event::OutboundQueryProgressed {
                 id, result, step, ..
-            } => self.handle_outbound_query_progressed(id, result, &step),
+            } => self.handle_outbound_query_progressed(id, result, &step, behaviour),
+            Event::InboundRequest {
+                request:
+                    InboundRequest::PutRecord {
+                        source,
+                        record: Some(record),
+                        ..
+                    },
+            } => {
+                self.store_inbound_record(source, record, behaviour);
+                Ok(())
+            }
+            Event::InboundRequest {
+                request:
+                    InboundRequest::AddProvider {
+                        record: Some(record),
+                    },
+            } => {
+                if let Err(e) = behaviour.kad.store_mut().add_provider(record) {
+                    tracing::warn!(error = ?e, "Failed to store inbound provider record");
+                }
+                Ok(())
+            }
             _ => Ok(()),
         }
     }
@@ -93,25 +188,82 @@ impl SubActor for Actor {
             Command::PutRecord {
                 record,
                 quorum,
+                owned,
                 sender,
             } => {
-                call_behaviour!(
-                    throws;
-                    self,
-                    put_record,
-                    put_record,
-                    behaviour,
-                    sender;
-                    record,
-                    quorum
-                )
+                let record = match self.prepare_record(record, owned, behaviour) {
+                    Ok(record) => record,
+                    Err(e) => {
+                        let _ = sender.send(Err(e));
+                        return Ok(());
+                    }
+                };
+
+                let query_id = behaviour.kad.put_record(record.clone(), quorum)?;
+                // We can ignore that here becauase QueryId is unique
+                let _ = self.put_record.insert(query_id, sender);
+
+                self.announce_record(&record, behaviour);
+                Ok(())
             }
-            Command::GetRecord { key, sender } => {
-                call_behaviour!(self, get_record, get_record, behaviour, sender; key)
+            Command::GetRecord {
+                key,
+                quorum,
+                sender,
+            } => {
+                let query_id = behaviour.kad.get_record(key.clone());
+                // We can ignore that here becauase QueryId is unique
+                let _ = self.get_record.insert(query_id, sender);
+                let _ = self.get_record_quorum.insert(
+                    query_id,
+                    RecordQuorum {
+                        key,
+                        required: quorum_size(quorum),
+                        found: 0,
+                    },
+                );
+                Ok(())
             }
             Command::RemoveRecord { key, sender } => {
-                behaviour.kad.remove_record(&key);
-                let _ = sender.send(Ok(()));
+                let local_peer_id = self.keypair().public().to_peer_id();
+                let res = behaviour
+                    .kad
+                    .store_mut()
+                    .get(&key)
+                    .map_or(Ok(()), |existing| {
+                        owned::validate_removal(&existing, local_peer_id)
+                    });
+
+                if res.is_ok() {
+                    behaviour.kad.remove_record(&key);
+                }
+
+                let _ = sender.send(res);
+                Ok(())
+            }
+            Command::DeleteRecord { key, sender } => {
+                let query_id = behaviour.kad.get_record(key.clone());
+                let _ = self.delete_record.insert(
+                    query_id,
+                    PendingDelete {
+                        key,
+                        sender,
+                        replicas: Vec::new(),
+                        local: false,
+                        record: None,
+                    },
+                );
+                Ok(())
+            }
+            Command::WatchRecord { key, sender } => {
+                self.record_watchers
+                    .retain(|_, watchers| watchers.receiver_count() > 0);
+                let receiver = self
+                    .record_watchers
+                    .entry(key)
+                    .or_insert_with(|| broadcast::channel(WATCH_CHANNEL_CAP).0)
+                    .subscribe();
+                let _ = sender.send(receiver);
                 Ok(())
             }
             Command::Bootstrap { sender } => call_behaviour!(
@@ -151,10 +303,29 @@ impl Actor {
         id: QueryId,
         result: QueryResult,
         step: &ProgressStep,
+        behaviour: &mut MyBehaviour,
     ) -> Result<(), EventError> {
         match result {
-            QueryResult::GetRecord(res) => {
-                if let Some(sender) = self.get_record.remove(&id) {
+            QueryResult::GetRecord(mut res) => {
+                if self.delete_record.contains_key(&id) {
+                    self.handle_delete_lookup(id, res, step, behaviour);
+                } else if let Some(sender) = self.get_record.remove(&id) {
+                    if let Ok(GetRecordOk::FoundRecord(PeerRecord { peer, record })) = &res {
+                        if let Err(e) = owned::validate(None, record) {
+                            tracing::warn!(?peer, error = %e, "Dropping invalid owned record");
+                            if !step.last {
+                                self.get_record.insert(id, sender);
+                                return Ok(());
+                            }
+                            // The query ends with this record, so the caller still needs the
+                            // final result without it.
+                            res = Ok(GetRecordOk::FinishedWithNoAdditionalRecord {
+                                cache_candidates: Default::default(),
+                            });
+                        }
+                    }
+
+                    let res = self.check_record_quorum(id, res, step, behaviour);
                     sender
                         .try_send(res)
                         .map_err(EventError::GetRecordSendError)?;
@@ -168,7 +339,21 @@ impl Actor {
             }
             QueryResult::PutRecord(res) => {
                 if let Some(sender) = self.put_record.remove(&id) {
-                    sender.send(res).map_err(EventError::PutRecordSendError)?;
+                    sender
+                        .send(res.map_err(PutError::Kad))
+                        .map_err(EventError::PutRecordSendError)?;
+                } else if let Some(delete) = self.delete_record_put.remove(&id) {
+                    let res = match res {
+                        Ok(_) => Ok(delete.local + delete.remote),
+                        Err(
+                            PutRecordError::QuorumFailed { success, .. }
+                            | PutRecordError::Timeout { success, .. },
+                        ) => Ok(delete.local + success.len()),
+                    };
+                    delete
+                        .sender
+                        .send(res)
+                        .map_err(EventError::DeleteRecordSendError)?;
                 } else {
                     tracing::trace!(?id, "PutRecord result for unknown query id");
                 }
@@ -211,4 +396,221 @@ impl Actor {
             _ => Ok(()),
         }
     }
+
+    fn keypair(&self) -> &Keypair {
+        self.keypair
+            .as_ref()
+            .expect("Kad actor is always created with the local keypair")
+    }
+
+    /// Signs the record if it should be owned and checks that it may replace the local copy.
+    fn prepare_record(
+        &self,
+        mut record: Record,
+        owned: bool,
+        behaviour: &mut MyBehaviour,
+    ) -> Result<Record, PutError> {
+        if owned {
+            let keypair = self.keypair();
+            let value = OwnedValue::sign(keypair, &record.key, record.value, record.expires)?;
+            record.value = value.encode();
+            record.publisher = Some(keypair.public().to_peer_id());
+        }
+
+        owned::validate(
+            behaviour.kad.store_mut().get(&record.key).as_deref(),
+            &record,
+        )?;
+
+        Ok(record)
+    }
+
+    /// Stores a record received from another peer, unless it would overwrite an owned record.
+    fn store_inbound_record(&self, source: PeerId, record: Record, behaviour: &mut MyBehaviour) {
+        let store = behaviour.kad.store_mut();
+
+        if let Err(e) = owned::validate(store.get(&record.key).as_deref(), &record) {
+            tracing::warn!(%source, key = ?record.key, error = %e, "Rejected inbound record");
+            return;
+        }
+
+        if let Err(e) = store.put(record.clone()) {
+            tracing::warn!(%source, error = ?e, "Failed to store inbound record");
+            return;
+        }
+
+        self.notify_watchers(record);
+    }
+
+    /// Notifies local watchers of a record put by this node
+    /// and announces the change to watchers on other nodes.
+    fn announce_record(&self, record: &Record, behaviour: &mut MyBehaviour) {
+        if let Some(data) = encode_notification(record) {
+            if let Err(e) = behaviour.gossipsub.publish(watch_topic(&record.key), data) {
+                // Nobody watching the key is the common case
+                tracing::trace!(error = %e, key = ?record.key, "Failed to announce record");
+            }
+        }
+
+        self.notify_watchers(record.clone());
+    }
+
+    fn notify_watchers(&self, record: Record) {
+        if let Some(watchers) = self.record_watchers.get(&record.key) {
+            let _ = watchers.send(record);
+        }
+    }
+
+    fn handle_delete_lookup(
+        &mut self,
+        id: QueryId,
+        res: Result<GetRecordOk, GetRecordError>,
+        step: &ProgressStep,
+        behaviour: &mut MyBehaviour,
+    ) {
+        let Some(pending) = self.delete_record.get_mut(&id) else {
+            return;
+        };
+
+        if let Ok(GetRecordOk::FoundRecord(PeerRecord { peer, record })) = res {
+            match peer {
+                Some(peer) => pending.replicas.push(peer),
+                None => pending.local = true,
+            }
+
+            let latest = pending
+                .record
+                .as_ref()
+                .map_or(true, |latest| outlives(record.expires, latest.expires));
+            if !is_tombstone(&record.value) && owned::validate(None, &record).is_ok() && latest {
+                pending.record = Some(record);
+            }
+        }
+
+        if step.last {
+            if let Some(pending) = self.delete_record.remove(&id) {
+                self.send_tombstone(pending, behaviour);
+            }
+        }
+    }
+
+    /// Replaces the record on all replicas found by the lookup with a tombstone
+    /// that expires together with the deleted record.
+    fn send_tombstone(&mut self, pending: PendingDelete, behaviour: &mut MyBehaviour) {
+        let PendingDelete {
+            key,
+            sender,
+            replicas,
+            local,
+            record,
+        } = pending;
+
+        let Some(record) = record else {
+            // Nothing to delete
+            let _ = sender.send(Ok(0));
+            return;
+        };
+
+        let local_peer_id = self.keypair().public().to_peer_id();
+        let owner = OwnedValue::decode(&record.value)
+            .and_then(Result::ok)
+            .map(|owned| owned.owner_id());
+        if let Some(owner) = owner.filter(|owner| *owner != local_peer_id) {
+            let _ = sender.send(Err(PutError::Rejected(ValidationError::NotOwner { owner })));
+            return;
+        }
+
+        let tombstone = Record {
+            key,
+            value: tombstone_value(),
+            publisher: Some(local_peer_id),
+            expires: record.expires,
+        };
+        let tombstone = match self.prepare_record(tombstone, owner.is_some(), behaviour) {
+            Ok(tombstone) => tombstone,
+            Err(e) => {
+                let _ = sender.send(Err(e));
+                return;
+            }
+        };
+
+        let local = if local {
+            match behaviour.kad.store_mut().put(tombstone.clone()) {
+                Ok(()) => 1,
+                Err(e) => {
+                    tracing::warn!(error = ?e, "Failed to store tombstone");
+                    0
+                }
+            }
+        } else {
+            0
+        };
+
+        self.announce_record(&tombstone, behaviour);
+
+        if replicas.is_empty() {
+            let _ = sender.send(Ok(local));
+            return;
+        }
+
+        let remote = replicas.len();
+        let query_id = behaviour
+            .kad
+            .put_record_to(tombstone, replicas.into_iter(), Quorum::All);
+        let _ = self.delete_record_put.insert(
+            query_id,
+            DeleteAcks {
+                sender,
+                local,
+                remote,
+            },
+        );
+    }
+
+    /// Counts the records found by a get record query and finishes the query
+    /// once the quorum is reached.
+    ///
+    /// If the query ends before the quorum is reached, the final result is replaced by
+    /// [`GetRecordError::QuorumFailed`].
+    fn check_record_quorum(
+        &mut self,
+        id: QueryId,
+        res: Result<GetRecordOk, GetRecordError>,
+        step: &ProgressStep,
+        behaviour: &mut MyBehaviour,
+    ) -> Result<GetRecordOk, GetRecordError> {
+        let Some(quorum) = self.get_record_quorum.get_mut(&id) else {
+            return res;
+        };
+
+        if let Ok(GetRecordOk::FoundRecord(_)) = &res {
+            quorum.found += 1;
+            if quorum.found == quorum.required.get() {
+                if let Some(mut query) = behaviour.kad.query_mut(&id) {
+                    query.finish();
+                }
+            }
+        }
+
+        if !step.last {
+            return res;
+        }
+
+        let Some(quorum) = self.get_record_quorum.remove(&id) else {
+            return res;
+        };
+
+        match res {
+            Ok(GetRecordOk::FinishedWithNoAdditionalRecord { .. })
+                if quorum.found < quorum.required.get() =>
+            {
+                Err(GetRecordError::QuorumFailed {
+                    key: quorum.key,
+                    records: Vec::new(),
+                    quorum: quorum.required,
+                })
+            }
+            res => res,
+        }
+    }
 }
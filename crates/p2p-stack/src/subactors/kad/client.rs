@@ -1,25 +1,35 @@
 use std::{convert::Infallible, time::Instant};
 
-use futures::stream::Stream;
+use futures::stream::{self, Stream, StreamExt as _};
 use libp2p::kad::{
     AddProviderError, AddProviderOk, BootstrapError, BootstrapOk, GetProvidersError,
-    GetProvidersOk, GetRecordError, GetRecordOk, PutRecordError, PutRecordOk, Quorum, Record,
-    RecordKey,
+    GetProvidersOk, GetRecordError, GetRecordOk, PutRecordOk, Quorum, Record, RecordKey,
 };
 use tokio::sync::{mpsc, oneshot};
-use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::wrappers::{BroadcastStream, ReceiverStream};
 
-use super::Command;
-use crate::client::{RequestError, RequestResult, SpecialClient};
+use super::{
+    owned,
+    watch::{decode_notification, watch_topic, WatchError, WatchEvent},
+    Command, PutError, ValidationError,
+};
+use crate::{
+    client::{RequestError, RequestResult, SpecialClient},
+    subactors::gossipsub,
+};
 
 #[derive(Clone)]
 pub struct Client {
     inner: SpecialClient<Command>,
+    gossipsub: gossipsub::Client,
 }
 
 impl From<SpecialClient<Command>> for Client {
     fn from(inner: SpecialClient<Command>) -> Self {
-        Self { inner }
+        Self {
+            gossipsub: SpecialClient::new(inner.sender.clone(), inner.peer_id).into(),
+            inner,
+        }
     }
 }
 
@@ -30,7 +40,8 @@ impl Client {
         value: Vec<u8>,
         expires: Option<Instant>,
         quorum: Quorum,
-    ) -> RequestResult<PutRecordOk, PutRecordError> {
+        owned: bool,
+    ) -> RequestResult<PutRecordOk, PutError> {
         let record = Record {
             key,
             value,
@@ -43,6 +54,7 @@ impl Client {
                 Command::PutRecord {
                     record,
                     quorum,
+                    owned,
                     sender,
                 },
                 receiver,
@@ -53,16 +65,21 @@ impl Client {
     pub async fn get_record(
         &self,
         key: RecordKey,
+        quorum: Quorum,
     ) -> Result<impl Stream<Item = Result<GetRecordOk, GetRecordError>>, RequestError> {
         let (sender, receiver) = mpsc::channel(10);
         self.inner
-            .send(Command::GetRecord { key, sender })
+            .send(Command::GetRecord {
+                key,
+                quorum,
+                sender,
+            })
             .await
             .map_err(RequestError::Send)?;
         Ok(ReceiverStream::new(receiver))
     }
 
-    pub async fn remove_record(&self, key: RecordKey) -> RequestResult<(), Infallible> {
+    pub async fn remove_record(&self, key: RecordKey) -> RequestResult<(), ValidationError> {
         let (sender, receiver) = oneshot::channel();
         self.inner
             .request(Command::RemoveRecord { key, sender }, receiver)
@@ -70,6 +87,67 @@ impl Client {
         Ok(())
     }
 
+    /// Deletes the record with the given key from all replicas in the DHT.
+    ///
+    /// Returns the number of replicas that acknowledged the delete.
+    pub async fn delete_record(&self, key: RecordKey) -> RequestResult<usize, PutError> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .request(Command::DeleteRecord { key, sender }, receiver)
+            .await
+    }
+
+    /// Watches the record with the given key for changes.
+    ///
+    /// Changes are received from puts of the local node, from records stored on behalf of
+    /// other peers and from the change notifications other nodes send when putting the record.
+    /// Owned records with an invalid signature are dropped.
+    pub async fn watch_record(
+        &self,
+        key: RecordKey,
+    ) -> Result<impl Stream<Item = WatchEvent>, WatchError> {
+        let notifications = self
+            .gossipsub
+            .get_topic(watch_topic(&key))
+            .subscribe()
+            .await?;
+
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .send(Command::WatchRecord {
+                key: key.clone(),
+                sender,
+            })
+            .await
+            .map_err(|e| WatchError::Request(RequestError::Send(e)))?;
+        let local = receiver
+            .await
+            .map_err(|e| WatchError::Request(RequestError::Oneshot(e)))?;
+
+        let local = BroadcastStream::new(local).map(|res| match res {
+            Ok(record) => WatchEvent::Changed(record),
+            Err(_) => WatchEvent::Lagged,
+        });
+
+        let remote = BroadcastStream::new(notifications).filter_map(move |res| {
+            let event = match res {
+                Ok(message) => decode_notification(&key, &message).map(WatchEvent::Changed),
+                Err(_) => Some(WatchEvent::Lagged),
+            };
+            async move { event }
+        });
+
+        Ok(stream::select(local, remote).filter(|event| {
+            let valid = match event {
+                WatchEvent::Changed(record) => owned::validate(None, record)
+                    .inspect_err(|e| tracing::debug!(error = %e, "Dropping invalid record"))
+                    .is_ok(),
+                WatchEvent::Lagged => true,
+            };
+            async move { valid }
+        }))
+    }
+
     pub async fn bootstrap(
         &self,
     ) -> Result<impl Stream<Item = Result<BootstrapOk, BootstrapError>>, RequestError> {
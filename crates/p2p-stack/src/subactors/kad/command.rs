@@ -2,10 +2,13 @@ use std::convert::Infallible;
 
 use libp2p::kad::{
     AddProviderError, AddProviderOk, BootstrapError, BootstrapOk, GetProvidersError,
-    GetProvidersOk, GetRecordError, GetRecordOk, PutRecordError, PutRecordOk, Quorum, Record,
-    RecordKey,
+    GetProvidersOk, GetRecordError, GetRecordOk, PutRecordOk, Quorum, Record, RecordKey,
 };
 
+use super::{PutError, ValidationError};
+
+use tokio::sync::{broadcast, oneshot};
+
 use crate::{
     command::{SendMultipleResult, SendResult},
     impl_from_special_command,
@@ -16,18 +19,48 @@ pub enum Command {
     Bootstrap {
         sender: SendMultipleResult<BootstrapOk, BootstrapError>,
     },
+    /// Stores a record in the DHT.
+    ///
+    /// If `owned` is set, the value is signed with the local keypair,
+    /// so that other peers can no longer overwrite or remove the record.
     PutRecord {
         record: Record,
         quorum: Quorum,
-        sender: SendResult<PutRecordOk, PutRecordError>,
+        owned: bool,
+        sender: SendResult<PutRecordOk, PutError>,
     },
+    /// Looks up a record in the DHT.
+    ///
+    /// The query is finished early as soon as `quorum` peers (including the local node)
+    /// returned the record. If the query ends with fewer records, it ends with
+    /// [`GetRecordError::QuorumFailed`] instead of
+    /// [`GetRecordOk::FinishedWithNoAdditionalRecord`].
     GetRecord {
         key: RecordKey,
+        quorum: Quorum,
         sender: SendMultipleResult<GetRecordOk, GetRecordError>,
     },
+    /// Removes a record from the local store.
+    ///
+    /// Fails if the record is owned by another peer.
     RemoveRecord {
         key: RecordKey,
-        sender: SendResult<(), Infallible>,
+        sender: SendResult<(), ValidationError>,
+    },
+    /// Deletes a record from all replicas in the DHT.
+    ///
+    /// The record is replaced by a tombstone that expires together with the deleted record.
+    /// The sender receives the number of replicas (including the local node)
+    /// that acknowledged the tombstone.
+    DeleteRecord {
+        key: RecordKey,
+        sender: SendResult<usize, PutError>,
+    },
+    /// Subscribes to the records put under `key` by the local node
+    /// and to the records stored on behalf of other peers.
+    WatchRecord {
+        key: RecordKey,
+        sender: oneshot::Sender<broadcast::Receiver<Record>>,
     },
     GetProviders {
         key: RecordKey,
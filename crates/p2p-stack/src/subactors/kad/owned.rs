@@ -0,0 +1,426 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use hyveos_core::dht::to_system_time;
+use libp2p::{
+    identity::{Keypair, PublicKey, SigningError},
+    kad::{Record, RecordKey},
+    PeerId,
+};
+
+use super::tombstone::{is_tombstone, outlives};
+
+/// Prefix of every owned record value, used to tell owned records apart from plain ones.
+const OWNED_RECORD_MAGIC: &[u8] = b"\0hyveos/owned/2\0";
+
+/// Domain separator for the signature of an owned record.
+const SIGNATURE_DOMAIN: &[u8] = b"hyveos-owned-record:";
+
+/// How far the local expiry of an owned record may deviate from its signed expiry.
+///
+/// The expiry is sent as a relative TTL, so clock skew between peers and
+/// transmission delays shift it slightly.
+const EXPIRY_TOLERANCE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ValidationError {
+    #[error("Owned record is malformed")]
+    Malformed,
+    #[error("Owned record has an invalid signature")]
+    InvalidSignature,
+    #[error("Record is owned by {owner} and can not be changed by another peer")]
+    NotOwner { owner: PeerId },
+    #[error("Record is owned by {owner} and can only be replaced by another owned record")]
+    NotSigned { owner: PeerId },
+    #[error("Record expires later than its owner signed it for")]
+    InvalidExpiry,
+    #[error("Record is older than the stored record of the same owner")]
+    Outdated,
+    #[error("Record was deleted")]
+    Deleted,
+}
+
+/// The value of a record in owned mode.
+///
+/// The value is signed together with the record key, a timestamp and the expiry
+/// by the owner's keypair.
+/// Once an owned record is stored, only newer records signed by the same owner can replace it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedValue {
+    pub owner: PublicKey,
+    /// Milliseconds since the Unix epoch at which the record was signed.
+    pub timestamp: u64,
+    /// Milliseconds since the Unix epoch at which the record expires, if it expires.
+    pub expires: Option<u64>,
+    pub value: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+/// A record whose publisher has been verified, if it is an owned record.
+#[derive(Debug, Clone)]
+pub struct VerifiedRecord {
+    pub value: Vec<u8>,
+    pub publisher: Option<PeerId>,
+    /// Whether the record is owned, in which case `publisher` is the verified owner.
+    pub owned: bool,
+    /// Whether the record is a tombstone of a deleted record.
+    pub deleted: bool,
+}
+
+impl OwnedValue {
+    pub fn sign(
+        keypair: &Keypair,
+        key: &RecordKey,
+        value: Vec<u8>,
+        expires: Option<Instant>,
+    ) -> Result<Self, SigningError> {
+        let timestamp = unix_millis(SystemTime::now());
+        // Zero marks a record without expiry in the encoding
+        let expires = expires.map(|expires| unix_millis(to_system_time(expires)).max(1));
+
+        let signature = keypair.sign(&signing_payload(key, timestamp, expires, &value))?;
+
+        Ok(Self {
+            owner: keypair.public(),
+            timestamp,
+            expires,
+            value,
+            signature,
+        })
+    }
+
+    pub fn owner_id(&self) -> PeerId {
+        self.owner.to_peer_id()
+    }
+
+    /// Verifies the signature of the value for the key of `record`
+    /// and checks that the record doesn't outlive the signed expiry.
+    pub fn verify(&self, record: &Record) -> Result<(), ValidationError> {
+        if !self.owner.verify(
+            &signing_payload(&record.key, self.timestamp, self.expires, &self.value),
+            &self.signature,
+        ) {
+            return Err(ValidationError::InvalidSignature);
+        }
+
+        let Some(expires) = self.expires else {
+            return Ok(());
+        };
+
+        // Replicas may store the record for a shorter time, but never longer
+        let limit = UNIX_EPOCH + Duration::from_millis(expires) + EXPIRY_TOLERANCE;
+        match record.expires {
+            Some(local) if to_system_time(local) <= limit => Ok(()),
+            _ => Err(ValidationError::InvalidExpiry),
+        }
+    }
+
+    /// Encodes the owned value into the bytes stored as the value of the DHT record.
+    pub fn encode(&self) -> Vec<u8> {
+        let owner = self.owner.encode_protobuf();
+
+        let mut bytes = Vec::with_capacity(
+            OWNED_RECORD_MAGIC.len()
+                + 4
+                + owner.len()
+                + 8
+                + 8
+                + 4
+                + self.signature.len()
+                + self.value.len(),
+        );
+        bytes.extend_from_slice(OWNED_RECORD_MAGIC);
+        write_chunk(&mut bytes, &owner);
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes.extend_from_slice(&self.expires.unwrap_or_default().to_be_bytes());
+        write_chunk(&mut bytes, &self.signature);
+        bytes.extend_from_slice(&self.value);
+        bytes
+    }
+
+    /// Decodes the value of a DHT record.
+    ///
+    /// Returns `None` if the value is not an owned value.
+    pub fn decode(bytes: &[u8]) -> Option<Result<Self, ValidationError>> {
+        let bytes = bytes.strip_prefix(OWNED_RECORD_MAGIC)?;
+
+        Some(Self::decode_inner(bytes).ok_or(ValidationError::Malformed))
+    }
+
+    fn decode_inner(bytes: &[u8]) -> Option<Self> {
+        let (owner, bytes) = read_chunk(bytes)?;
+        let owner = PublicKey::try_decode_protobuf(owner).ok()?;
+        let (timestamp, bytes) = bytes.split_first_chunk::<8>()?;
+        let (expires, bytes) = bytes.split_first_chunk::<8>()?;
+        let (signature, value) = read_chunk(bytes)?;
+
+        Some(Self {
+            owner,
+            timestamp: u64::from_be_bytes(*timestamp),
+            expires: Some(u64::from_be_bytes(*expires)).filter(|&expires| expires != 0),
+            value: value.to_vec(),
+            signature: signature.to_vec(),
+        })
+    }
+}
+
+/// Checks whether `new` may replace the currently stored record `existing`.
+///
+/// Owned records have to be signed correctly. Once an owned record is stored,
+/// it can only be replaced by a newer record signed by the same owner,
+/// or by a replica of the same record that doesn't expire earlier.
+/// A tombstone of a plain record can only be replaced by a record that expires after it.
+pub fn validate(existing: Option<&Record>, new: &Record) -> Result<(), ValidationError> {
+    let new_owned = OwnedValue::decode(&new.value).transpose()?;
+
+    if let Some(new_owned) = &new_owned {
+        new_owned.verify(new)?;
+    }
+
+    let Some(existing_owned) = existing
+        .and_then(|existing| OwnedValue::decode(&existing.value))
+        .and_then(Result::ok)
+    else {
+        return match existing {
+            Some(existing)
+                if is_tombstone(&existing.value)
+                    && !is_tombstone(&new.value)
+                    && !outlives(new.expires, existing.expires) =>
+            {
+                Err(ValidationError::Deleted)
+            }
+            _ => Ok(()),
+        };
+    };
+
+    let Some(new_owned) = new_owned else {
+        return Err(ValidationError::NotSigned {
+            owner: existing_owned.owner_id(),
+        });
+    };
+
+    if new_owned.owner != existing_owned.owner {
+        return Err(ValidationError::NotOwner {
+            owner: existing_owned.owner_id(),
+        });
+    }
+
+    if new_owned.timestamp < existing_owned.timestamp {
+        return Err(ValidationError::Outdated);
+    }
+
+    if new_owned.timestamp == existing_owned.timestamp
+        && (new_owned != existing_owned
+            || expires_earlier(new.expires, existing.and_then(|e| e.expires)))
+    {
+        return Err(ValidationError::Outdated);
+    }
+
+    Ok(())
+}
+
+/// Checks whether `peer` may remove the stored record `existing`.
+pub fn validate_removal(existing: &Record, peer: PeerId) -> Result<(), ValidationError> {
+    match OwnedValue::decode(&existing.value) {
+        Some(Ok(owned)) if owned.owner_id() != peer => Err(ValidationError::NotOwner {
+            owner: owned.owner_id(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Unwraps the value of a record and verifies its owner, if it is an owned record.
+pub fn verify_record(record: Record) -> Result<VerifiedRecord, ValidationError> {
+    match OwnedValue::decode(&record.value) {
+        Some(owned) => {
+            let owned = owned?;
+            owned.verify(&record)?;
+
+            Ok(VerifiedRecord {
+                publisher: Some(owned.owner_id()),
+                deleted: is_tombstone(&owned.value),
+                value: owned.value,
+                owned: true,
+            })
+        }
+        None => Ok(VerifiedRecord {
+            deleted: is_tombstone(&record.value),
+            value: record.value,
+            publisher: record.publisher,
+            owned: false,
+        }),
+    }
+}
+
+/// Checks whether a replica with the local expiry `new` would be dropped noticeably earlier
+/// than the stored replica with the local expiry `existing`.
+fn expires_earlier(new: Option<Instant>, existing: Option<Instant>) -> bool {
+    match (new, existing) {
+        (Some(new), Some(existing)) => new + EXPIRY_TOLERANCE < existing,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+        .try_into()
+        .unwrap_or(u64::MAX)
+}
+
+fn signing_payload(key: &RecordKey, timestamp: u64, expires: Option<u64>, value: &[u8]) -> Vec<u8> {
+    let mut payload =
+        Vec::with_capacity(SIGNATURE_DOMAIN.len() + 4 + key.as_ref().len() + 8 + 8 + value.len());
+    payload.extend_from_slice(SIGNATURE_DOMAIN);
+    write_chunk(&mut payload, key.as_ref());
+    payload.extend_from_slice(&timestamp.to_be_bytes());
+    payload.extend_from_slice(&expires.unwrap_or_default().to_be_bytes());
+    payload.extend_from_slice(value);
+    payload
+}
+
+fn write_chunk(bytes: &mut Vec<u8>, chunk: &[u8]) {
+    let len = u32::try_from(chunk.len()).expect("chunk too large");
+    bytes.extend_from_slice(&len.to_be_bytes());
+    bytes.extend_from_slice(chunk);
+}
+
+fn read_chunk(bytes: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (len, rest) = bytes.split_first_chunk::<4>()?;
+    let len = usize::try_from(u32::from_be_bytes(*len)).ok()?;
+
+    (rest.len() >= len).then(|| rest.split_at(len))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    fn owned_record(keypair: &Keypair, key: &RecordKey, value: &[u8]) -> Record {
+        let owned = OwnedValue::sign(keypair, key, value.to_vec(), None).unwrap();
+        Record::new(key.clone(), owned.encode())
+    }
+
+    #[test]
+    fn test_owned_record_roundtrip() {
+        let keypair = Keypair::generate_ed25519();
+        let key = RecordKey::new(&"config/interval");
+
+        let record = owned_record(&keypair, &key, b"10");
+        let verified = verify_record(record).unwrap();
+
+        assert!(verified.owned);
+        assert_eq!(verified.value, b"10");
+        assert_eq!(verified.publisher, Some(keypair.public().to_peer_id()));
+    }
+
+    #[test]
+    fn test_tampered_record_is_rejected() {
+        let keypair = Keypair::generate_ed25519();
+        let key = RecordKey::new(&"config/interval");
+
+        let mut record = owned_record(&keypair, &key, b"10");
+        *record.value.last_mut().unwrap() = b'1';
+
+        assert_eq!(
+            validate(None, &record),
+            Err(ValidationError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_overwrite_by_other_peer_is_rejected() {
+        let owner = Keypair::generate_ed25519();
+        let other = Keypair::generate_ed25519();
+        let key = RecordKey::new(&"config/interval");
+
+        let existing = owned_record(&owner, &key, b"10");
+
+        let plain = Record::new(key.clone(), b"20".to_vec());
+        assert!(matches!(
+            validate(Some(&existing), &plain),
+            Err(ValidationError::NotSigned { .. })
+        ));
+
+        let foreign = owned_record(&other, &key, b"20");
+        assert!(matches!(
+            validate(Some(&existing), &foreign),
+            Err(ValidationError::NotOwner { .. })
+        ));
+
+        // Records signed in the same millisecond count as replicas
+        std::thread::sleep(Duration::from_millis(2));
+        let update = owned_record(&owner, &key, b"20");
+        assert_eq!(validate(Some(&existing), &update), Ok(()));
+    }
+
+    #[test]
+    fn test_expiry_is_signed() {
+        let keypair = Keypair::generate_ed25519();
+        let key = RecordKey::new(&"config/interval");
+        let expires = Instant::now() + Duration::from_secs(600);
+
+        let owned = OwnedValue::sign(&keypair, &key, b"10".to_vec(), Some(expires)).unwrap();
+        let mut record = Record::new(key.clone(), owned.encode());
+        record.expires = Some(expires);
+        assert_eq!(validate(None, &record), Ok(()));
+
+        let mut never_expires = record.clone();
+        never_expires.expires = None;
+        assert_eq!(
+            validate(None, &never_expires),
+            Err(ValidationError::InvalidExpiry)
+        );
+
+        let mut extended = record.clone();
+        extended.expires = Some(expires + Duration::from_secs(3600));
+        assert_eq!(
+            validate(None, &extended),
+            Err(ValidationError::InvalidExpiry)
+        );
+
+        let mut tampered = OwnedValue::decode(&record.value).unwrap().unwrap();
+        tampered.expires = tampered.expires.map(|expires| expires + 3_600_000);
+        let mut tampered = Record::new(key, tampered.encode());
+        tampered.expires = extended.expires;
+        assert_eq!(
+            validate(None, &tampered),
+            Err(ValidationError::InvalidSignature)
+        );
+
+        // A replica may expire earlier, but must not shorten the stored replica
+        let mut shortened = record.clone();
+        shortened.expires = Some(Instant::now() + Duration::from_secs(1));
+        assert_eq!(validate(None, &shortened), Ok(()));
+        assert_eq!(
+            validate(Some(&record), &shortened),
+            Err(ValidationError::Outdated)
+        );
+        assert_eq!(validate(Some(&record), &record), Ok(()));
+    }
+
+    #[test]
+    fn test_tombstone_rejects_stale_replicas() {
+        use crate::subactors::kad::tombstone::tombstone_value;
+
+        let key = RecordKey::new(&"config/interval");
+        let expires = Instant::now() + Duration::from_secs(60);
+
+        let mut tombstone = Record::new(key.clone(), tombstone_value());
+        tombstone.expires = Some(expires);
+
+        let mut stale = Record::new(key.clone(), b"10".to_vec());
+        stale.expires = Some(expires);
+        assert_eq!(
+            validate(Some(&tombstone), &stale),
+            Err(ValidationError::Deleted)
+        );
+
+        let mut newer = Record::new(key, b"20".to_vec());
+        newer.expires = Some(expires + Duration::from_secs(60));
+        assert_eq!(validate(Some(&tombstone), &newer), Ok(()));
+    }
+}
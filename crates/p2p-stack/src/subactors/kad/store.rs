@@ -0,0 +1,483 @@
+use std::{
+    borrow::Cow,
+    sync::mpsc,
+    thread,
+    time::{Instant, SystemTime},
+};
+
+use hyveos_core::dht::to_system_time;
+use libp2p::{
+    kad::{
+        store::{self, MemoryStore, MemoryStoreConfig, RecordStore},
+        ProviderRecord, Record, RecordKey,
+    },
+    Multiaddr, PeerId,
+};
+use serde::{Deserialize, Serialize};
+
+/// The tables a [`StoreBackend`] has to provide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StoreTable {
+    Records,
+    Providers,
+}
+
+/// A change to an entry of a [`StoreBackend`] table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreChange {
+    Insert {
+        table: StoreTable,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Remove {
+        table: StoreTable,
+        key: Vec<u8>,
+    },
+}
+
+/// A persistent key-value backend for the Kademlia record store.
+///
+/// The backend only stores opaque bytes, serialization of the records is done by [`Store`].
+/// Changes are applied on a separate thread, so the backend may block.
+pub trait StoreBackend: Send + 'static {
+    fn load(&self, table: StoreTable) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>>;
+
+    /// Applies a batch of changes, ideally in a single transaction.
+    fn apply(&self, changes: Vec<StoreChange>) -> anyhow::Result<()>;
+}
+
+/// Configuration of the Kademlia record store.
+#[derive(Default)]
+pub struct StoreConfig {
+    pub limits: MemoryStoreConfig,
+    pub backend: Option<Box<dyn StoreBackend>>,
+}
+
+/// A Kademlia record store that keeps all records in memory
+/// and optionally mirrors every change to a persistent [`StoreBackend`].
+///
+/// When created with a backend, all records and provider records are loaded from the backend.
+/// Records that expired while the node was offline are removed from the backend instead.
+pub struct Store {
+    memory: MemoryStore,
+    writer: Option<BackendWriter>,
+}
+
+/// Applies the changes of a [`Store`] to its backend on a separate thread,
+/// so that disk writes don't block the swarm.
+///
+/// Changes that queue up while a batch is written are applied together as the next batch.
+/// Dropping the writer waits until all queued changes are applied.
+struct BackendWriter {
+    sender: Option<mpsc::Sender<StoreChange>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredRecord {
+    key: Vec<u8>,
+    value: Vec<u8>,
+    publisher: Option<PeerId>,
+    expires: Option<SystemTime>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredProvider {
+    key: Vec<u8>,
+    provider: PeerId,
+    expires: Option<SystemTime>,
+    addresses: Vec<Multiaddr>,
+}
+
+impl Store {
+    pub fn new(peer_id: PeerId, config: StoreConfig) -> Self {
+        let StoreConfig { limits, backend } = config;
+
+        let mut store = Self {
+            memory: MemoryStore::with_config(peer_id, limits),
+            writer: None,
+        };
+
+        if let Some(backend) = backend {
+            store.load(backend.as_ref());
+            store.writer = Some(BackendWriter::spawn(backend));
+        }
+
+        store
+    }
+
+    fn load(&mut self, backend: &dyn StoreBackend) {
+        let now = Instant::now();
+
+        let records = backend.load(StoreTable::Records).unwrap_or_else(|e| {
+            tracing::error!(error = ?e, "Failed to load kad records");
+            Vec::new()
+        });
+
+        let mut stale = Vec::new();
+        let mut expired = 0usize;
+        for (key, value) in records {
+            match cbor4ii::serde::from_slice::<StoredRecord>(&value) {
+                Ok(stored) => {
+                    let Some(record) = stored.into_record(now) else {
+                        expired += 1;
+                        stale.push(StoreChange::Remove {
+                            table: StoreTable::Records,
+                            key,
+                        });
+                        continue;
+                    };
+
+                    if let Err(e) = self.memory.put(record) {
+                        tracing::warn!(error = ?e, "Dropping persisted kad record");
+                        stale.push(StoreChange::Remove {
+                            table: StoreTable::Records,
+                            key,
+                        });
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(error = ?e, "Dropping malformed kad record");
+                    stale.push(StoreChange::Remove {
+                        table: StoreTable::Records,
+                        key,
+                    });
+                }
+            }
+        }
+
+        let providers = backend.load(StoreTable::Providers).unwrap_or_else(|e| {
+            tracing::error!(error = ?e, "Failed to load kad provider records");
+            Vec::new()
+        });
+
+        for (key, value) in providers {
+            match cbor4ii::serde::from_slice::<StoredProvider>(&value) {
+                Ok(stored) => {
+                    let Some(record) = stored.into_provider_record(now) else {
+                        expired += 1;
+                        stale.push(StoreChange::Remove {
+                            table: StoreTable::Providers,
+                            key,
+                        });
+                        continue;
+                    };
+
+                    if let Err(e) = self.memory.add_provider(record) {
+                        tracing::warn!(error = ?e, "Dropping persisted kad provider record");
+                        stale.push(StoreChange::Remove {
+                            table: StoreTable::Providers,
+                            key,
+                        });
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(error = ?e, "Dropping malformed kad provider record");
+                    stale.push(StoreChange::Remove {
+                        table: StoreTable::Providers,
+                        key,
+                    });
+                }
+            }
+        }
+
+        if !stale.is_empty() {
+            apply_to_backend(backend, stale);
+        }
+
+        tracing::debug!(
+            records = self.memory.records().count(),
+            provided = self.memory.provided().count(),
+            expired,
+            "Loaded persisted kad store"
+        );
+    }
+
+    fn persist(&self, table: StoreTable, key: Vec<u8>, value: &impl Serialize) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+
+        match cbor4ii::serde::to_vec(Vec::new(), value) {
+            Ok(value) => writer.send(StoreChange::Insert { table, key, value }),
+            Err(e) => tracing::error!(error = ?e, ?table, "Failed to serialize kad store entry"),
+        }
+    }
+
+    fn unpersist(&self, table: StoreTable, key: Vec<u8>) {
+        if let Some(writer) = &self.writer {
+            writer.send(StoreChange::Remove { table, key });
+        }
+    }
+}
+
+impl BackendWriter {
+    fn spawn(backend: Box<dyn StoreBackend>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        let thread = thread::Builder::new()
+            .name("kad-store".to_string())
+            .spawn(move || {
+                while let Ok(change) = receiver.recv() {
+                    let mut changes = vec![change];
+                    changes.extend(receiver.try_iter());
+                    apply_to_backend(backend.as_ref(), changes);
+                }
+            })
+            .expect("Failed to spawn kad store thread");
+
+        Self {
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+
+    fn send(&self, change: StoreChange) {
+        if let Some(sender) = &self.sender {
+            if sender.send(change).is_err() {
+                tracing::error!("Kad store thread stopped, dropping change");
+            }
+        }
+    }
+}
+
+impl Drop for BackendWriter {
+    fn drop(&mut self) {
+        // Closing the channel lets the thread finish once all queued changes are applied
+        self.sender.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl RecordStore for Store {
+    type RecordsIter<'a> = <MemoryStore as RecordStore>::RecordsIter<'a>;
+    type ProvidedIter<'a> = <MemoryStore as RecordStore>::ProvidedIter<'a>;
+
+    fn get(&self, k: &RecordKey) -> Option<Cow<'_, Record>> {
+        self.memory.get(k)
+    }
+
+    fn put(&mut self, r: Record) -> store::Result<()> {
+        let stored = StoredRecord::from(&r);
+        self.memory.put(r)?;
+        self.persist(StoreTable::Records, stored.key.clone(), &stored);
+        Ok(())
+    }
+
+    fn remove(&mut self, k: &RecordKey) {
+        self.memory.remove(k);
+        self.unpersist(StoreTable::Records, k.to_vec());
+    }
+
+    fn records(&self) -> Self::RecordsIter<'_> {
+        self.memory.records()
+    }
+
+    fn add_provider(&mut self, record: ProviderRecord) -> store::Result<()> {
+        let stored = StoredProvider::from(&record);
+        self.memory.add_provider(record)?;
+        self.persist(
+            StoreTable::Providers,
+            provider_key(&stored.key, &stored.provider),
+            &stored,
+        );
+        Ok(())
+    }
+
+    fn providers(&self, key: &RecordKey) -> Vec<ProviderRecord> {
+        self.memory.providers(key)
+    }
+
+    fn provided(&self) -> Self::ProvidedIter<'_> {
+        self.memory.provided()
+    }
+
+    fn remove_provider(&mut self, k: &RecordKey, p: &PeerId) {
+        self.memory.remove_provider(k, p);
+        self.unpersist(StoreTable::Providers, provider_key(k.as_ref(), p));
+    }
+}
+
+impl From<&Record> for StoredRecord {
+    fn from(record: &Record) -> Self {
+        Self {
+            key: record.key.to_vec(),
+            value: record.value.clone(),
+            publisher: record.publisher,
+            expires: record.expires.map(to_system_time),
+        }
+    }
+}
+
+impl StoredRecord {
+    fn into_record(self, now: Instant) -> Option<Record> {
+        let expires = match self.expires {
+            Some(expires) => Some(to_instant(expires, now)?),
+            None => None,
+        };
+
+        Some(Record {
+            key: RecordKey::new(&self.key),
+            value: self.value,
+            publisher: self.publisher,
+            expires,
+        })
+    }
+}
+
+impl From<&ProviderRecord> for StoredProvider {
+    fn from(record: &ProviderRecord) -> Self {
+        Self {
+            key: record.key.to_vec(),
+            provider: record.provider,
+            expires: record.expires.map(to_system_time),
+            addresses: record.addresses.clone(),
+        }
+    }
+}
+
+impl StoredProvider {
+    fn into_provider_record(self, now: Instant) -> Option<ProviderRecord> {
+        let expires = match self.expires {
+            Some(expires) => Some(to_instant(expires, now)?),
+            None => None,
+        };
+
+        Some(ProviderRecord {
+            key: RecordKey::new(&self.key),
+            provider: self.provider,
+            expires,
+            addresses: self.addresses,
+        })
+    }
+}
+
+fn provider_key(key: &[u8], provider: &PeerId) -> Vec<u8> {
+    let mut bytes = key.to_vec();
+    bytes.extend(provider.to_bytes());
+    bytes
+}
+
+fn apply_to_backend(backend: &dyn StoreBackend, changes: Vec<StoreChange>) {
+    let count = changes.len();
+    if let Err(e) = backend.apply(changes) {
+        tracing::error!(error = ?e, count, "Failed to apply kad store changes");
+    }
+}
+
+/// Converts a wall clock deadline back into a monotonic one.
+///
+/// Returns `None` if the deadline already passed.
+fn to_instant(time: SystemTime, now: Instant) -> Option<Instant> {
+    time.duration_since(SystemTime::now())
+        .ok()
+        .map(|remaining| now + remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::*;
+
+    type Entries = HashMap<(StoreTable, Vec<u8>), Vec<u8>>;
+
+    #[derive(Clone, Default)]
+    struct MapBackend(Arc<Mutex<Entries>>);
+
+    impl StoreBackend for MapBackend {
+        fn load(&self, table: StoreTable) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+            Ok(self
+                .0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|((t, _), _)| *t == table)
+                .map(|((_, k), v)| (k.clone(), v.clone()))
+                .collect())
+        }
+
+        fn apply(&self, changes: Vec<StoreChange>) -> anyhow::Result<()> {
+            let mut map = self.0.lock().unwrap();
+            for change in changes {
+                match change {
+                    StoreChange::Insert { table, key, value } => {
+                        map.insert((table, key), value);
+                    }
+                    StoreChange::Remove { table, key } => {
+                        map.remove(&(table, key));
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn store(peer_id: PeerId, backend: &MapBackend) -> Store {
+        Store::new(
+            peer_id,
+            StoreConfig {
+                limits: MemoryStoreConfig::default(),
+                backend: Some(Box::new(backend.clone())),
+            },
+        )
+    }
+
+    #[test]
+    fn test_records_survive_restart() {
+        let peer_id = PeerId::random();
+        let backend = MapBackend::default();
+
+        let mut first = store(peer_id, &backend);
+        let mut record = Record::new(RecordKey::new(&"key"), b"value".to_vec());
+        record.publisher = Some(peer_id);
+        record.expires = Some(Instant::now() + Duration::from_secs(60));
+        first.put(record).unwrap();
+        first
+            .add_provider(ProviderRecord::new(
+                RecordKey::new(&"file"),
+                peer_id,
+                Vec::new(),
+            ))
+            .unwrap();
+        drop(first);
+
+        let second = store(peer_id, &backend);
+        let record = second.get(&RecordKey::new(&"key")).unwrap();
+        assert_eq!(record.value, b"value");
+        assert_eq!(record.publisher, Some(peer_id));
+        assert!(record.expires.is_some());
+        assert_eq!(second.providers(&RecordKey::new(&"file")).len(), 1);
+    }
+
+    #[test]
+    fn test_expired_records_are_dropped_on_startup() {
+        let peer_id = PeerId::random();
+        let backend = MapBackend::default();
+
+        let expired = StoredRecord {
+            key: b"key".to_vec(),
+            value: b"value".to_vec(),
+            publisher: None,
+            expires: Some(SystemTime::now() - Duration::from_secs(1)),
+        };
+        backend
+            .apply(vec![StoreChange::Insert {
+                table: StoreTable::Records,
+                key: expired.key.clone(),
+                value: cbor4ii::serde::to_vec(Vec::new(), &expired).unwrap(),
+            }])
+            .unwrap();
+
+        let store = store(peer_id, &backend);
+        assert!(store.get(&RecordKey::new(&"key")).is_none());
+        assert!(backend.load(StoreTable::Records).unwrap().is_empty());
+    }
+}
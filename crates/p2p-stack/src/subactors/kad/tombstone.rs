@@ -0,0 +1,27 @@
+use std::time::Instant;
+
+/// The value of a record that was deleted.
+///
+/// Tombstones replace the deleted record on all replicas and expire together with it,
+/// so that stale replicas can't republish the deleted record in the meantime.
+const TOMBSTONE: &[u8] = b"\0hyveos/tombstone/1\0";
+
+pub fn is_tombstone(value: &[u8]) -> bool {
+    value == TOMBSTONE
+}
+
+pub(super) fn tombstone_value() -> Vec<u8> {
+    TOMBSTONE.to_vec()
+}
+
+/// Checks whether a record with the expiry `new` is newer than a tombstone with the expiry
+/// `tombstone`, i.e. whether it was put after the deleted record.
+///
+/// A record without an expiry is put with the default record TTL and therefore counts as newer.
+pub(super) fn outlives(new: Option<Instant>, tombstone: Option<Instant>) -> bool {
+    match (new, tombstone) {
+        (None, _) => true,
+        (Some(_), None) => false,
+        (Some(new), Some(tombstone)) => new > tombstone,
+    }
+}
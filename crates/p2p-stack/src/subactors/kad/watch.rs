@@ -0,0 +1,76 @@
+use std::time::{Instant, SystemTime};
+
+use base64_simd::URL_SAFE_NO_PAD;
+use hyveos_core::pub_sub::ReceivedMessage;
+use libp2p::{
+    gossipsub::{IdentTopic, SubscriptionError},
+    kad::{Record, RecordKey},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::client::RequestError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WatchError {
+    #[error("Failed to subscribe to change notifications: {0}")]
+    Subscription(#[from] RequestError<SubscriptionError>),
+    #[error("Failed to watch local changes: {0}")]
+    Request(#[from] RequestError),
+}
+
+/// A change of a watched record.
+#[derive(Debug, Clone)]
+pub enum WatchEvent {
+    /// The record was put, either by the local node or by another peer in the network.
+    Changed(Record),
+    /// Changes were dropped because the watcher didn't keep up.
+    ///
+    /// The current value of the record should be read from the DHT again.
+    Lagged,
+}
+
+/// Change notification sent on the gossipsub side channel of a key.
+#[derive(Serialize, Deserialize)]
+struct Notification {
+    value: Vec<u8>,
+    expires: Option<SystemTime>,
+}
+
+/// The gossipsub topic on which changes of the record with the given key are announced.
+pub fn watch_topic(key: &RecordKey) -> IdentTopic {
+    IdentTopic::new(format!("kv/{}", URL_SAFE_NO_PAD.encode_to_string(key)))
+}
+
+pub(super) fn encode_notification(record: &Record) -> Option<Vec<u8>> {
+    let notification = Notification {
+        value: record.value.clone(),
+        expires: record
+            .expires
+            .map(|expires| SystemTime::now() + expires.saturating_duration_since(Instant::now())),
+    };
+
+    cbor4ii::serde::to_vec(Vec::new(), &notification)
+        .inspect_err(|e| tracing::warn!(error = ?e, "Failed to encode kv change notification"))
+        .ok()
+}
+
+/// Decodes a change notification received on the side channel of `key`.
+///
+/// The publisher of the record is the (signed) source of the gossipsub message.
+pub(super) fn decode_notification(key: &RecordKey, message: &ReceivedMessage) -> Option<Record> {
+    let Notification { value, expires } = cbor4ii::serde::from_slice(&message.message.data)
+        .inspect_err(|e| tracing::debug!(error = ?e, "Ignoring malformed kv change notification"))
+        .ok()?;
+
+    Some(Record {
+        key: key.clone(),
+        value,
+        publisher: message.source,
+        expires: expires.map(|expires| {
+            Instant::now()
+                + expires
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default()
+        }),
+    })
+}
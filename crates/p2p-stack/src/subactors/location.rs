@@ -1,4 +1,6 @@
+use hyveos_core::location::Location;
 use libp2p::{
+    gossipsub::IdentTopic,
     request_response::{cbor, Config, ProtocolSupport},
     swarm::NetworkBehaviour,
     StreamProtocol,
@@ -6,19 +8,15 @@ use libp2p::{
 use serde::{Deserialize, Serialize};
 
 pub use self::{
-    actor::{Actor, CommandError, EventError},
+    actor::{Actor, CommandError, EventError, NearbyError},
+    client::Client,
     command::Command,
 };
 
 mod actor;
 mod client;
 mod command;
-
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
-pub struct Location {
-    pub lat: f64,
-    pub lon: f64,
-}
+mod table;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Request {
@@ -42,3 +40,8 @@ pub fn new() -> Behaviour {
         Config::default(),
     )
 }
+
+/// The gossipsub topic on which peers announce their location when it changes.
+pub fn updates_topic() -> IdentTopic {
+    IdentTopic::new("location/updates")
+}
@@ -1,7 +1,28 @@
-use crate::actor::SubActor;
+use std::collections::HashMap;
+
+use hyveos_core::location::{Location, PeerLocation};
+use libp2p::{
+    identity::Keypair,
+    request_response::{Event, Message, OutboundFailure, OutboundRequestId},
+    PeerId,
+};
+
+use super::{table::LocationTable, updates_topic, Command, Request, Response};
+use crate::{actor::SubActor, behaviour::MyBehaviour, command::SendResult};
 
 #[derive(Debug, Default)]
-pub struct Actor {}
+pub struct Actor {
+    peer_id: Option<PeerId>,
+    location: Option<Location>,
+    location_requests: HashMap<OutboundRequestId, SendResult<Option<Location>, OutboundFailure>>,
+    table: LocationTable,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NearbyError {
+    #[error("The location of the local node is not set")]
+    UnknownLocation,
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum EventError {}
@@ -14,4 +35,113 @@ impl SubActor for Actor {
     type SubCommand = super::Command;
     type EventError = EventError;
     type CommandError = CommandError;
+
+    fn new(keypair: &Keypair) -> Self {
+        Self {
+            peer_id: Some(keypair.public().to_peer_id()),
+            ..Default::default()
+        }
+    }
+
+    fn handle_command(
+        &mut self,
+        command: Self::SubCommand,
+        behaviour: &mut MyBehaviour,
+    ) -> Result<(), Self::CommandError> {
+        match command {
+            Command::SetLocation(location) => {
+                self.location = Some(location);
+                self.announce_location(behaviour);
+            }
+            Command::AnnounceLocation => self.announce_location(behaviour),
+            Command::CacheLocation(PeerLocation { peer_id, location }) => {
+                self.table.insert(peer_id, location);
+            }
+            Command::GetNearbyPeers { query, sender } => {
+                let center = query
+                    .center
+                    .or_else(|| self.location.map(|location| location.point()))
+                    .ok_or(NearbyError::UnknownLocation);
+
+                let _ = sender.send(
+                    center.map(|center| self.table.nearby(center, query.radius, query.limit)),
+                );
+            }
+            Command::GetLocation { peer, sender } => {
+                if Some(peer) == self.peer_id {
+                    let _ = sender.send(Ok(self.location));
+                } else {
+                    let request_id = behaviour.location.send_request(&peer, Request::GetLocation);
+                    self.location_requests.insert(request_id, sender);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_event(
+        &mut self,
+        event: Self::Event,
+        behaviour: &mut MyBehaviour,
+    ) -> Result<(), Self::EventError> {
+        match event {
+            Event::Message {
+                peer,
+                message: Message::Request { channel, .. },
+            } => {
+                tracing::trace!(%peer, "Received location request");
+
+                if behaviour
+                    .location
+                    .send_response(channel, Response::Location(self.location))
+                    .is_err()
+                {
+                    tracing::debug!(%peer, "Failed to respond to location request");
+                }
+            }
+            Event::Message {
+                message:
+                    Message::Response {
+                        request_id,
+                        response: Response::Location(location),
+                    },
+                ..
+            } => {
+                if let Some(sender) = self.location_requests.remove(&request_id) {
+                    let _ = sender.send(Ok(location));
+                }
+            }
+            Event::OutboundFailure {
+                request_id, error, ..
+            } => {
+                if let Some(sender) = self.location_requests.remove(&request_id) {
+                    let _ = sender.send(Err(error));
+                }
+            }
+            e => {
+                tracing::debug!("Unhandled event: {e:?}");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Actor {
+    fn announce_location(&self, behaviour: &mut MyBehaviour) {
+        let Some(location) = self.location else {
+            return;
+        };
+
+        match cbor4ii::serde::to_vec(Vec::new(), &location) {
+            Ok(data) => {
+                if let Err(e) = behaviour.gossipsub.publish(updates_topic(), data) {
+                    // No other peer being connected yet is the common case
+                    tracing::trace!(error = %e, "Failed to announce location");
+                }
+            }
+            Err(e) => tracing::warn!(error = ?e, "Failed to encode location"),
+        }
+    }
 }
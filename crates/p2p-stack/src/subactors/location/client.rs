@@ -1,13 +1,136 @@
-use super::Command;
-use crate::client::SpecialClient;
+use std::{pin::pin, time::Duration};
 
+use futures::{Stream, StreamExt as _};
+use hyveos_core::location::{Location, NearbyPeer, NearbyQuery, PeerLocation};
+use libp2p::{gossipsub::SubscriptionError, request_response::OutboundFailure, PeerId};
+use tokio::sync::oneshot;
+use tokio_stream::wrappers::BroadcastStream;
+
+use super::{updates_topic, Command, NearbyError};
+use crate::{
+    client::{RequestError, RequestResult, SpecialClient},
+    subactors::gossipsub,
+};
+
+/// How often the location of the local node is reannounced.
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone)]
 pub struct Client {
-    #[allow(dead_code)]
     inner: SpecialClient<Command>,
+    gossipsub: gossipsub::Client,
 }
 
 impl From<SpecialClient<Command>> for Client {
     fn from(inner: SpecialClient<Command>) -> Self {
-        Self { inner }
+        Self {
+            gossipsub: SpecialClient::new(inner.sender.clone(), inner.peer_id).into(),
+            inner,
+        }
+    }
+}
+
+impl Client {
+    /// Sets the location of the local node and announces it to subscribed peers.
+    pub async fn set_location(&self, location: Location) -> Result<(), RequestError> {
+        self.inner
+            .send(Command::SetLocation(location))
+            .await
+            .map_err(RequestError::Send)
+    }
+
+    /// Gets the last location the given peer set, if any.
+    ///
+    /// The location of the local node is returned without a network request.
+    pub async fn get_location(
+        &self,
+        peer: PeerId,
+    ) -> RequestResult<Option<Location>, OutboundFailure> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .request(Command::GetLocation { peer, sender }, receiver)
+            .await
+    }
+
+    /// Subscribes to the location updates of other peers.
+    ///
+    /// If `peer` is set, only updates of that peer are received.
+    /// Updates with an invalid location are dropped.
+    pub async fn subscribe(
+        &self,
+        peer: Option<PeerId>,
+    ) -> Result<impl Stream<Item = PeerLocation>, RequestError<SubscriptionError>> {
+        let updates = self
+            .gossipsub
+            .get_topic(updates_topic())
+            .subscribe()
+            .await?;
+
+        Ok(BroadcastStream::new(updates).filter_map(move |res| {
+            let update = res
+                .inspect_err(|e| tracing::warn!(error = %e, "Location updates lagged behind"))
+                .ok()
+                .and_then(|message| {
+                    let peer_id = message.source?;
+                    if peer.is_some_and(|peer| peer != peer_id) {
+                        return None;
+                    }
+
+                    let location = cbor4ii::serde::from_slice::<Location>(&message.message.data)
+                        .ok()
+                        .filter(|location| location.validate().is_ok());
+                    if location.is_none() {
+                        tracing::debug!(%peer_id, "Ignoring malformed location update");
+                    }
+
+                    location.map(|location| PeerLocation { peer_id, location })
+                });
+
+            async move { update }
+        }))
+    }
+
+    /// Gets the peers near a point from the location table, sorted by distance.
+    ///
+    /// If the query has no center, the location of the local node is used.
+    pub async fn get_nearby_peers(
+        &self,
+        query: NearbyQuery,
+    ) -> RequestResult<Vec<NearbyPeer>, NearbyError> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .request(Command::GetNearbyPeers { query, sender }, receiver)
+            .await
+    }
+
+    /// Keeps the location table up to date.
+    ///
+    /// Stores the location updates of other peers in the location table and regularly
+    /// reannounces the location of the local node, so that peers that joined later learn it.
+    /// Runs until the actor stops.
+    pub async fn cache_locations(self) {
+        let updates = match self.subscribe(None).await {
+            Ok(updates) => updates,
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to subscribe to location updates");
+                return;
+            }
+        };
+        let mut updates = pin!(updates);
+        let mut interval = tokio::time::interval(ANNOUNCE_INTERVAL);
+
+        loop {
+            let command = tokio::select! {
+                update = updates.next() => match update {
+                    Some(update) => Command::CacheLocation(update),
+                    None => break,
+                },
+                _ = interval.tick() => Command::AnnounceLocation,
+            };
+
+            if self.inner.send(command).await.is_err() {
+                break;
+            }
+        }
     }
 }
@@ -1,16 +1,24 @@
-use libp2p::PeerId;
-use tokio::sync::oneshot;
+use hyveos_core::location::{Location, NearbyPeer, NearbyQuery, PeerLocation};
+use libp2p::{request_response::OutboundFailure, PeerId};
 
-use super::Location;
-use crate::impl_from_special_command;
+use super::NearbyError;
+use crate::{command::SendResult, impl_from_special_command};
 
 /// Commands that the client can send to the actor.
 #[derive(Debug)]
 pub enum Command {
-    #[allow(dead_code)]
+    SetLocation(Location),
     GetLocation {
         peer: PeerId,
-        location: oneshot::Sender<Option<Location>>,
+        sender: SendResult<Option<Location>, OutboundFailure>,
+    },
+    /// Announces the location of the local node again, if it is set.
+    AnnounceLocation,
+    /// Stores a location update of another peer in the location table.
+    CacheLocation(PeerLocation),
+    GetNearbyPeers {
+        query: NearbyQuery,
+        sender: SendResult<Vec<NearbyPeer>, NearbyError>,
     },
 }
 
@@ -0,0 +1,117 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
+
+use hyveos_core::location::{GeoPoint, Location, NearbyPeer};
+use libp2p::PeerId;
+
+/// How long the location of a peer is kept after the last update from it.
+///
+/// Peers reannounce their location regularly, so this is only reached if a peer left.
+const ENTRY_TTL: Duration = Duration::from_secs(3 * 60);
+
+/// The last known locations of other peers, as received from their location updates.
+#[derive(Debug, Default)]
+pub struct LocationTable {
+    entries: HashMap<PeerId, (Location, Instant)>,
+}
+
+impl LocationTable {
+    /// Stores the location of a peer, unless a location measured later is already known.
+    pub fn insert(&mut self, peer_id: PeerId, location: Location) {
+        let now = Instant::now();
+
+        match self.entries.entry(peer_id) {
+            Entry::Occupied(mut entry) => {
+                let (known, received) = entry.get_mut();
+                if known.timestamp <= location.timestamp {
+                    *known = location;
+                }
+                *received = now;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((location, now));
+            }
+        }
+    }
+
+    /// Returns the peers around `center`, sorted by distance.
+    pub fn nearby(
+        &mut self,
+        center: GeoPoint,
+        radius: Option<f64>,
+        limit: Option<NonZeroUsize>,
+    ) -> Vec<NearbyPeer> {
+        self.entries
+            .retain(|_, (_, received)| received.elapsed() < ENTRY_TTL);
+
+        let mut peers = self
+            .entries
+            .iter()
+            .map(|(peer_id, (location, _))| NearbyPeer {
+                peer_id: *peer_id,
+                location: *location,
+                distance: center.distance(&location.point()),
+            })
+            .filter(|peer| radius.map_or(true, |radius| peer.distance <= radius))
+            .collect::<Vec<_>>();
+
+        peers.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+
+        if let Some(limit) = limit {
+            peers.truncate(limit.get());
+        }
+
+        peers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(latitude: f64, longitude: f64) -> Location {
+        Location::new(latitude, longitude).unwrap()
+    }
+
+    #[test]
+    fn test_nearby_peers_are_sorted_and_filtered() {
+        let center = GeoPoint::new(48.0, 11.0).unwrap();
+        let near = PeerId::random();
+        let nearer = PeerId::random();
+        let far = PeerId::random();
+
+        let mut table = LocationTable::default();
+        // Roughly 111 m and 11 m north of the center
+        table.insert(near, location(48.001, 11.0));
+        table.insert(nearer, location(48.0001, 11.0));
+        table.insert(far, location(49.0, 11.0));
+
+        let peers = table.nearby(center, Some(200.0), None);
+        assert_eq!(
+            peers.iter().map(|peer| peer.peer_id).collect::<Vec<_>>(),
+            [nearer, near]
+        );
+
+        let peers = table.nearby(center, None, NonZeroUsize::new(1));
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].peer_id, nearer);
+    }
+
+    #[test]
+    fn test_outdated_location_is_ignored() {
+        let peer_id = PeerId::random();
+        let center = GeoPoint::new(48.0, 11.0).unwrap();
+
+        let mut table = LocationTable::default();
+        let newer = location(48.0, 11.0);
+        let older = location(49.0, 11.0).with_timestamp(newer.timestamp - Duration::from_secs(1));
+
+        table.insert(peer_id, newer);
+        table.insert(peer_id, older);
+
+        assert_eq!(table.nearby(center, None, None)[0].location, newer);
+    }
+}
@@ -1,30 +1,58 @@
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
+use asynchronous_codec::{CborCodec, CborCodecError, Framed};
+use futures::{
+    sink::SinkExt as _,
+    stream::{self, BoxStream},
+    Stream, StreamExt as _,
+};
 use hyveos_core::{
-    debug::{MessageDebugEventType, RequestDebugEvent, ResponseDebugEvent},
+    debug::{MessageDebugEventType, PendingRequest, RequestDebugEvent, ResponseDebugEvent},
     req_resp::{self, InboundRequest, Response, ResponseError, TopicQuery},
 };
 use libp2p::{
+    identity::Keypair,
+    kad::RecordKey,
     request_response::{
-        cbor, Config, Event, InboundRequestId, Message, OutboundRequestId, ProtocolSupport,
-        ResponseChannel,
+        cbor, Config, Event, InboundRequestId, Message, OutboundFailure, OutboundRequestId,
+        ProtocolSupport, ResponseChannel,
     },
     swarm::NetworkBehaviour,
     PeerId, StreamProtocol,
 };
+use libp2p_stream::{Control, OpenStreamError};
 use serde::{Deserialize, Serialize};
 use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
 use ulid::Ulid;
 
+pub use self::responder::StreamResponder;
+
 use crate::{
     actor::SubActor,
     behaviour::MyBehaviour,
     client::{RequestError, SpecialClient},
     impl_from_special_command,
+    providers::ProviderLookup,
 };
 
-// TODO: lower timeout (requires changes to file_transfer actor)
-const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+mod responder;
+
+/// The maximum time a request can take, callers can choose a shorter timeout per request.
+pub const MAX_REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The protocol for requests with a stream of responses, on the stream behaviour that is shared
+/// with file transfer.
+const STREAM_PROTOCOL: StreamProtocol = StreamProtocol::new("/req_resp/stream/1.0.0");
+
+/// The number of response chunks that are buffered before the responder has to wait.
+const CHUNK_BUFFER: usize = 16;
+
+/// How often inbound requests are checked for passed deadlines.
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Request {
@@ -37,21 +65,82 @@ pub type Behaviour = cbor::Behaviour<Request, Response>;
 pub fn new() -> Behaviour {
     cbor::Behaviour::new(
         [(StreamProtocol::new("/req_resp"), ProtocolSupport::Full)],
-        Config::default().with_request_timeout(REQUEST_TIMEOUT),
+        Config::default().with_request_timeout(MAX_REQUEST_TIMEOUT),
     )
 }
 
+/// A frame of a streaming response, which consists of any number of chunks and an end marker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ResponseFrame {
+    Chunk(Response),
+    End,
+}
+
 #[derive(Debug, Hash, PartialEq, Eq)]
 pub struct SubscriptionId(u64);
 
+/// A subscription of an app to inbound requests.
+#[derive(Debug)]
+struct RequestSubscription {
+    query: Option<TopicQuery>,
+    sender: mpsc::Sender<InboundRequest>,
+    /// The time the subscriber has to respond before the request is answered with an error.
+    response_timeout: Duration,
+}
+
+impl RequestSubscription {
+    fn matches(&self, topic: Option<&String>) -> bool {
+        match (self.query.as_ref(), topic) {
+            (Some(query), Some(topic)) => query.matches(topic),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+/// The cached providers of a service for anycast calls.
+#[derive(Debug)]
+struct ProviderCache {
+    neighbours: Vec<PeerId>,
+    others: Vec<PeerId>,
+    refreshed: Instant,
+    next: usize,
+}
+
+impl ProviderCache {
+    /// Returns the providers in the order they should be tried, neighbours first.
+    ///
+    /// Each call starts at the next provider, so the load is spread across all of them.
+    fn candidates(&mut self) -> Vec<PeerId> {
+        let next = self.next;
+        self.next = self.next.wrapping_add(1);
+
+        rotated(&self.neighbours, next)
+            .chain(rotated(&self.others, next))
+            .collect()
+    }
+}
+
+/// Iterates over the peers, starting at the given position and wrapping around.
+fn rotated(peers: &[PeerId], start: usize) -> impl Iterator<Item = PeerId> + '_ {
+    let mid = if peers.is_empty() {
+        0
+    } else {
+        start % peers.len()
+    };
+    peers[mid..].iter().chain(&peers[..mid]).copied()
+}
+
 pub enum Command {
     Request {
         peer_id: PeerId,
         req: req_resp::Request,
+        timeout: Duration,
         sender: oneshot::Sender<Response>,
     },
     Subscribe {
         query: Option<TopicQuery>,
+        response_timeout: Duration,
         sender: oneshot::Sender<(SubscriptionId, mpsc::Receiver<InboundRequest>)>,
     },
     Unsubscribe(SubscriptionId),
@@ -59,6 +148,34 @@ pub enum Command {
         id: u64,
         response: Response,
     },
+    GetControl(oneshot::Sender<Control>),
+    StreamRequest {
+        peer_id: PeerId,
+        req: req_resp::Request,
+        sender: mpsc::Sender<ResponseFrame>,
+    },
+    GetResponseStream {
+        id: u64,
+        end: bool,
+        sender: oneshot::Sender<Option<mpsc::Sender<ResponseFrame>>>,
+    },
+    GetCachedProviders {
+        key: RecordKey,
+        max_age: Duration,
+        sender: oneshot::Sender<Option<Vec<PeerId>>>,
+    },
+    CacheProviders {
+        key: RecordKey,
+        neighbours: Vec<PeerId>,
+        others: Vec<PeerId>,
+        sender: oneshot::Sender<Vec<PeerId>>,
+    },
+    EvictProvider {
+        key: RecordKey,
+        peer_id: PeerId,
+    },
+    ExpireInbound,
+    GetPendingRequests(oneshot::Sender<Vec<PendingRequest>>),
     DebugSubscribe(oneshot::Sender<broadcast::Receiver<MessageDebugEventType>>),
 }
 
@@ -67,17 +184,246 @@ impl_from_special_command!(ReqResp);
 type ForeignOrSelfResponseChannel =
     Result<(Ulid, ResponseChannel<Response>), oneshot::Sender<Response>>;
 
+/// An outbound request waiting for its response.
+#[derive(Debug)]
+struct PendingResponse {
+    deadline: Instant,
+    sender: oneshot::Sender<Response>,
+}
+
+/// An inbound request waiting for a subscriber to respond.
+#[derive(Debug)]
+struct PendingInbound<T> {
+    channel: T,
+    peer_id: PeerId,
+    topic: Option<String>,
+    received: Instant,
+    /// Streaming responses can take arbitrarily long, so streaming requests have no deadline.
+    deadline: Option<Instant>,
+    /// The subscriptions that received the request.
+    subscriptions: Vec<u64>,
+}
+
 #[derive(Debug, Default)]
 pub struct Actor {
     peer_id: Option<PeerId>,
-    response_senders: HashMap<OutboundRequestId, oneshot::Sender<Response>>,
-    request_subscriptions: HashMap<u64, (Option<TopicQuery>, mpsc::Sender<InboundRequest>)>,
-    response_channels: HashMap<u64, ForeignOrSelfResponseChannel>,
+    response_senders: HashMap<OutboundRequestId, PendingResponse>,
+    request_subscriptions: HashMap<u64, RequestSubscription>,
+    response_channels: HashMap<u64, PendingInbound<ForeignOrSelfResponseChannel>>,
+    response_streams: HashMap<u64, PendingInbound<mpsc::Sender<ResponseFrame>>>,
+    provider_caches: HashMap<RecordKey, ProviderCache>,
     next_subscription_id: u64,
     debug_sender: Option<broadcast::Sender<MessageDebugEventType>>,
 }
 
 impl Actor {
+    /// Removes outbound requests that timed out or whose caller went away.
+    ///
+    /// Requests that timed out are answered with [`ResponseError::Timeout`],
+    /// in case the caller is still waiting.
+    fn prune_pending(&mut self) {
+        self.response_senders.retain(|request_id, pending| {
+            let cancelled = pending.sender.is_closed();
+            if cancelled {
+                tracing::debug!("Request with id {request_id} was cancelled");
+            }
+            !cancelled
+        });
+
+        let now = Instant::now();
+        let timed_out = self
+            .response_senders
+            .iter()
+            .filter(|(_, pending)| pending.deadline <= now)
+            .map(|(request_id, _)| *request_id)
+            .collect::<Vec<_>>();
+        for request_id in timed_out {
+            if let Some(pending) = self.response_senders.remove(&request_id) {
+                let _ = pending.sender.send(Response::Error(ResponseError::Timeout));
+            }
+        }
+
+        // Self requests whose caller went away can't be answered anymore
+        self.response_channels
+            .retain(|_, pending| !matches!(&pending.channel, Err(sender) if sender.is_closed()));
+
+        self.response_streams.retain(|id, pending| {
+            let cancelled = pending.channel.is_closed();
+            if cancelled {
+                tracing::debug!("Streaming request with id {id} was cancelled");
+            }
+            !cancelled
+        });
+    }
+
+    /// Sends an inbound request to all subscriptions matching its topic.
+    ///
+    /// Returns the ids of the subscriptions that received the request.
+    fn dispatch(&self, request: &InboundRequest) -> Vec<u64> {
+        let topic = request.req.topic.as_ref();
+
+        self.request_subscriptions
+            .iter()
+            .filter(|(_, subscription)| {
+                subscription.matches(topic) && subscription.sender.try_send(request.clone()).is_ok()
+            })
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Keeps track of a dispatched request until one of its subscribers responds.
+    ///
+    /// Non-streaming requests get the longest response timeout of their subscriptions as deadline.
+    fn track<T>(
+        &self,
+        channel: T,
+        request: &InboundRequest,
+        subscriptions: Vec<u64>,
+    ) -> PendingInbound<T> {
+        let received = Instant::now();
+        let deadline = if request.streaming {
+            None
+        } else {
+            subscriptions
+                .iter()
+                .filter_map(|id| self.request_subscriptions.get(id))
+                .map(|subscription| subscription.response_timeout)
+                .max()
+                .map(|timeout| received + timeout)
+        };
+
+        PendingInbound {
+            channel,
+            peer_id: request.peer_id,
+            topic: request.req.topic.clone(),
+            received,
+            deadline,
+            subscriptions,
+        }
+    }
+
+    /// Returns the number of subscriptions that received the request and are still active.
+    fn active_subscribers<T>(&self, pending: &PendingInbound<T>) -> usize {
+        pending
+            .subscriptions
+            .iter()
+            .filter_map(|id| self.request_subscriptions.get(id))
+            .filter(|subscription| !subscription.sender.is_closed())
+            .count()
+    }
+
+    /// Answers inbound requests that won't get a response from a subscriber anymore.
+    fn expire_inbound(&mut self, behaviour: &mut MyBehaviour) {
+        for (id, channel, error) in self.take_expired(Instant::now()) {
+            tracing::debug!(%error, "Answering unanswered request with id {id}");
+            self.respond(channel, Response::Error(error), behaviour);
+        }
+    }
+
+    /// Removes the inbound requests that won't get a response from a subscriber anymore.
+    ///
+    /// Returns the requests whose deadline passed with [`ResponseError::Timeout`] and the
+    /// requests whose subscribers all went away with [`ResponseError::Failed`]. Abandoned
+    /// streaming requests are dropped, which makes the stream responder send an error to the
+    /// requester.
+    fn take_expired(
+        &mut self,
+        now: Instant,
+    ) -> Vec<(u64, ForeignOrSelfResponseChannel, ResponseError)> {
+        let expired = self
+            .response_channels
+            .iter()
+            .filter_map(|(id, pending)| {
+                if pending.deadline.is_some_and(|deadline| deadline <= now) {
+                    Some((*id, ResponseError::Timeout))
+                } else if self.active_subscribers(pending) == 0 {
+                    let reason = "The subscriber stopped before responding".to_string();
+                    Some((*id, ResponseError::Failed(reason)))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let abandoned = self
+            .response_streams
+            .iter()
+            .filter(|(_, pending)| self.active_subscribers(pending) == 0)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in abandoned {
+            tracing::debug!("Dropping abandoned streaming request with id {id}");
+            self.response_streams.remove(&id);
+        }
+
+        expired
+            .into_iter()
+            .filter_map(|(id, error)| {
+                let pending = self.response_channels.remove(&id)?;
+                Some((id, pending.channel, error))
+            })
+            .collect()
+    }
+
+    /// Sends the response to a request from another peer or from this peer.
+    fn respond(
+        &mut self,
+        channel: ForeignOrSelfResponseChannel,
+        response: Response,
+        behaviour: &mut MyBehaviour,
+    ) {
+        match channel {
+            Ok((debug_id, channel)) => {
+                self.send_debug_event(|| {
+                    MessageDebugEventType::Response(ResponseDebugEvent {
+                        req_id: debug_id,
+                        response: response.clone(),
+                    })
+                });
+
+                let _ = behaviour.req_resp.send_response(channel, response);
+            }
+            Err(sender) => {
+                let _ = sender.send(response);
+            }
+        }
+    }
+
+    /// Lists the inbound requests that are waiting for a response.
+    fn pending_requests(&self) -> Vec<PendingRequest> {
+        let now = Instant::now();
+        let requests = self
+            .response_channels
+            .iter()
+            .map(|(id, pending)| self.pending_request(*id, pending, false, now));
+        let streams = self
+            .response_streams
+            .iter()
+            .map(|(id, pending)| self.pending_request(*id, pending, true, now));
+
+        requests.chain(streams).collect()
+    }
+
+    fn pending_request<T>(
+        &self,
+        id: u64,
+        pending: &PendingInbound<T>,
+        streaming: bool,
+        now: Instant,
+    ) -> PendingRequest {
+        PendingRequest {
+            id,
+            peer_id: pending.peer_id,
+            topic: pending.topic.clone(),
+            streaming,
+            age: now.saturating_duration_since(pending.received),
+            remaining: pending
+                .deadline
+                .map(|deadline| deadline.saturating_duration_since(now)),
+            subscribers: self.active_subscribers(pending) as u64,
+        }
+    }
+
     fn send_debug_event(&mut self, f: impl FnOnce() -> MessageDebugEventType) {
         if let Some(debug_sender) = self.debug_sender.take() {
             if debug_sender.send(f()).is_ok() {
@@ -94,9 +440,9 @@ impl SubActor for Actor {
     type EventError = void::Void;
     type CommandError = void::Void;
 
-    fn new(peer_id: PeerId) -> Self {
+    fn new(keypair: &Keypair) -> Self {
         Self {
-            peer_id: Some(peer_id),
+            peer_id: Some(keypair.public().to_peer_id()),
             ..Default::default()
         }
     }
@@ -106,10 +452,12 @@ impl SubActor for Actor {
         command: Self::SubCommand,
         behaviour: &mut MyBehaviour,
     ) -> Result<(), Self::CommandError> {
+        self.prune_pending();
         match command {
             Command::Request {
                 peer_id,
                 req,
+                timeout,
                 sender,
             } => {
                 if let Some(own_id) = self.peer_id {
@@ -118,23 +466,17 @@ impl SubActor for Actor {
                         let id = rand::random();
                         let topic = req.topic.clone();
 
-                        let request = InboundRequest { id, peer_id, req };
-
-                        let mut sent_to_subscriber = false;
-                        for (query, sender) in self.request_subscriptions.values() {
-                            let is_match = match (query.as_ref(), topic.as_ref()) {
-                                (Some(query), Some(topic)) => query.matches(topic),
-                                (None, None) => true,
-                                _ => false,
-                            };
-
-                            if is_match && sender.try_send(request.clone()).is_ok() {
-                                sent_to_subscriber = true;
-                            }
-                        }
+                        let request = InboundRequest {
+                            id,
+                            peer_id,
+                            req,
+                            streaming: false,
+                        };
 
-                        if sent_to_subscriber {
-                            self.response_channels.insert(id, Err(sender));
+                        let subscriptions = self.dispatch(&request);
+                        if !subscriptions.is_empty() {
+                            let pending = self.track(Err(sender), &request, subscriptions);
+                            self.response_channels.insert(id, pending);
                         } else {
                             let response =
                                 Response::Error(ResponseError::TopicNotSubscribed(topic));
@@ -160,43 +502,149 @@ impl SubActor for Actor {
                 let req = Request { debug_id, req };
 
                 let outbound_id = behaviour.req_resp.send_request(&peer_id, req);
-                self.response_senders.insert(outbound_id, sender);
+                self.response_senders.insert(
+                    outbound_id,
+                    PendingResponse {
+                        deadline: Instant::now() + timeout.min(MAX_REQUEST_TIMEOUT),
+                        sender,
+                    },
+                );
             }
-            Command::Subscribe { query, sender } => {
+            Command::Subscribe {
+                query,
+                response_timeout,
+                sender,
+            } => {
                 let id = self.next_subscription_id;
                 self.next_subscription_id += 1;
 
                 let (request_sender, request_receiver) = mpsc::channel(10);
 
-                self.request_subscriptions
-                    .insert(id, (query, request_sender));
+                self.request_subscriptions.insert(
+                    id,
+                    RequestSubscription {
+                        query,
+                        sender: request_sender,
+                        response_timeout,
+                    },
+                );
 
                 let _ = sender.send((SubscriptionId(id), request_receiver));
             }
             Command::Unsubscribe(id) => {
                 self.request_subscriptions.remove(&id.0);
+                self.expire_inbound(behaviour);
             }
-            Command::Respond { id, response } => match self.response_channels.remove(&id) {
-                Some(Ok((debug_id, channel))) => {
-                    tracing::debug!("Responding to request with id {id}");
+            Command::Respond { id, response } if self.response_streams.contains_key(&id) => {
+                tracing::debug!("Responding to streaming request with id {id}");
 
-                    self.send_debug_event(|| {
-                        MessageDebugEventType::Response(ResponseDebugEvent {
-                            req_id: debug_id,
-                            response: response.clone(),
-                        })
+                if let Some(PendingInbound {
+                    channel: sender, ..
+                }) = self.response_streams.remove(&id)
+                {
+                    // A single response to a streaming request is its only chunk
+                    tokio::spawn(async move {
+                        if sender.send(ResponseFrame::Chunk(response)).await.is_ok() {
+                            let _ = sender.send(ResponseFrame::End).await;
+                        }
                     });
+                }
+            }
+            Command::Respond { id, response } => {
+                if let Some(pending) = self.response_channels.remove(&id) {
+                    tracing::debug!("Responding to request with id {id}");
+                    self.respond(pending.channel, response, behaviour);
+                } else {
+                    tracing::warn!("Response with id {id} not found");
+                }
+            }
+            Command::GetControl(sender) => {
+                let _ = sender.send(behaviour.file_transfer.new_control());
+            }
+            Command::StreamRequest {
+                peer_id,
+                req,
+                sender,
+            } => {
+                let id = rand::random();
+                let topic = req.topic.clone();
 
-                    let _ = behaviour.req_resp.send_response(channel, response);
+                let request = InboundRequest {
+                    id,
+                    peer_id,
+                    req,
+                    streaming: true,
+                };
+
+                let subscriptions = self.dispatch(&request);
+                if !subscriptions.is_empty() {
+                    let pending = self.track(sender, &request, subscriptions);
+                    self.response_streams.insert(id, pending);
+                } else {
+                    let response = Response::Error(ResponseError::TopicNotSubscribed(topic));
+                    // The channel is new, so it has room for both frames
+                    let _ = sender.try_send(ResponseFrame::Chunk(response));
+                    let _ = sender.try_send(ResponseFrame::End);
                 }
-                Some(Err(sender)) => {
-                    tracing::debug!("Responding to self request with id {id}");
-                    let _ = sender.send(response);
+            }
+            Command::GetResponseStream { id, end, sender } => {
+                let stream = if end {
+                    self.response_streams
+                        .remove(&id)
+                        .map(|pending| pending.channel)
+                } else {
+                    self.response_streams
+                        .get(&id)
+                        .map(|pending| pending.channel.clone())
+                };
+                let _ = sender.send(stream);
+            }
+            Command::GetCachedProviders {
+                key,
+                max_age,
+                sender,
+            } => {
+                let candidates = self
+                    .provider_caches
+                    .get_mut(&key)
+                    .filter(|cache| cache.refreshed.elapsed() < max_age)
+                    .map(ProviderCache::candidates);
+                let _ = sender.send(candidates);
+            }
+            Command::CacheProviders {
+                key,
+                neighbours,
+                others,
+                sender,
+            } => {
+                let next = self.provider_caches.get(&key).map_or(0, |cache| cache.next);
+                let mut cache = ProviderCache {
+                    neighbours,
+                    others,
+                    refreshed: Instant::now(),
+                    next,
+                };
+                let _ = sender.send(cache.candidates());
+                // Without providers, the next call looks them up again
+                if !cache.neighbours.is_empty() || !cache.others.is_empty() {
+                    self.provider_caches.insert(key, cache);
                 }
-                None => {
-                    tracing::warn!("Response with id {id} not found");
+            }
+            Command::EvictProvider { key, peer_id } => {
+                if let Some(cache) = self.provider_caches.get_mut(&key) {
+                    cache.neighbours.retain(|peer| *peer != peer_id);
+                    cache.others.retain(|peer| *peer != peer_id);
+                    if cache.neighbours.is_empty() && cache.others.is_empty() {
+                        self.provider_caches.remove(&key);
+                    }
                 }
-            },
+            }
+            Command::ExpireInbound => {
+                self.expire_inbound(behaviour);
+            }
+            Command::GetPendingRequests(sender) => {
+                let _ = sender.send(self.pending_requests());
+            }
             Command::DebugSubscribe(sender) => {
                 let receiver = self
                     .debug_sender
@@ -214,6 +662,7 @@ impl SubActor for Actor {
         event: Self::Event,
         behaviour: &mut MyBehaviour,
     ) -> Result<(), Self::EventError> {
+        self.prune_pending();
         match event {
             Event::Message { peer, message } => match message {
                 Message::Request {
@@ -231,34 +680,16 @@ impl SubActor for Actor {
                         id,
                         peer_id: peer,
                         req,
+                        streaming: false,
                     };
 
-                    let mut sent_to_subscriber = false;
-                    for (query, sender) in self.request_subscriptions.values() {
-                        let is_match = match (query.as_ref(), topic.as_ref()) {
-                            (Some(query), Some(topic)) => query.matches(topic),
-                            (None, None) => true,
-                            _ => false,
-                        };
-
-                        if is_match && sender.try_send(request.clone()).is_ok() {
-                            sent_to_subscriber = true;
-                        }
-                    }
-
-                    if sent_to_subscriber {
-                        self.response_channels.insert(id, Ok((debug_id, channel)));
+                    let subscriptions = self.dispatch(&request);
+                    if !subscriptions.is_empty() {
+                        let pending = self.track(Ok((debug_id, channel)), &request, subscriptions);
+                        self.response_channels.insert(id, pending);
                     } else {
                         let response = Response::Error(ResponseError::TopicNotSubscribed(topic));
-
-                        self.send_debug_event(|| {
-                            MessageDebugEventType::Response(ResponseDebugEvent {
-                                req_id: debug_id,
-                                response: response.clone(),
-                            })
-                        });
-
-                        let _ = behaviour.req_resp.send_response(channel, response);
+                        self.respond(Ok((debug_id, channel)), response, behaviour);
                     }
                 }
                 Message::Response {
@@ -267,11 +698,27 @@ impl SubActor for Actor {
                 } => {
                     tracing::debug!("Received response for request with id {request_id}");
 
-                    if let Some(sender) = self.response_senders.remove(&request_id) {
-                        sender.send(response).unwrap();
+                    if let Some(pending) = self.response_senders.remove(&request_id) {
+                        let _ = pending.sender.send(response);
                     }
                 }
             },
+            Event::OutboundFailure {
+                peer,
+                request_id,
+                error,
+                ..
+            } => {
+                tracing::debug!("Request with id {request_id} to peer {peer} failed: {error}");
+
+                if let Some(pending) = self.response_senders.remove(&request_id) {
+                    let error = match error {
+                        OutboundFailure::Timeout => ResponseError::Timeout,
+                        e => ResponseError::Failed(e.to_string()),
+                    };
+                    let _ = pending.sender.send(Response::Error(error));
+                }
+            }
             e => {
                 tracing::debug!("Unhandled event: {e:?}");
             }
@@ -280,45 +727,275 @@ impl SubActor for Actor {
     }
 }
 
+/// Turns the frames of a streaming response into the responses, ending with the end marker.
+///
+/// A missing end marker, a failed frame or waiting longer than `timeout` for a frame ends the
+/// responses with an error.
+fn responses(
+    frames: BoxStream<'static, Result<ResponseFrame, String>>,
+    timeout: Duration,
+) -> impl Stream<Item = Response> + Send + 'static {
+    stream::unfold(Some(frames), move |frames| async move {
+        let mut frames = frames?;
+        let error = match tokio::time::timeout(timeout, frames.next()).await {
+            Ok(Some(Ok(ResponseFrame::Chunk(response)))) => return Some((response, Some(frames))),
+            Ok(Some(Ok(ResponseFrame::End))) => return None,
+            Ok(Some(Err(e))) => ResponseError::Failed(e),
+            Ok(None) => {
+                ResponseError::Failed("Stream closed before the end of the response".into())
+            }
+            Err(_) => ResponseError::Timeout,
+        };
+        Some((Response::Error(error), None))
+    })
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StreamRequestError {
+    #[error("Request error: `{0:?}`")]
+    Request(#[from] RequestError),
+    #[error("Open stream error: `{0}`")]
+    OpenStream(#[from] OpenStreamError),
+    #[error("Codec error: `{0}`")]
+    Codec(#[from] CborCodecError),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ResponseStreamError {
+    #[error("Request error: `{0:?}`")]
+    Request(#[from] RequestError),
+    #[error("No streaming request with id {0} is waiting for a response")]
+    NotFound(u64),
+    #[error("The requester closed the stream")]
+    Closed,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CallError {
+    #[error("Request error: `{0:?}`")]
+    Request(#[from] RequestError),
+    #[error("No providers found")]
+    NoProviders,
+}
+
+#[derive(Clone)]
 pub struct Client {
     inner: SpecialClient<Command>,
+    providers: ProviderLookup,
 }
 
 impl From<SpecialClient<Command>> for Client {
     fn from(inner: SpecialClient<Command>) -> Self {
-        Self { inner }
+        Self {
+            providers: ProviderLookup::new(&inner.sender, inner.peer_id),
+            inner,
+        }
     }
 }
 
 impl Client {
+    /// Sends a request to a peer and waits for the response.
+    ///
+    /// The request is given up after `timeout`, or [`MAX_REQUEST_TIMEOUT`] if it is `None`.
+    /// Dropping the returned future cancels the request.
     pub async fn send_request(
         &self,
         peer_id: PeerId,
         req: req_resp::Request,
+        timeout: Option<Duration>,
     ) -> Result<Response, RequestError> {
+        let timeout = timeout.map_or(MAX_REQUEST_TIMEOUT, |timeout| {
+            timeout.min(MAX_REQUEST_TIMEOUT)
+        });
+
         let (sender, receiver) = oneshot::channel();
         self.inner
             .send(Command::Request {
                 peer_id,
                 req,
+                timeout,
                 sender,
             })
             .await
             .map_err(RequestError::Send)?;
 
-        tokio::time::timeout(REQUEST_TIMEOUT, receiver)
+        tokio::time::timeout(timeout, receiver)
             .await
             .unwrap_or(Ok(Response::Error(ResponseError::Timeout)))
             .map_err(RequestError::Oneshot)
     }
 
+    /// Sends a request to a peer and returns the stream of response chunks.
+    ///
+    /// The stream ends after the last chunk, or with an error response if the request fails.
+    /// Waiting for each chunk is given up after `timeout`, or [`MAX_REQUEST_TIMEOUT`] if it is
+    /// `None`. Dropping the returned stream cancels the request.
+    pub async fn send_streaming_request(
+        &self,
+        peer_id: PeerId,
+        req: req_resp::Request,
+        timeout: Option<Duration>,
+    ) -> Result<impl Stream<Item = Response> + Send + 'static, StreamRequestError> {
+        let timeout = timeout.map_or(MAX_REQUEST_TIMEOUT, |timeout| {
+            timeout.min(MAX_REQUEST_TIMEOUT)
+        });
+
+        let frames: BoxStream<'static, Result<ResponseFrame, String>> =
+            if peer_id == self.inner.peer_id {
+                tracing::debug!("Sending streaming self request");
+
+                let (sender, receiver) = mpsc::channel(CHUNK_BUFFER);
+                self.dispatch_stream_request(peer_id, req, sender).await?;
+
+                ReceiverStream::new(receiver).map(Ok).boxed()
+            } else {
+                tracing::debug!("Sending streaming request to peer {peer_id}");
+
+                let stream = self
+                    .get_control()
+                    .await?
+                    .open_stream(peer_id, STREAM_PROTOCOL)
+                    .await?;
+                let mut framed =
+                    Framed::new(stream, CborCodec::<req_resp::Request, ResponseFrame>::new());
+                framed.send(req).await?;
+
+                framed.map(|res| res.map_err(|e| e.to_string())).boxed()
+            };
+
+        Ok(responses(frames, timeout))
+    }
+
+    /// Sends a request to one of the providers of a key and returns the provider and its response.
+    ///
+    /// Providers that are batman neighbours are tried first, and each call starts at another
+    /// provider to spread the load. If a provider fails, times out or isn't subscribed to the
+    /// topic of the request, the request is retried on the next provider, up to `max_attempts`
+    /// providers. The last failure is returned if all of them fail.
+    ///
+    /// The providers of the key are cached and looked up again once they are older than
+    /// `refresh_interval`, or when all cached providers failed.
+    pub async fn call_provider(
+        &self,
+        key: RecordKey,
+        req: req_resp::Request,
+        timeout: Option<Duration>,
+        max_attempts: usize,
+        refresh_interval: Duration,
+    ) -> Result<(PeerId, Response), CallError> {
+        let candidates = self.get_candidates(&key, refresh_interval).await?;
+
+        let mut last_failure = None;
+        for peer_id in candidates.into_iter().take(max_attempts.max(1)) {
+            match self.send_request(peer_id, req.clone(), timeout).await? {
+                Response::Error(
+                    e @ (ResponseError::Timeout
+                    | ResponseError::Failed(_)
+                    | ResponseError::TopicNotSubscribed(_)),
+                ) => {
+                    tracing::debug!(%peer_id, error = %e, "Provider failed, trying the next one");
+
+                    self.inner
+                        .send(Command::EvictProvider {
+                            key: key.clone(),
+                            peer_id,
+                        })
+                        .await
+                        .map_err(RequestError::Send)?;
+
+                    last_failure = Some((peer_id, Response::Error(e)));
+                }
+                response => return Ok((peer_id, response)),
+            }
+        }
+
+        last_failure.ok_or(CallError::NoProviders)
+    }
+
+    async fn get_candidates(
+        &self,
+        key: &RecordKey,
+        refresh_interval: Duration,
+    ) -> Result<Vec<PeerId>, RequestError> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .send(Command::GetCachedProviders {
+                key: key.clone(),
+                max_age: refresh_interval,
+                sender,
+            })
+            .await
+            .map_err(RequestError::Send)?;
+        if let Some(candidates) = receiver.await.map_err(RequestError::Oneshot)? {
+            return Ok(candidates);
+        }
+
+        let (neighbours, others) = self.providers.get_all_providers(key.clone()).await?;
+
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .send(Command::CacheProviders {
+                key: key.clone(),
+                neighbours,
+                others,
+                sender,
+            })
+            .await
+            .map_err(RequestError::Send)?;
+        receiver.await.map_err(RequestError::Oneshot)
+    }
+
+    pub async fn create_stream_responder(&self) -> Result<StreamResponder, RequestError> {
+        let control = self.get_control().await?;
+        Ok(StreamResponder::new(control, self.clone()))
+    }
+
+    async fn get_control(&self) -> Result<Control, RequestError> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .send(Command::GetControl(sender))
+            .await
+            .map_err(RequestError::Send)?;
+        receiver.await.map_err(RequestError::Oneshot)
+    }
+
+    async fn dispatch_stream_request(
+        &self,
+        peer_id: PeerId,
+        req: req_resp::Request,
+        sender: mpsc::Sender<ResponseFrame>,
+    ) -> Result<(), RequestError> {
+        self.inner
+            .send(Command::StreamRequest {
+                peer_id,
+                req,
+                sender,
+            })
+            .await
+            .map_err(RequestError::Send)
+    }
+
+    /// Subscribes to inbound requests matching the query.
+    ///
+    /// Requests that aren't answered within `response_timeout`, or [`MAX_REQUEST_TIMEOUT`] if it
+    /// is `None`, are answered with [`ResponseError::Timeout`]. Requests that no subscriber can
+    /// answer anymore, because all of them unsubscribed, are answered with an error right away.
     pub async fn subscribe(
         &self,
         query: Option<TopicQuery>,
+        response_timeout: Option<Duration>,
     ) -> Result<(SubscriptionId, mpsc::Receiver<InboundRequest>), RequestError> {
+        let response_timeout = response_timeout.map_or(MAX_REQUEST_TIMEOUT, |timeout| {
+            timeout.min(MAX_REQUEST_TIMEOUT)
+        });
+
         let (sender, receiver) = oneshot::channel();
         self.inner
-            .send(Command::Subscribe { query, sender })
+            .send(Command::Subscribe {
+                query,
+                response_timeout,
+                sender,
+            })
             .await
             .map_err(RequestError::Send)?;
         receiver.await.map_err(RequestError::Oneshot)
@@ -331,6 +1008,31 @@ impl Client {
             .map_err(RequestError::Send)
     }
 
+    /// Lists the inbound requests that are waiting for a response from a subscriber.
+    pub async fn pending_requests(&self) -> Result<Vec<PendingRequest>, RequestError> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .send(Command::GetPendingRequests(sender))
+            .await
+            .map_err(RequestError::Send)?;
+        receiver.await.map_err(RequestError::Oneshot)
+    }
+
+    /// Regularly answers inbound requests whose response deadline passed.
+    ///
+    /// Runs until the actor stops.
+    pub async fn expire_unanswered(self) {
+        let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            if self.inner.send(Command::ExpireInbound).await.is_err() {
+                break;
+            }
+        }
+    }
+
     pub async fn send_response(&self, id: u64, response: Response) -> Result<(), RequestError> {
         self.inner
             .send(Command::Respond { id, response })
@@ -338,6 +1040,46 @@ impl Client {
             .map_err(RequestError::Send)
     }
 
+    /// Sends one chunk of the response to a streaming request.
+    ///
+    /// Waits while the requester is not keeping up with the chunks.
+    pub async fn send_response_chunk(
+        &self,
+        id: u64,
+        response: Response,
+    ) -> Result<(), ResponseStreamError> {
+        self.get_response_stream(id, false)
+            .await?
+            .send(ResponseFrame::Chunk(response))
+            .await
+            .map_err(|_| ResponseStreamError::Closed)
+    }
+
+    /// Ends the response to a streaming request.
+    pub async fn end_response(&self, id: u64) -> Result<(), ResponseStreamError> {
+        self.get_response_stream(id, true)
+            .await?
+            .send(ResponseFrame::End)
+            .await
+            .map_err(|_| ResponseStreamError::Closed)
+    }
+
+    async fn get_response_stream(
+        &self,
+        id: u64,
+        end: bool,
+    ) -> Result<mpsc::Sender<ResponseFrame>, ResponseStreamError> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .send(Command::GetResponseStream { id, end, sender })
+            .await
+            .map_err(RequestError::Send)?;
+        receiver
+            .await
+            .map_err(RequestError::Oneshot)?
+            .ok_or(ResponseStreamError::NotFound(id))
+    }
+
     pub async fn debug_subscribe(
         &self,
     ) -> Result<broadcast::Receiver<MessageDebugEventType>, RequestError> {
@@ -349,3 +1091,189 @@ impl Client {
         receiver.await.map_err(RequestError::Oneshot)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::task::JoinHandle;
+
+    use super::*;
+
+    /// Answers the commands of anycast calls with the responses of the given providers.
+    ///
+    /// Returns the evicted providers once the client is dropped.
+    fn serve_providers(
+        mut commands: mpsc::Receiver<crate::command::Command>,
+        providers: Vec<(PeerId, Response)>,
+    ) -> JoinHandle<Vec<PeerId>> {
+        tokio::spawn(async move {
+            let mut evicted = Vec::new();
+            while let Some(crate::command::Command::ReqResp(command)) = commands.recv().await {
+                match command {
+                    Command::GetCachedProviders { sender, .. } => {
+                        let peers = providers.iter().map(|(peer_id, _)| *peer_id).collect();
+                        let _ = sender.send(Some(peers));
+                    }
+                    Command::Request {
+                        peer_id, sender, ..
+                    } => {
+                        let (_, response) = providers
+                            .iter()
+                            .find(|(provider, _)| *provider == peer_id)
+                            .expect("Request to unknown provider");
+                        let _ = sender.send(response.clone());
+                    }
+                    Command::EvictProvider { peer_id, .. } => evicted.push(peer_id),
+                    _ => panic!("Unexpected command"),
+                }
+            }
+            evicted
+        })
+    }
+
+    fn request() -> req_resp::Request {
+        req_resp::Request {
+            data: b"request".to_vec(),
+            topic: Some("topic".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_provider_failover() {
+        let providers = [PeerId::random(), PeerId::random(), PeerId::random()];
+        let (sender, commands) = mpsc::channel(1);
+        let server = serve_providers(
+            commands,
+            vec![
+                (
+                    providers[0],
+                    Response::Error(ResponseError::Failed("failed".to_string())),
+                ),
+                (
+                    providers[1],
+                    Response::Error(ResponseError::TopicNotSubscribed(None)),
+                ),
+                (providers[2], Response::Data(b"response".to_vec())),
+            ],
+        );
+
+        let client = crate::client::Client::new(sender, PeerId::random()).req_resp();
+        let (peer_id, response) = client
+            .call_provider(
+                RecordKey::new(&"service"),
+                request(),
+                None,
+                3,
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+        assert_eq!(peer_id, providers[2]);
+        assert!(matches!(response, Response::Data(data) if data == b"response"));
+
+        drop(client);
+        assert_eq!(server.await.unwrap(), providers[..2]);
+    }
+
+    #[tokio::test]
+    async fn test_call_provider_max_attempts() {
+        let providers = [PeerId::random(), PeerId::random(), PeerId::random()];
+        let (sender, commands) = mpsc::channel(1);
+        let server = serve_providers(
+            commands,
+            vec![
+                (providers[0], Response::Error(ResponseError::Timeout)),
+                (providers[1], Response::Error(ResponseError::Timeout)),
+                (providers[2], Response::Data(b"response".to_vec())),
+            ],
+        );
+
+        let client = crate::client::Client::new(sender, PeerId::random()).req_resp();
+        let (peer_id, response) = client
+            .call_provider(
+                RecordKey::new(&"service"),
+                request(),
+                None,
+                2,
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+        assert_eq!(peer_id, providers[1]);
+        assert!(matches!(response, Response::Error(ResponseError::Timeout)));
+
+        drop(client);
+        assert_eq!(server.await.unwrap(), providers[..2]);
+    }
+
+    fn subscribe(
+        actor: &mut Actor,
+        topic: &str,
+        response_timeout: Duration,
+    ) -> mpsc::Receiver<InboundRequest> {
+        let (sender, receiver) = mpsc::channel(10);
+        actor.request_subscriptions.insert(
+            actor.next_subscription_id,
+            RequestSubscription {
+                query: Some(TopicQuery::String(topic.into())),
+                sender,
+                response_timeout,
+            },
+        );
+        actor.next_subscription_id += 1;
+        receiver
+    }
+
+    fn inbound(id: u64, topic: &str, streaming: bool) -> InboundRequest {
+        InboundRequest {
+            id,
+            peer_id: PeerId::random(),
+            req: req_resp::Request {
+                data: b"request".to_vec(),
+                topic: Some(topic.to_string()),
+            },
+            streaming,
+        }
+    }
+
+    #[test]
+    fn test_expire_inbound() {
+        let mut actor = Actor::default();
+        let _fast = subscribe(&mut actor, "fast", Duration::from_secs(5));
+        let _slow = subscribe(&mut actor, "slow", Duration::from_secs(60));
+        let gone = subscribe(&mut actor, "gone", Duration::from_secs(60));
+
+        for (id, topic) in [(1, "fast"), (2, "slow"), (3, "gone")] {
+            let request = inbound(id, topic, false);
+            let subscriptions = actor.dispatch(&request);
+            let pending = actor.track(Err(oneshot::channel().0), &request, subscriptions);
+            actor.response_channels.insert(id, pending);
+        }
+        let request = inbound(4, "gone", true);
+        let subscriptions = actor.dispatch(&request);
+        let (sender, mut stream) = mpsc::channel(CHUNK_BUFFER);
+        let pending = actor.track(sender, &request, subscriptions);
+        actor.response_streams.insert(4, pending);
+
+        assert!(actor.take_expired(Instant::now()).is_empty());
+        assert_eq!(actor.response_streams.len(), 1);
+
+        drop(gone);
+        let mut expired = actor.take_expired(Instant::now() + Duration::from_secs(10));
+        expired.sort_by_key(|(id, _, _)| *id);
+        assert!(matches!(
+            expired.as_slice(),
+            [
+                (1, Err(_), ResponseError::Timeout),
+                (3, Err(_), ResponseError::Failed(_)),
+            ]
+        ));
+
+        assert!(actor.response_channels.contains_key(&2));
+        assert_eq!(actor.response_channels.len(), 1);
+        assert!(actor.response_streams.is_empty());
+        assert!(matches!(
+            stream.try_recv(),
+            Err(mpsc::error::TryRecvError::Disconnected)
+        ));
+    }
+}
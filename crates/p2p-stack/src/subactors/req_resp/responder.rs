@@ -0,0 +1,188 @@
+use std::io;
+
+use asynchronous_codec::{CborCodec, Framed};
+use futures::{sink::SinkExt as _, stream::StreamExt as _, AsyncRead, AsyncWrite};
+use hyveos_core::req_resp::{self, Response, ResponseError};
+use libp2p::PeerId;
+use libp2p_stream::Control;
+use tokio::sync::mpsc;
+
+use super::{Client, ResponseFrame, CHUNK_BUFFER, STREAM_PROTOCOL};
+
+/// Accepts streaming requests from peers and hands them to the request subscribers.
+pub struct StreamResponder {
+    control: Control,
+    client: Client,
+}
+
+impl StreamResponder {
+    pub(super) fn new(control: Control, client: Client) -> Self {
+        Self { control, client }
+    }
+
+    pub async fn run(mut self) {
+        let mut streams = self
+            .control
+            .accept(STREAM_PROTOCOL)
+            .expect("Already registered stream (likely two stream responders)");
+
+        while let Some((peer_id, stream)) = streams.next().await {
+            let client = self.client.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_stream(&client, peer_id, stream).await {
+                    tracing::trace!(error = ?e, "Error handling request stream");
+                }
+            });
+        }
+    }
+}
+
+async fn handle_stream(
+    client: &Client,
+    peer_id: PeerId,
+    stream: impl AsyncRead + AsyncWrite + Unpin,
+) -> anyhow::Result<()> {
+    let mut framed = Framed::new(stream, CborCodec::<ResponseFrame, req_resp::Request>::new());
+    let req = match framed.next().await {
+        Some(req) => req?,
+        None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "No request"))?,
+    };
+
+    tracing::debug!("Received streaming request from peer {peer_id}");
+
+    let (sender, mut receiver) = mpsc::channel(CHUNK_BUFFER);
+    client
+        .dispatch_stream_request(peer_id, req, sender)
+        .await
+        .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+
+    let (mut sink, mut requester) = framed.split();
+    loop {
+        tokio::select! {
+            frame = receiver.recv() => {
+                let Some(frame) = frame else {
+                    break;
+                };
+                let end = matches!(frame, ResponseFrame::End);
+                sink.send(frame).await?;
+                if end {
+                    sink.close().await?;
+                    return Ok(());
+                }
+            }
+            // The requester doesn't send anything after the request, so this only
+            // resolves when it closed the stream and cancelled the request
+            _ = requester.next() => {
+                tracing::debug!("Streaming request from peer {peer_id} was cancelled");
+                return Ok(());
+            }
+        }
+    }
+
+    // The response stream was dropped without an end marker, e.g. because the subscriber went away
+    let error = ResponseError::Failed("Responder stopped before the end of the response".into());
+    sink.send(ResponseFrame::Chunk(Response::Error(error)))
+        .await?;
+    sink.send(ResponseFrame::End).await?;
+    sink.close().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use asynchronous_codec::{CborCodec, Framed};
+    use std::time::Duration;
+
+    use futures::{sink::SinkExt as _, stream::StreamExt as _};
+    use hyveos_core::req_resp::{Request, Response, ResponseError};
+    use libp2p::PeerId;
+    use tokio::{io::DuplexStream, sync::mpsc, task::JoinHandle};
+    use tokio_util::compat::{Compat, TokioAsyncReadCompatExt as _};
+
+    use super::handle_stream;
+    use crate::{
+        command::Command,
+        subactors::req_resp::{self, responses, ResponseFrame},
+    };
+
+    type Requester = Framed<Compat<DuplexStream>, CborCodec<Request, ResponseFrame>>;
+
+    /// Starts handling a streaming request and returns the requester's end of the stream and the
+    /// sender the subscriber responds with.
+    async fn start() -> (
+        Requester,
+        mpsc::Sender<ResponseFrame>,
+        JoinHandle<anyhow::Result<()>>,
+    ) {
+        let (command_sender, mut commands) = mpsc::channel(1);
+        let client = crate::client::Client::new(command_sender, PeerId::random()).req_resp();
+
+        let (requester, responder) = tokio::io::duplex(1024);
+        let handle = tokio::spawn(async move {
+            handle_stream(&client, PeerId::random(), responder.compat()).await
+        });
+
+        let mut requester = Framed::new(requester.compat(), CborCodec::new());
+        requester
+            .send(Request {
+                data: b"request".to_vec(),
+                topic: None,
+            })
+            .await
+            .unwrap();
+
+        let Some(Command::ReqResp(req_resp::Command::StreamRequest { req, sender, .. })) =
+            commands.recv().await
+        else {
+            panic!("Expected a streaming request");
+        };
+        assert_eq!(req.data, b"request");
+
+        (requester, sender, handle)
+    }
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let (mut requester, sender, handle) = start().await;
+
+        sender
+            .send(ResponseFrame::Chunk(Response::Data(b"chunk".to_vec())))
+            .await
+            .unwrap();
+        assert!(matches!(
+            requester.next().await,
+            Some(Ok(ResponseFrame::Chunk(Response::Data(data)))) if data == b"chunk"
+        ));
+
+        // Closing the stream cancels the request while the subscriber is still responding
+        drop(requester);
+        handle.await.unwrap().unwrap();
+        sender.closed().await;
+    }
+
+    #[tokio::test]
+    async fn test_no_end_marker() {
+        let (requester, sender, handle) = start().await;
+
+        sender
+            .send(ResponseFrame::Chunk(Response::Data(b"chunk".to_vec())))
+            .await
+            .unwrap();
+        drop(sender);
+
+        // The requester sees a single error and then the end of the response
+        let frames = requester.map(|res| res.map_err(|e| e.to_string())).boxed();
+        let responses = responses(frames, Duration::from_secs(10))
+            .collect::<Vec<_>>()
+            .await;
+        assert!(matches!(
+            responses.as_slice(),
+            [
+                Response::Data(data),
+                Response::Error(ResponseError::Failed(_)),
+            ] if data == b"chunk"
+        ));
+        handle.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,289 @@
+//! Byte streams between apps and TCP connections forwarded to ports of other peers.
+//!
+//! The streams share the stream behaviour with file transfer, but use their own protocols.
+
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    io,
+    sync::{Arc, Weak},
+    time::Duration,
+};
+
+use futures::StreamExt as _;
+use libp2p::{PeerId, StreamProtocol};
+use libp2p_stream::{AlreadyRegistered, Control, IncomingStreams, OpenStreamError};
+use tokio::sync::{
+    mpsc::{self, error::TrySendError},
+    oneshot, Mutex,
+};
+
+pub use self::forward::PortForwardProvider;
+use crate::{
+    actor::SubActor,
+    behaviour::MyBehaviour,
+    client::{RequestError, SpecialClient},
+    impl_from_special_command,
+};
+
+mod forward;
+
+/// The number of incoming app streams per protocol that are buffered until an app accepts them.
+/// Further streams are reset until an app accepts some of them.
+const APP_STREAM_BUFFER: usize = 16;
+
+/// How often it is checked whether an app still accepts streams for a protocol.
+/// Protocols nobody accepts anymore are unregistered, so peers can't open streams for them.
+const APP_STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+type AppStreamQueue = Mutex<mpsc::Receiver<(PeerId, libp2p::Stream)>>;
+type AppStreamReceiver = Arc<AppStreamQueue>;
+
+pub enum Command {
+    GetControl {
+        sender: oneshot::Sender<Control>,
+    },
+    AcceptAppStreams {
+        protocol: StreamProtocol,
+        sender: oneshot::Sender<Result<AppStreamReceiver, AppStreamError>>,
+    },
+}
+
+impl_from_special_command!(Streams);
+
+#[derive(Debug, Default)]
+pub struct Actor {
+    /// The task forwarding the incoming streams holds the receiver while it is registered.
+    app_streams: HashMap<StreamProtocol, Weak<AppStreamQueue>>,
+}
+
+impl SubActor for Actor {
+    type SubCommand = Command;
+    type Event = void::Void;
+    type EventError = void::Void;
+    type CommandError = void::Void;
+
+    fn handle_command(
+        &mut self,
+        command: Self::SubCommand,
+        behaviour: &mut MyBehaviour,
+    ) -> Result<(), Self::CommandError> {
+        match command {
+            Command::GetControl { sender } => {
+                let control = behaviour.file_transfer.new_control();
+                let _ = sender.send(control);
+            }
+            Command::AcceptAppStreams { protocol, sender } => {
+                self.app_streams
+                    .retain(|_, streams| streams.strong_count() > 0);
+
+                let streams = match self.app_streams.entry(protocol) {
+                    Entry::Occupied(entry) => entry.get().upgrade().ok_or(AppStreamError::Closed),
+                    Entry::Vacant(entry) => behaviour
+                        .file_transfer
+                        .new_control()
+                        .accept(entry.key().clone())
+                        .map(|incoming| {
+                            let (stream_sender, stream_receiver) = mpsc::channel(APP_STREAM_BUFFER);
+                            let streams = Arc::new(Mutex::new(stream_receiver));
+                            entry.insert(Arc::downgrade(&streams));
+                            tokio::spawn(forward_app_streams(
+                                incoming,
+                                stream_sender,
+                                streams.clone(),
+                            ));
+                            streams
+                        })
+                        .map_err(Into::into),
+                };
+                let _ = sender.send(streams);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Forwards the incoming streams of an app protocol until no app accepts them anymore.
+///
+/// Dropping `incoming` unregisters the protocol.
+async fn forward_app_streams(
+    mut incoming: IncomingStreams,
+    sender: mpsc::Sender<(PeerId, libp2p::Stream)>,
+    streams: AppStreamReceiver,
+) {
+    let mut interval = tokio::time::interval(APP_STREAM_IDLE_TIMEOUT);
+    // The first tick completes immediately
+    interval.tick().await;
+
+    loop {
+        tokio::select! {
+            stream = incoming.next() => {
+                let Some(stream) = stream else {
+                    break;
+                };
+                match sender.try_send(stream) {
+                    Ok(()) => {}
+                    // Dropping the stream resets it, so the peer notices right away
+                    Err(TrySendError::Full((peer_id, _))) => {
+                        tracing::debug!(%peer_id, "Dropping app stream, too many are waiting to be accepted");
+                    }
+                    Err(TrySendError::Closed(_)) => break,
+                }
+            }
+            _ = interval.tick() => {
+                // Every pending `accept_app_stream` call holds another reference
+                if Arc::strong_count(&streams) == 1 {
+                    break;
+                }
+            }
+        }
+    }
+
+    // Unregister the protocol before the receiver goes away, so it can be registered again
+    // as soon as the next call notices
+    drop(incoming);
+}
+
+/// Maps the protocol of an app stream into its own namespace, so that apps can't interfere with
+/// the protocols of the runtime.
+fn app_protocol(protocol: &str) -> StreamProtocol {
+    let protocol = format!("/app/{}", protocol.trim_start_matches('/'));
+    StreamProtocol::try_from_owned(protocol).expect("App protocols start with a slash")
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AppStreamError {
+    #[error("Request error: `{0:?}`")]
+    Request(#[from] RequestError),
+    #[error("Open stream error: `{0}`")]
+    OpenStream(#[from] OpenStreamError),
+    #[error("Protocol already registered: `{0}`")]
+    AlreadyRegistered(#[from] AlreadyRegistered),
+    #[error("Stopped accepting streams")]
+    Closed,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PortForwardError {
+    #[error("Request error: `{0:?}`")]
+    Request(#[from] RequestError),
+    #[error("Open stream error: `{0}`")]
+    OpenStream(#[from] OpenStreamError),
+    #[error("IO error: `{0}`")]
+    Io(#[from] io::Error),
+    #[error("Forwarding to port {0} is not allowed by the peer")]
+    Denied(u16),
+    #[error("Port {0} is not reachable on the peer")]
+    Unreachable(u16),
+}
+
+#[derive(Clone)]
+pub struct Client {
+    inner: SpecialClient<Command>,
+}
+
+impl From<SpecialClient<Command>> for Client {
+    fn from(inner: SpecialClient<Command>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Client {
+    async fn get_control(&self) -> Result<Control, RequestError> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner
+            .send(Command::GetControl { sender })
+            .await
+            .map_err(RequestError::Send)?;
+        receiver.await.map_err(RequestError::Oneshot)
+    }
+
+    /// Opens a stream to an app on a peer that accepts streams for the protocol.
+    pub async fn open_app_stream(
+        &self,
+        peer_id: PeerId,
+        protocol: &str,
+    ) -> Result<libp2p::Stream, AppStreamError> {
+        let stream = self
+            .get_control()
+            .await?
+            .open_stream(peer_id, app_protocol(protocol))
+            .await?;
+        Ok(stream)
+    }
+
+    /// Waits for a peer to open a stream for the protocol and returns the peer and the stream.
+    ///
+    /// Streams for the protocol are queued from the first call on, until an app accepts them.
+    /// Each stream is only returned to one caller. Streams beyond [`APP_STREAM_BUFFER`] that
+    /// aren't accepted yet are reset. Once no call has been waiting for a while, the protocol
+    /// is unregistered again and queued streams are dropped.
+    pub async fn accept_app_stream(
+        &self,
+        protocol: &str,
+    ) -> Result<(PeerId, libp2p::Stream), AppStreamError> {
+        loop {
+            let (sender, receiver) = oneshot::channel();
+            self.inner
+                .send(Command::AcceptAppStreams {
+                    protocol: app_protocol(protocol),
+                    sender,
+                })
+                .await
+                .map_err(RequestError::Send)?;
+            let streams = match receiver.await.map_err(RequestError::Oneshot)? {
+                Ok(streams) => streams,
+                // The protocol was unregistered just now, so it can be registered again
+                Err(AppStreamError::Closed) => continue,
+                Err(e) => return Err(e),
+            };
+
+            let stream = streams.lock().await.recv().await;
+            if let Some(stream) = stream {
+                return Ok(stream);
+            }
+            // The protocol was unregistered right after this call picked up the streams
+        }
+    }
+
+    /// Opens a stream to a peer that is connected to a local TCP port on the peer's side.
+    pub async fn open_port_forward(
+        &self,
+        peer_id: PeerId,
+        port: u16,
+    ) -> Result<libp2p::Stream, PortForwardError> {
+        use futures::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+        let mut stream = self
+            .get_control()
+            .await?
+            .open_stream(peer_id, forward::FORWARD_PROTOCOL)
+            .await
+            .map_err(|e| match e {
+                // The peer doesn't accept forwarded connections at all
+                OpenStreamError::UnsupportedProtocol(_) => PortForwardError::Denied(port),
+                e => PortForwardError::OpenStream(e),
+            })?;
+
+        stream.write_all(&port.to_be_bytes()).await?;
+        let mut status = [0];
+        stream.read_exact(&mut status).await?;
+
+        match status[0] {
+            forward::STATUS_CONNECTED => Ok(stream),
+            forward::STATUS_DENIED => Err(PortForwardError::Denied(port)),
+            _ => Err(PortForwardError::Unreachable(port)),
+        }
+    }
+
+    /// Creates the provider that accepts TCP connections forwarded by other peers.
+    ///
+    /// Only connections to `allowed_ports` are accepted, so that local services like the
+    /// bridge are never exposed by accident.
+    pub async fn create_port_forward_provider(
+        &self,
+        allowed_ports: HashSet<u16>,
+    ) -> Result<PortForwardProvider, RequestError> {
+        let control = self.get_control().await?;
+        Ok(PortForwardProvider::new(control, allowed_ports))
+    }
+}
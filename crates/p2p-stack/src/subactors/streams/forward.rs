@@ -0,0 +1,85 @@
+use std::{collections::HashSet, io, sync::Arc};
+
+use futures::{
+    io::{AsyncReadExt as _, AsyncWriteExt as _},
+    stream::StreamExt as _,
+};
+use libp2p::{PeerId, Stream, StreamProtocol};
+use libp2p_stream::Control;
+use tokio::net::TcpStream;
+use tokio_util::compat::FuturesAsyncReadCompatExt as _;
+
+/// The protocol for TCP connections forwarded to a port on the remote peer.
+///
+/// The opener sends the port as two big-endian bytes and the remote peer answers with a single
+/// status byte. After [`STATUS_CONNECTED`], the stream carries the raw TCP data in both directions.
+pub(super) const FORWARD_PROTOCOL: StreamProtocol = StreamProtocol::new("/port-forward/1.0.0");
+
+pub(super) const STATUS_CONNECTED: u8 = 0;
+pub(super) const STATUS_DENIED: u8 = 1;
+pub(super) const STATUS_UNREACHABLE: u8 = 2;
+
+/// Terminates TCP connections forwarded by other peers into connections to local ports.
+pub struct PortForwardProvider {
+    control: Control,
+    allowed_ports: HashSet<u16>,
+}
+
+impl PortForwardProvider {
+    pub(super) fn new(control: Control, allowed_ports: HashSet<u16>) -> Self {
+        Self {
+            control,
+            allowed_ports,
+        }
+    }
+
+    pub async fn run(mut self) {
+        let mut streams = self
+            .control
+            .accept(FORWARD_PROTOCOL)
+            .expect("Already registered stream (likely two port forward providers)");
+
+        let allowed_ports = Arc::new(self.allowed_ports);
+
+        while let Some((peer_id, stream)) = streams.next().await {
+            let allowed_ports = Arc::clone(&allowed_ports);
+            tokio::spawn(async move {
+                if let Err(e) = handle_stream(peer_id, stream, &allowed_ports).await {
+                    tracing::trace!(error = ?e, "Error handling port forward stream");
+                }
+            });
+        }
+    }
+}
+
+async fn handle_stream(
+    peer_id: PeerId,
+    mut stream: Stream,
+    allowed_ports: &HashSet<u16>,
+) -> io::Result<()> {
+    let mut port = [0; 2];
+    stream.read_exact(&mut port).await?;
+    let port = u16::from_be_bytes(port);
+
+    if !allowed_ports.contains(&port) {
+        tracing::info!(%peer_id, port, "Denied forwarding to port");
+        stream.write_all(&[STATUS_DENIED]).await?;
+        return stream.close().await;
+    }
+
+    let mut connection = match TcpStream::connect(("localhost", port)).await {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::debug!(%peer_id, port, error = %e, "Failed to connect to forwarded port");
+            stream.write_all(&[STATUS_UNREACHABLE]).await?;
+            return stream.close().await;
+        }
+    };
+    stream.write_all(&[STATUS_CONNECTED]).await?;
+
+    tracing::debug!(%peer_id, port, "Forwarding connection to port");
+
+    tokio::io::copy_bidirectional(&mut connection, &mut stream.compat()).await?;
+
+    Ok(())
+}
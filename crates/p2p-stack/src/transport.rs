@@ -0,0 +1,223 @@
+use std::{error::Error, time::Duration};
+
+use libp2p::{
+    core::{
+        muxing::StreamMuxerBox,
+        transport::{Boxed, Transport as _},
+        upgrade::Version,
+    },
+    identity::Keypair,
+    multiaddr::Protocol,
+    noise,
+    pnet::{PnetConfig, PreSharedKey},
+    quic, tcp, yamux, Multiaddr, PeerId,
+};
+
+const UPGRADE_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// A transport the swarm can use to connect to peers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    /// QUIC over UDP.
+    Quic,
+    /// TCP, secured with noise and multiplexed with yamux.
+    Tcp,
+}
+
+impl Transport {
+    /// Returns whether the address can be dialed with this transport.
+    pub fn matches(self, addr: &Multiaddr) -> bool {
+        let mut protocols = addr.iter().skip(1);
+        match self {
+            Self::Quic => matches!(
+                (protocols.next(), protocols.next()),
+                (Some(Protocol::Udp(_)), Some(Protocol::QuicV1))
+            ),
+            Self::Tcp => matches!(protocols.next(), Some(Protocol::Tcp(_))),
+        }
+    }
+
+    /// Rewrites an address of another transport to use this transport on the same IP and port.
+    ///
+    /// Returns `None` if the address is not an IP address of a known transport.
+    pub fn translate(self, addr: &Multiaddr) -> Option<Multiaddr> {
+        let mut protocols = addr.iter();
+        let ip = match protocols.next()? {
+            ip @ (Protocol::Ip4(_) | Protocol::Ip6(_)) => ip,
+            _ => return None,
+        };
+        let port = match protocols.next()? {
+            Protocol::Tcp(port) => port,
+            Protocol::Udp(port) if protocols.next()? == Protocol::QuicV1 => port,
+            _ => return None,
+        };
+
+        let translated = Multiaddr::empty().with(ip);
+        let translated = match self {
+            Self::Quic => translated.with(Protocol::Udp(port)).with(Protocol::QuicV1),
+            Self::Tcp => translated.with(Protocol::Tcp(port)),
+        };
+
+        Some(protocols.fold(translated, Multiaddr::with))
+    }
+}
+
+/// Configuration of the transports the swarm uses.
+#[derive(Debug, Clone)]
+pub struct TransportConfig {
+    /// The pre-shared key of a private swarm.
+    ///
+    /// If set, only peers that know the same key can connect to the node.
+    /// Since QUIC can not be protected by a pre-shared key, the node then only uses TCP.
+    pub swarm_key: Option<PreSharedKey>,
+    /// The enabled transports, most preferred first.
+    ///
+    /// If dialing a peer with one transport fails,
+    /// the peer is dialed again with the next transport on the same IP and port.
+    pub preference: Vec<Transport>,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            swarm_key: None,
+            preference: vec![Transport::Quic, Transport::Tcp],
+        }
+    }
+}
+
+impl TransportConfig {
+    /// Returns the transports that are actually used, most preferred first.
+    pub fn transports(&self) -> Vec<Transport> {
+        if self.swarm_key.is_some() {
+            vec![Transport::Tcp]
+        } else {
+            let mut transports = Vec::with_capacity(self.preference.len());
+            for &transport in &self.preference {
+                if !transports.contains(&transport) {
+                    transports.push(transport);
+                }
+            }
+            transports
+        }
+    }
+}
+
+/// Returns the addresses to try after dialing `addr` failed,
+/// using the transports that come after the one of `addr` in `transports`.
+pub(crate) fn fallback_addresses(transports: &[Transport], addr: &Multiaddr) -> Vec<Multiaddr> {
+    transports
+        .iter()
+        .position(|transport| transport.matches(addr))
+        .map(|index| {
+            transports[index + 1..]
+                .iter()
+                .filter_map(|transport| transport.translate(addr))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Builds the transport of the swarm from all enabled transports.
+///
+/// # Panics
+///
+/// Panics if no transport is enabled.
+pub(crate) fn build(
+    keypair: &Keypair,
+    config: TransportConfig,
+) -> Result<Boxed<(PeerId, StreamMuxerBox)>, Box<dyn Error + Send + Sync>> {
+    if let Some(swarm_key) = config.swarm_key {
+        return Ok(private_tcp(keypair, swarm_key)?);
+    }
+
+    let mut transports = config
+        .transports()
+        .into_iter()
+        .map(|transport| match transport {
+            Transport::Quic => Ok(quic_transport(keypair)),
+            Transport::Tcp => tcp_transport(keypair),
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter();
+
+    let first = transports
+        .next()
+        .expect("At least one transport must be enabled");
+
+    Ok(transports.fold(first, |transport, other| {
+        transport
+            .or_transport(other)
+            .map(|either, _| either.into_inner())
+            .boxed()
+    }))
+}
+
+fn quic_transport(keypair: &Keypair) -> Boxed<(PeerId, StreamMuxerBox)> {
+    quic::tokio::Transport::new(quic::Config::new(keypair))
+        .map(|(peer_id, connection), _| (peer_id, StreamMuxerBox::new(connection)))
+        .boxed()
+}
+
+fn tcp_transport(keypair: &Keypair) -> Result<Boxed<(PeerId, StreamMuxerBox)>, noise::Error> {
+    Ok(
+        tcp::tokio::Transport::new(tcp::Config::default().nodelay(true))
+            .upgrade(Version::V1Lazy)
+            .authenticate(noise::Config::new(keypair)?)
+            .multiplex(yamux::Config::default())
+            .timeout(UPGRADE_TIMEOUT)
+            .boxed(),
+    )
+}
+
+/// Builds a TCP transport that is protected by a pre-shared key.
+///
+/// The key is checked before the noise handshake, so peers of other swarms can't even learn
+/// the identity of the node.
+fn private_tcp(
+    keypair: &Keypair,
+    swarm_key: PreSharedKey,
+) -> Result<Boxed<(PeerId, StreamMuxerBox)>, noise::Error> {
+    Ok(
+        tcp::tokio::Transport::new(tcp::Config::default().nodelay(true))
+            .and_then(move |socket, _| PnetConfig::new(swarm_key).handshake(socket))
+            .upgrade(Version::V1Lazy)
+            .authenticate(noise::Config::new(keypair)?)
+            .multiplex(yamux::Config::default())
+            .timeout(UPGRADE_TIMEOUT)
+            .boxed(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_addresses() {
+        let transports = [Transport::Quic, Transport::Tcp];
+        let quic: Multiaddr = "/ip6/fe80::1/udp/39811/quic-v1".parse().unwrap();
+        let tcp: Multiaddr = "/ip6/fe80::1/tcp/39811".parse().unwrap();
+
+        assert_eq!(fallback_addresses(&transports, &quic), vec![tcp.clone()]);
+        assert!(fallback_addresses(&transports, &tcp).is_empty());
+        assert!(fallback_addresses(&[Transport::Quic], &quic).is_empty());
+    }
+
+    #[test]
+    fn test_translate_keeps_peer_id() {
+        let peer_id = PeerId::random();
+        let quic: Multiaddr = format!("/ip4/10.0.0.1/udp/4001/quic-v1/p2p/{peer_id}")
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            Transport::Tcp.translate(&quic),
+            Some(
+                format!("/ip4/10.0.0.1/tcp/4001/p2p/{peer_id}")
+                    .parse()
+                    .unwrap()
+            )
+        );
+    }
+}
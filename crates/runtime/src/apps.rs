@@ -26,6 +26,11 @@ use hyveos_core::{
 use hyveos_docker::{Compression, ContainerManager, NetworkMode, PulledImage, StoppedContainer};
 use hyveos_p2p_stack::{apps::ActorToClient, file_transfer, Client as P2PClient};
 use libp2p::PeerId;
+use prometheus_client::{
+    encoding::EncodeLabelSet,
+    metrics::{counter::Counter, family::Family, gauge::Gauge},
+    registry::Registry,
+};
 use tokio::{
     fs::{metadata, File},
     io::{stderr, stdout, AsyncReadExt as _, AsyncWriteExt as _, BufReader},
@@ -61,6 +66,36 @@ enum SelfCommand {
     },
 }
 
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ExitLabels {
+    status: &'static str,
+}
+
+/// Prometheus metrics of the containers managed by the runtime.
+#[derive(Clone, Default)]
+struct AppMetrics {
+    running: Gauge,
+    exited: Family<ExitLabels, Counter>,
+}
+
+impl AppMetrics {
+    fn new(registry: &mut Registry) -> Self {
+        let metrics = Self::default();
+        let registry = registry.sub_registry_with_prefix("apps");
+        registry.register(
+            "running",
+            "Number of running containers",
+            metrics.running.clone(),
+        );
+        registry.register(
+            "exited",
+            "Number of containers that exited on their own",
+            metrics.exited.clone(),
+        );
+        metrics
+    }
+}
+
 pub struct ApplicationManagerBuilder {
     command_broker: mpsc::Receiver<ActorToClient>,
     client: P2PClient,
@@ -71,10 +106,11 @@ pub struct ApplicationManagerBuilder {
     apps_management: ApplicationManagementConfig,
     heartbeat_timeout: Duration,
     telemetry: Telemetry,
+    metrics: AppMetrics,
 }
 
 impl ApplicationManagerBuilder {
-    #[cfg_attr(feature = "batman", expect(clippy::too_many_arguments))]
+    #[expect(clippy::too_many_arguments)]
     pub fn new(
         command_broker: mpsc::Receiver<ActorToClient>,
         client: P2PClient,
@@ -84,6 +120,7 @@ impl ApplicationManagerBuilder {
         apps_management: ApplicationManagementConfig,
         heartbeat_timeout: Duration,
         telemetry: Telemetry,
+        registry: &mut Registry,
     ) -> Self {
         Self {
             command_broker,
@@ -95,6 +132,7 @@ impl ApplicationManagerBuilder {
             apps_management,
             heartbeat_timeout,
             telemetry,
+            metrics: AppMetrics::new(registry),
         }
     }
 
@@ -109,6 +147,7 @@ impl ApplicationManagerBuilder {
             apps_management,
             heartbeat_timeout,
             telemetry,
+            metrics,
         } = self;
         let (self_command_sender, self_command_receiver) = mpsc::channel(1);
 
@@ -135,6 +174,7 @@ impl ApplicationManagerBuilder {
                 heartbeat_timeout,
                 container_handles: FutureMap::new(),
                 telemetry,
+                metrics,
             }
         };
 
@@ -155,6 +195,7 @@ pub struct ApplicationManager {
     heartbeat_timeout: Duration,
     container_handles: FutureMap<Ulid, ContainerHandle>,
     telemetry: Telemetry,
+    metrics: AppMetrics,
 }
 
 impl ApplicationManager {
@@ -182,6 +223,12 @@ impl ApplicationManager {
                     self.handle_self_command(command).await;
                 },
                 Some((_, res)) = self.container_handles.next() => {
+                    self.update_running_metric();
+                    let status = if res.is_ok() { "success" } else { "failure" };
+                    self.metrics
+                        .exited
+                        .get_or_create(&ExitLabels { status })
+                        .inc();
                     match res {
                         Ok((id, container)) => {
                             tracing::info!(
@@ -297,6 +344,7 @@ impl ApplicationManager {
                 let id = handle.id;
                 let image_name = handle.image_name.clone();
                 self.container_handles.insert(id, handle);
+                self.update_running_metric();
 
                 if let Some(ports) = persisted_ports {
                     if let Err(e) = self
@@ -320,6 +368,13 @@ impl ApplicationManager {
         send(id).await;
     }
 
+    fn update_running_metric(&self) {
+        let running = self.container_handles.iter().count();
+        self.metrics
+            .running
+            .set(i64::try_from(running).unwrap_or(i64::MAX));
+    }
+
     fn list_containers(&self) -> Vec<RunningApp> {
         self.container_handles
             .iter()
@@ -333,6 +388,7 @@ impl ApplicationManager {
 
     async fn stop_container(&mut self, container_id: Ulid) -> Result<(), ExecutionError> {
         if let Some(handle) = self.container_handles.remove(&container_id) {
+            self.update_running_metric();
             match handle.stop(false).await {
                 Ok((_, container)) => {
                     tracing::info!(
@@ -360,6 +416,7 @@ impl ApplicationManager {
             .collect::<FuturesUnordered<_>>()
             .try_collect::<Vec<_>>()
             .await;
+        self.update_running_metric();
 
         match containers {
             Ok(containers) => {
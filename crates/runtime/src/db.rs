@@ -3,6 +3,7 @@ use std::{
     sync::{Arc, PoisonError, RwLock},
 };
 
+use hyveos_p2p_stack::kad::{StoreBackend, StoreChange, StoreTable};
 use redb::{
     Database, Key, ReadTransaction, ReadableTable, TableDefinition, TableError, Value,
     WriteTransaction,
@@ -12,6 +13,10 @@ const STARTUP_APPS_TABLE: TableDefinition<String, Vec<u16>> = TableDefinition::n
 
 const BRIDGE_TABLE: TableDefinition<String, Vec<u8>> = TableDefinition::new("bridge");
 
+const KAD_RECORDS_TABLE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("kad_records");
+
+const KAD_PROVIDERS_TABLE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("kad_providers");
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error(transparent)]
@@ -153,3 +158,54 @@ impl hyveos_bridge::DbClient for Client {
         }
     }
 }
+
+impl StoreBackend for Client {
+    fn load(&self, table: StoreTable) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let read = self.read()?;
+        let table = match table {
+            StoreTable::Records => KAD_RECORDS_TABLE,
+            StoreTable::Providers => KAD_PROVIDERS_TABLE,
+        };
+        match read.open_table(table) {
+            Ok(table) => table
+                .iter()?
+                .map(|res| {
+                    res.map(|(k, v)| (k.value().to_vec(), v.value().to_vec()))
+                        .map_err(Into::into)
+                })
+                .collect(),
+            Err(TableError::TableDoesNotExist(_)) => Ok(Vec::new()),
+            Err(e) => Err(Error::from(e).into()),
+        }
+    }
+
+    fn apply(&self, changes: Vec<StoreChange>) -> anyhow::Result<()> {
+        let write = self.write()?;
+        {
+            let mut records = write.open_table(KAD_RECORDS_TABLE)?;
+            let mut providers = write.open_table(KAD_PROVIDERS_TABLE)?;
+
+            for change in changes {
+                match change {
+                    StoreChange::Insert { table, key, value } => {
+                        let table = match table {
+                            StoreTable::Records => &mut records,
+                            StoreTable::Providers => &mut providers,
+                        };
+                        table.insert(key.as_slice(), value.as_slice())?;
+                    }
+                    StoreChange::Remove { table, key } => {
+                        let table = match table {
+                            StoreTable::Records => &mut records,
+                            StoreTable::Providers => &mut providers,
+                        };
+                        table.remove(key.as_slice())?;
+                    }
+                }
+            }
+        }
+        write.commit()?;
+
+        Ok(())
+    }
+}
@@ -1,24 +1,34 @@
 #![warn(clippy::pedantic)]
 #![allow(clippy::module_name_repetitions)]
 
-#[cfg(feature = "network")]
-use std::net::SocketAddr;
 use std::{
     future::Future,
+    net::SocketAddr,
     path::{Path, PathBuf},
+    sync::Arc,
     time::Duration,
 };
 
 use futures::{future, FutureExt as _, TryFutureExt as _};
 #[cfg(feature = "network")]
 use hyveos_bridge::NetworkBridge;
-use hyveos_bridge::{AppsClient as _, Bridge, Telemetry};
-use hyveos_config::{ApplicationManagementConfig, LogFilter};
-use hyveos_core::{get_runtime_base_path, pub_sub::ReceivedMessage};
+use hyveos_bridge::{AppsClient as _, Bridge, BridgeMetrics, Telemetry};
+use hyveos_config::{
+    ApplicationManagementConfig, KadStoreBackend, KadStoreConfig, LogFilter, PortForwardingConfig,
+    TransportProtocol,
+};
+use hyveos_core::{admin::PeerGate, get_runtime_base_path, pub_sub::ReceivedMessage};
 #[cfg(feature = "batman")]
 use hyveos_p2p_stack::DebugClient;
-use hyveos_p2p_stack::{Client as P2PClient, FullActor};
-use libp2p::{self, gossipsub::IdentTopic, identity::Keypair, Multiaddr};
+use hyveos_p2p_stack::{
+    kad::{StoreBackend, StoreConfig},
+    Client as P2PClient, FullActor, Transport, TransportConfig,
+};
+use libp2p::{
+    self, gossipsub::IdentTopic, identity::Keypair, kad::store::MemoryStoreConfig,
+    pnet::PreSharedKey, Multiaddr,
+};
+use prometheus_client::registry::Registry;
 use tokio::task::{AbortHandle, JoinHandle};
 use tokio_util::sync::CancellationToken;
 use tracing::level_filters::LevelFilter;
@@ -29,11 +39,13 @@ use tracing_subscriber::{
 use crate::{
     apps::{ApplicationManagerBuilder, AppsClient},
     db::Client as DbClient,
+    metrics::MetricsServer,
 };
 
 mod apps;
 mod db;
 mod future_map;
+mod metrics;
 
 #[derive(Debug)]
 pub enum CliConnectionType {
@@ -56,26 +68,39 @@ pub struct RuntimeArgs {
     pub store_directory: PathBuf,
     pub db_file: PathBuf,
     pub keypair: Keypair,
+    pub swarm_key: Option<PreSharedKey>,
+    pub transport_preference: Vec<TransportProtocol>,
+    pub peer_gate: PeerGate,
     pub random_directory: bool,
     pub apps_management: ApplicationManagementConfig,
     pub application_heartbeat_timeout: Duration,
+    pub port_forwarding: PortForwardingConfig,
+    pub forwardable_ports: Vec<u16>,
     pub clean: bool,
     pub log_dir: Option<PathBuf>,
     pub log_level: LogFilter,
     pub cli_connection: CliConnectionType,
     pub telemetry: bool,
+    pub kad_store: KadStoreConfig,
+    pub metrics_addr: Option<SocketAddr>,
 }
 
 pub struct Runtime {
     clients: Clients,
     actor_task: JoinHandle<()>,
     file_provider_task: JoinHandle<()>,
+    stream_responder_task: JoinHandle<()>,
+    request_expiry_task: JoinHandle<()>,
+    port_forward_task: Option<JoinHandle<()>>,
     #[cfg(feature = "batman")]
     debug_client_task: JoinHandle<()>,
     application_manager_task: JoinHandle<()>,
     ping_task: JoinHandle<()>,
+    #[cfg(feature = "location")]
+    location_task: JoinHandle<()>,
     cli_bridge_task: JoinHandle<Result<(), hyveos_bridge::Error>>,
     cli_bridge_cancellation_token: CancellationToken,
+    metrics_task: Option<JoinHandle<std::io::Result<()>>>,
 }
 
 macro_rules! create_logfile {
@@ -126,14 +151,21 @@ impl Runtime {
             store_directory,
             db_file,
             keypair,
+            swarm_key,
+            transport_preference,
+            peer_gate,
             random_directory,
             apps_management,
             application_heartbeat_timeout,
+            port_forwarding,
+            forwardable_ports,
             clean,
             log_dir,
             log_level,
             cli_connection,
             telemetry,
+            kad_store,
+            metrics_addr,
         } = args;
 
         setup_logging(log_dir, log_level);
@@ -161,7 +193,24 @@ impl Runtime {
 
         let db_client = DbClient::new(db_file)?;
 
-        let (p2p_client, mut actor) = FullActor::build(keypair);
+        let mut registry = Registry::with_prefix("hyveos");
+
+        let (p2p_client, mut actor) = FullActor::build(
+            keypair,
+            Self::kad_store_config(&kad_store, &db_client),
+            TransportConfig {
+                swarm_key,
+                preference: transport_preference
+                    .into_iter()
+                    .map(|protocol| match protocol {
+                        TransportProtocol::Quic => Transport::Quic,
+                        TransportProtocol::Tcp => Transport::Tcp,
+                    })
+                    .collect(),
+            },
+            peer_gate,
+            &mut registry,
+        );
 
         #[cfg(feature = "batman")]
         let opt_batman_addr = Some(batman_addr);
@@ -183,6 +232,28 @@ impl Runtime {
 
         let file_provider_task = tokio::spawn(file_provider.run());
 
+        let stream_responder = p2p_client
+            .req_resp()
+            .create_stream_responder()
+            .await
+            .map_err(|_| anyhow::anyhow!("Failed to create stream responder"))?;
+
+        let stream_responder_task = tokio::spawn(stream_responder.run());
+
+        let request_expiry_task = tokio::spawn(p2p_client.req_resp().expire_unanswered());
+
+        let port_forward_task = if let PortForwardingConfig::Allow = port_forwarding {
+            let port_forward_provider = p2p_client
+                .streams()
+                .create_port_forward_provider(forwardable_ports.into_iter().collect())
+                .await
+                .map_err(|_| anyhow::anyhow!("Failed to create port forward provider"))?;
+
+            Some(tokio::spawn(port_forward_provider.run()))
+        } else {
+            None
+        };
+
         #[cfg(feature = "batman")]
         let (debug_client, debug_command_sender) = DebugClient::build(p2p_client.clone());
 
@@ -193,7 +264,11 @@ impl Runtime {
             return Err(anyhow::anyhow!("Failed to get command broker"));
         };
 
-        let mut application_telemetry = Telemetry::default().context("application-telemetry");
+        let bridge_metrics = BridgeMetrics::new(&mut registry);
+
+        let mut application_telemetry = Telemetry::default()
+            .metrics(bridge_metrics.clone())
+            .context("application-telemetry");
 
         if !telemetry {
             application_telemetry.opt_out();
@@ -209,6 +284,7 @@ impl Runtime {
             apps_management,
             application_heartbeat_timeout,
             application_telemetry,
+            &mut registry,
         );
 
         let (application_manager, apps_client) = builder.build();
@@ -224,7 +300,10 @@ impl Runtime {
 
         let ping_task = tokio::spawn(Self::ping_task(p2p_client.clone()));
 
-        let mut cli_telemetry = Telemetry::default();
+        #[cfg(feature = "location")]
+        let location_task = tokio::spawn(p2p_client.location().cache_locations());
+
+        let mut cli_telemetry = Telemetry::default().metrics(bridge_metrics);
         if !telemetry {
             cli_telemetry.opt_out();
         }
@@ -278,6 +357,13 @@ impl Runtime {
             }
         };
 
+        let metrics_task = if let Some(metrics_addr) = metrics_addr {
+            let server = MetricsServer::bind(metrics_addr, Arc::new(registry)).await?;
+            Some(tokio::spawn(server.run()))
+        } else {
+            None
+        };
+
         let clients = Clients {
             p2p_client,
             apps_client,
@@ -287,12 +373,18 @@ impl Runtime {
             clients,
             actor_task,
             file_provider_task,
+            stream_responder_task,
+            request_expiry_task,
+            port_forward_task,
             #[cfg(feature = "batman")]
             debug_client_task,
             application_manager_task,
             ping_task,
+            #[cfg(feature = "location")]
+            location_task,
             cli_bridge_task,
             cli_bridge_cancellation_token,
+            metrics_task,
         })
     }
 
@@ -323,12 +415,18 @@ impl Runtime {
             clients,
             actor_task,
             file_provider_task,
+            stream_responder_task,
+            request_expiry_task,
+            port_forward_task,
             #[cfg(feature = "batman")]
             debug_client_task,
             application_manager_task,
             ping_task,
+            #[cfg(feature = "location")]
+            location_task,
             cli_bridge_task,
             cli_bridge_cancellation_token,
+            metrics_task,
         } = self;
 
         let apps_client = clients.apps_client.clone();
@@ -341,18 +439,34 @@ impl Runtime {
         apps_client.stop_all_containers(true, None).await?;
 
         file_provider_task.abort();
+        stream_responder_task.abort();
+        request_expiry_task.abort();
         #[cfg(feature = "batman")]
         debug_client_task.abort();
         application_manager_task.abort();
         ping_task.abort();
+        #[cfg(feature = "location")]
+        location_task.abort();
 
         cli_bridge_cancellation_token.cancel();
 
+        if let Some(port_forward_task) = port_forward_task {
+            port_forward_task.abort();
+        }
+
+        if let Some(metrics_task) = metrics_task {
+            metrics_task.abort();
+        }
+
         map_to_anyhow!(file_provider_task);
+        map_to_anyhow!(stream_responder_task);
+        map_to_anyhow!(request_expiry_task);
         #[cfg(feature = "batman")]
         map_to_anyhow!(debug_client_task);
         map_to_anyhow!(application_manager_task);
         map_to_anyhow!(ping_task);
+        #[cfg(feature = "location")]
+        map_to_anyhow!(location_task);
 
         let cli_bridge_task = cli_bridge_task.map(|res| match res {
             Ok(Ok(())) => Ok(()),
@@ -363,6 +477,8 @@ impl Runtime {
         #[cfg(feature = "batman")]
         tokio::try_join!(
             file_provider_task,
+            stream_responder_task,
+            request_expiry_task,
             debug_client_task,
             application_manager_task,
             ping_task,
@@ -372,14 +488,42 @@ impl Runtime {
         #[cfg(not(feature = "batman"))]
         tokio::try_join!(
             file_provider_task,
+            stream_responder_task,
+            request_expiry_task,
             application_manager_task,
             ping_task,
             cli_bridge_task,
         )?;
 
+        #[cfg(feature = "location")]
+        location_task.await?;
+
         Ok(())
     }
 
+    fn kad_store_config(config: &KadStoreConfig, db_client: &DbClient) -> StoreConfig {
+        let defaults = MemoryStoreConfig::default();
+        let limits = MemoryStoreConfig {
+            max_records: config.max_records.unwrap_or(defaults.max_records),
+            max_value_bytes: config.max_value_bytes.unwrap_or(defaults.max_value_bytes),
+            max_providers_per_key: config
+                .max_providers_per_key
+                .unwrap_or(defaults.max_providers_per_key),
+            max_provided_keys: config
+                .max_provided_keys
+                .unwrap_or(defaults.max_provided_keys),
+        };
+
+        let backend = match config.backend {
+            KadStoreBackend::Memory => None,
+            KadStoreBackend::Persistent => {
+                Some(Box::new(db_client.clone()) as Box<dyn StoreBackend>)
+            }
+        };
+
+        StoreConfig { limits, backend }
+    }
+
     fn cleanup_store_directory(store_directory: impl AsRef<Path>) -> anyhow::Result<()> {
         let number_of_cleans: usize = store_directory
             .as_ref()
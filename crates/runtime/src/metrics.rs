@@ -0,0 +1,46 @@
+use std::{io, net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use prometheus_client::{encoding::text::encode, registry::Registry};
+use tokio::net::TcpListener;
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Serves the metrics of a registry in the Prometheus text format at `/metrics`.
+pub struct MetricsServer {
+    listener: TcpListener,
+    registry: Arc<Registry>,
+}
+
+impl MetricsServer {
+    pub async fn bind(addr: SocketAddr, registry: Arc<Registry>) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        tracing::info!(%addr, "Serving metrics");
+
+        Ok(Self { listener, registry })
+    }
+
+    pub async fn run(self) -> io::Result<()> {
+        let router = Router::new()
+            .route("/metrics", get(metrics))
+            .with_state(self.registry);
+
+        axum::serve(self.listener, router).await
+    }
+}
+
+async fn metrics(State(registry): State<Arc<Registry>>) -> Result<impl IntoResponse, StatusCode> {
+    let mut body = String::new();
+    encode(&mut body, &registry).map_err(|e| {
+        tracing::error!("Failed to encode metrics: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(([(header::CONTENT_TYPE, CONTENT_TYPE)], body))
+}
@@ -21,8 +21,8 @@ use crate::services::JsonReqRespService;
 use crate::{
     error::{Error, Result},
     services::{
-        DbService, DebugService, DhtService, DiscoveryService, FileTransferService,
-        GossipSubService, NeighboursService, ReqRespService,
+        AdminService, DbService, DebugService, DhtService, DiscoveryService, FileTransferService,
+        GossipSubService, LocationService, NeighboursService, ReqRespService, StreamService,
     },
 };
 
@@ -492,6 +492,27 @@ impl Connection {
         ConnectionBuilder::new()
     }
 
+    /// Returns a handle to the admin service.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut admin_service = connection.admin();
+    /// let peer_gate = admin_service.get_peer_gate().await.unwrap();
+    ///
+    /// println!("Blocked peers: {:?}", peer_gate.blocked);
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn admin(&self) -> AdminService {
+        AdminService::new(self)
+    }
+
     /// Returns a handle to the application management service.
     ///
     /// # Example
@@ -636,6 +657,28 @@ impl Connection {
         DbService::new(self)
     }
 
+    /// Returns a handle to the location service.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut location_service = connection.location();
+    ///
+    /// if let Some(location) = location_service.get(None).await.unwrap() {
+    ///     println!("Own location: {}, {}", location.latitude, location.longitude);
+    /// }
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn location(&self) -> LocationService {
+        LocationService::new(self)
+    }
+
     /// Returns a handle to the neighbours service.
     ///
     /// # Example
@@ -717,6 +760,30 @@ impl Connection {
         ReqRespService::new(self)
     }
 
+    /// Returns a handle to the stream service.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::Connection;
+    /// use tokio::io::AsyncReadExt as _;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut stream_service = connection.stream();
+    /// let (peer_id, mut stream) = stream_service.accept("example").await.unwrap();
+    ///
+    /// let mut data = Vec::new();
+    /// stream.read_to_end(&mut data).await.unwrap();
+    /// println!("Received {} bytes from {peer_id}", data.len());
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn stream(&self) -> StreamService {
+        StreamService::new(self)
+    }
+
     /// Returns a handle to the request-response service with JSON-encoded requests and responses.
     ///
     /// # Example
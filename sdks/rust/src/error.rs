@@ -33,6 +33,9 @@ pub enum Error {
     /// An environment variable was expected to be set, but it wasn't.
     #[error("Could not get {0}: {1}")]
     EnvVarMissing(&'static str, #[source] std::env::VarError),
+    /// The runtime sent a stream frame that wasn't expected at this point.
+    #[error("Unexpected stream frame")]
+    UnexpectedStreamFrame,
     /// A path was expected to have a file name, but it didn't.
     #[error("Path has no file name: {}", .0.display())]
     NoFileName(PathBuf),
@@ -1,4 +1,9 @@
-pub use hyveos_core::{debug::MeshTopologyEvent, neighbours::NeighbourEvent};
+pub use hyveos_core::{
+    admin::PeerGate,
+    debug::MeshTopologyEvent,
+    location::{GeoPoint, Location, NearbyPeer, NearbyQuery, PeerLocation},
+    neighbours::NeighbourEvent,
+};
 
 #[doc(hidden)]
 #[cfg(feature = "app-management")]
@@ -11,12 +16,15 @@ pub use self::req_resp::CborService as CborReqRespService;
 pub use self::req_resp::JsonService as JsonReqRespService;
 #[doc(inline)]
 pub use self::{
-    debug::Service as DebugService, discovery::Service as DiscoveryService,
-    file_transfer::Service as FileTransferService, kv::Service as DhtService,
-    local_kv::Service as DbService, neighbours::Service as NeighboursService,
+    admin::Service as AdminService, debug::Service as DebugService,
+    discovery::Service as DiscoveryService, file_transfer::Service as FileTransferService,
+    kv::Service as DhtService, local_kv::Service as DbService,
+    location::Service as LocationService, neighbours::Service as NeighboursService,
     pub_sub::Service as GossipSubService, req_resp::Service as ReqRespService,
+    stream::Service as StreamService,
 };
 
+pub mod admin;
 #[doc(hidden)]
 #[cfg(feature = "app-management")]
 pub mod apps;
@@ -25,6 +33,8 @@ pub mod discovery;
 pub mod file_transfer;
 pub mod kv;
 pub mod local_kv;
+pub mod location;
 pub mod neighbours;
 pub mod pub_sub;
 pub mod req_resp;
+pub mod stream;
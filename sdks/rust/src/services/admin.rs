@@ -0,0 +1,135 @@
+use hyveos_core::{
+    admin::PeerGate,
+    grpc::{self, admin_client::AdminClient, Empty},
+};
+use libp2p_identity::PeerId;
+use tonic::transport::Channel;
+
+use crate::{connection::Connection, error::Result};
+
+/// A handle to the admin service.
+///
+/// Exposes methods to administrate the local runtime,
+/// such as blocking misbehaving peers from connecting to it.
+///
+/// The admin service is not available to applications running on the runtime.
+///
+/// # Example
+///
+/// ```no_run
+/// use hyveos_sdk::{Connection, PeerId};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let connection = Connection::new().await.unwrap();
+/// let mut admin_service = connection.admin();
+///
+/// let peer_id: PeerId = "12D3KooW...".parse().unwrap();
+/// admin_service.block_peer(peer_id).await.unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Service {
+    client: AdminClient<Channel>,
+}
+
+impl Service {
+    pub(crate) fn new(connection: &Connection) -> Self {
+        let client = AdminClient::new(connection.channel.clone());
+
+        Self { client }
+    }
+
+    /// Blocks a peer from connecting to the local runtime.
+    ///
+    /// All existing connections to the peer are closed. The block only lasts until the runtime
+    /// is restarted, peers that should stay blocked have to be added to the `denied-peers`
+    /// in the config file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails or if the peer is the local runtime.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::{Connection, PeerId};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut admin_service = connection.admin();
+    ///
+    /// let peer_id: PeerId = "12D3KooW...".parse().unwrap();
+    /// admin_service.block_peer(peer_id).await.unwrap();
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self))]
+    pub async fn block_peer(&mut self, peer_id: PeerId) -> Result<()> {
+        self.client.block_peer(grpc::Peer::from(peer_id)).await?;
+
+        Ok(())
+    }
+
+    /// Unblocks a previously blocked peer.
+    ///
+    /// If the runtime has an allowlist, the peer still has to be in the allowlist to connect.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::{Connection, PeerId};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut admin_service = connection.admin();
+    ///
+    /// let peer_id: PeerId = "12D3KooW...".parse().unwrap();
+    /// admin_service.unblock_peer(peer_id).await.unwrap();
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self))]
+    pub async fn unblock_peer(&mut self, peer_id: PeerId) -> Result<()> {
+        self.client.unblock_peer(grpc::Peer::from(peer_id)).await?;
+
+        Ok(())
+    }
+
+    /// Gets the peers that are allowed or blocked to connect to the local runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut admin_service = connection.admin();
+    /// let peer_gate = admin_service.get_peer_gate().await.unwrap();
+    ///
+    /// println!("Blocked peers:");
+    /// for peer_id in peer_gate.blocked {
+    ///     println!("- {peer_id}");
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self))]
+    pub async fn get_peer_gate(&mut self) -> Result<PeerGate> {
+        self.client
+            .get_peer_gate(Empty {})
+            .await?
+            .into_inner()
+            .try_into()
+            .map_err(Into::into)
+    }
+}
@@ -1,6 +1,6 @@
 use futures::{Stream, StreamExt as _, TryStreamExt as _};
 pub use hyveos_core::debug::{
-    MeshTopologyEvent, MessageDebugEvent, MessageDebugEventType, RequestDebugEvent,
+    MeshTopologyEvent, MessageDebugEvent, MessageDebugEventType, PendingRequest, RequestDebugEvent,
     ResponseDebugEvent,
 };
 #[cfg(docsrs)]
@@ -133,4 +133,37 @@ impl Service {
             })
             .map_err(Into::into)
     }
+
+    /// Gets the inbound requests of this node that are waiting for a response from an app.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails or if the response contains invalid data.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut debug_service = connection.debug();
+    ///
+    /// for request in debug_service.get_pending_requests().await.unwrap() {
+    ///     println!("{request:?}");
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self))]
+    pub async fn get_pending_requests(&mut self) -> Result<Vec<PendingRequest>> {
+        self.client
+            .get_pending_requests(Empty {})
+            .await?
+            .into_inner()
+            .requests
+            .into_iter()
+            .map(|request| request.try_into().map_err(Into::into))
+            .collect()
+    }
 }
@@ -1,6 +1,10 @@
+use std::time::Duration;
+
+use futures::{Stream, StreamExt as _, TryStreamExt as _};
+pub use hyveos_core::dht::{Quorum, Record, RecordValue};
 use hyveos_core::{
     dht::Key,
-    grpc::{kv_client::KvClient, Data, DhtKey, DhtRecord},
+    grpc::{kv_client::KvClient, Data, DhtGetRecord, DhtKey, DhtRecord},
 };
 #[cfg(feature = "serde")]
 use serde::{de::DeserializeOwned, Serialize};
@@ -8,6 +12,71 @@ use tonic::transport::Channel;
 
 use crate::{connection::Connection, error::Result};
 
+/// Options for putting a record into the key-value store.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use hyveos_sdk::{services::kv::{PutOptions, Quorum}, Connection};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let connection = Connection::new().await.unwrap();
+/// let mut kv_service = connection.kv();
+/// let options = PutOptions::new()
+///     .ttl(Duration::from_secs(60))
+///     .quorum(Quorum::Majority);
+/// kv_service
+///     .put_record_with_options("sensors", "temperature", "21.5", options)
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PutOptions {
+    pub ttl: Option<Duration>,
+    pub quorum: Quorum,
+    pub owned: bool,
+}
+
+impl PutOptions {
+    /// Creates new options that store the record on a single peer
+    /// and use the default record TTL of the DHT.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the time-to-live of the record.
+    ///
+    /// The TTL has a resolution of seconds. Once the record expires, it is removed
+    /// from all nodes storing it.
+    #[must_use]
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the number of peers that have to store the record for the put to succeed.
+    #[must_use]
+    pub fn quorum(mut self, quorum: Quorum) -> Self {
+        self.quorum = quorum;
+        self
+    }
+
+    /// Signs the record with the key of the local node.
+    ///
+    /// Once an owned record is stored, other peers can no longer overwrite or remove it,
+    /// and readers get the verified owner as the publisher of the record.
+    #[must_use]
+    pub fn owned(mut self) -> Self {
+        self.owned = true;
+        self
+    }
+}
+
 /// A handle to the distributed key-value store service.
 ///
 /// Exposes methods to interact with the key-value store service,
@@ -61,13 +130,92 @@ impl Service {
     /// kv_service.put_record("topic", "key", "Hello, world!").await.unwrap();
     /// # }
     /// ```
-    #[tracing::instrument(skip_all, fields(topic))]
     pub async fn put_record(
         &mut self,
         topic: impl Into<String>,
         key: impl Into<Vec<u8>>,
         value: impl Into<Vec<u8>>,
     ) -> Result<()> {
+        self.put_record_with_options(topic, key, value, PutOptions::new())
+            .await
+    }
+
+    /// Puts a record into the key-value store that expires after the given time-to-live.
+    ///
+    /// The TTL has a resolution of seconds. Once the record expires, it is removed
+    /// from all nodes storing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut kv_service = connection.kv();
+    /// kv_service
+    ///     .put_record_with_ttl("sensors", "temperature", "21.5", Duration::from_secs(60))
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn put_record_with_ttl(
+        &mut self,
+        topic: impl Into<String>,
+        key: impl Into<Vec<u8>>,
+        value: impl Into<Vec<u8>>,
+        ttl: Duration,
+    ) -> Result<()> {
+        self.put_record_with_options(topic, key, value, PutOptions::new().ttl(ttl))
+            .await
+    }
+
+    /// Puts a record into the key-value store with the given options.
+    ///
+    /// If a quorum other than [`Quorum::One`] is requested, the put fails if not
+    /// enough peers stored the record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails or the quorum is not reached.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::{services::kv::{PutOptions, Quorum}, Connection};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut kv_service = connection.kv();
+    /// kv_service
+    ///     .put_record_with_options(
+    ///         "config",
+    ///         "interval",
+    ///         "10",
+    ///         PutOptions::new().quorum(Quorum::Majority),
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[tracing::instrument(skip_all, fields(topic))]
+    pub async fn put_record_with_options(
+        &mut self,
+        topic: impl Into<String>,
+        key: impl Into<Vec<u8>>,
+        value: impl Into<Vec<u8>>,
+        options: PutOptions,
+    ) -> Result<()> {
+        let PutOptions { ttl, quorum, owned } = options;
+
         let topic = topic.into();
 
         tracing::Span::current().record("topic", &topic);
@@ -80,6 +228,9 @@ impl Service {
         let request = DhtRecord {
             key: key.into(),
             value: Data { data: value.into() },
+            ttl: ttl.map(|ttl| ttl.as_secs()),
+            quorum: Some(quorum.into()),
+            owned: Some(owned),
         };
 
         self.client
@@ -189,12 +340,152 @@ impl Service {
     /// }
     /// # }
     /// ```
-    #[tracing::instrument(skip_all, fields(topic))]
     pub async fn get_record(
         &mut self,
         topic: impl Into<String>,
         key: impl Into<Vec<u8>>,
     ) -> Result<Option<Vec<u8>>> {
+        self.get_record_with_metadata(topic, key)
+            .await
+            .map(|record| record.map(|record| record.value))
+    }
+
+    /// Gets a record from the key-value store, together with its publisher and expiry time.
+    ///
+    /// Returns `None` if the record is not found.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails or the response contains an invalid peer id.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut kv_service = connection.kv();
+    /// let record = kv_service
+    ///     .get_record_with_metadata("sensors", "temperature")
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// if let Some(record) = record {
+    ///     println!("Record published by {:?}, expires at {:?}", record.publisher, record.expires);
+    /// } else {
+    ///    println!("Record not found");
+    /// }
+    /// # }
+    /// ```
+    pub async fn get_record_with_metadata(
+        &mut self,
+        topic: impl Into<String>,
+        key: impl Into<Vec<u8>>,
+    ) -> Result<Option<Record>> {
+        self.get_record_with_quorum(topic, key, Quorum::One).await
+    }
+
+    /// Gets a record from the key-value store, once at least `quorum` peers returned it.
+    ///
+    /// The local node counts towards the quorum if it holds the record.
+    /// Returns `None` if the record is not found.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails, the record was found but the quorum was not
+    /// reached, or the response contains an invalid peer id.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::{services::kv::Quorum, Connection};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut kv_service = connection.kv();
+    /// let record = kv_service
+    ///     .get_record_with_quorum("config", "interval", Quorum::Majority)
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// if let Some(record) = record {
+    ///     println!("Record has value: {:?}", record.value);
+    /// } else {
+    ///    println!("Record not found");
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip_all, fields(topic))]
+    pub async fn get_record_with_quorum(
+        &mut self,
+        topic: impl Into<String>,
+        key: impl Into<Vec<u8>>,
+        quorum: Quorum,
+    ) -> Result<Option<Record>> {
+        let request = Self::get_request(topic, key, quorum);
+
+        let response = self.client.get_record_with_quorum(request).await?;
+
+        Option::<Record>::try_from(response.into_inner()).map_err(Into::into)
+    }
+
+    /// Gets every distinct value stored for a key in the key-value store,
+    /// together with the peers holding each value.
+    ///
+    /// This is useful to detect conflicting values. The query stops once `quorum` peers
+    /// returned a value. Returns an empty list if the record is not found.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails, the record was found but the quorum was not
+    /// reached, or the response contains an invalid peer id.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::{services::kv::Quorum, Connection};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut kv_service = connection.kv();
+    /// let values = kv_service
+    ///     .get_record_values("config", "interval", Quorum::All)
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// for value in values {
+    ///     println!("{:?} is held by {} peers", value.value, value.peers.len());
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip_all, fields(topic))]
+    pub async fn get_record_values(
+        &mut self,
+        topic: impl Into<String>,
+        key: impl Into<Vec<u8>>,
+        quorum: Quorum,
+    ) -> Result<Vec<RecordValue>> {
+        let request = Self::get_request(topic, key, quorum);
+
+        let response = self.client.get_record_values(request).await?;
+
+        response
+            .into_inner()
+            .values
+            .into_iter()
+            .map(|value| RecordValue::try_from(value).map_err(Into::into))
+            .collect()
+    }
+
+    fn get_request(
+        topic: impl Into<String>,
+        key: impl Into<Vec<u8>>,
+        quorum: Quorum,
+    ) -> DhtGetRecord {
         let topic = topic.into();
 
         tracing::Span::current().record("topic", &topic);
@@ -204,11 +495,10 @@ impl Service {
             key: key.into(),
         };
 
-        self.client
-            .get_record(DhtKey::from(key))
-            .await
-            .map(|response| response.into_inner().data.map(|data| data.data))
-            .map_err(Into::into)
+        DhtGetRecord {
+            key: key.into(),
+            quorum: Some(quorum.into()),
+        }
     }
 
     /// Gets a record with a JSON-encoded value from the key-value store.
@@ -303,19 +593,110 @@ impl Service {
             .map_err(Into::into)
     }
 
+    /// Watches a record in the key-value store for changes.
+    ///
+    /// The stream first emits the current record (or `None` if the key is not found)
+    /// and then emits every new record put under the key anywhere in the network.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails. The stream emits errors that occur in the runtime
+    /// while watching the record, as well as data conversion errors.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use futures::TryStreamExt as _;
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut kv_service = connection.kv();
+    /// let mut changes = kv_service.watch("config", "interval").await.unwrap();
+    ///
+    /// while let Some(record) = changes.try_next().await.unwrap() {
+    ///     if let Some(value) = record.and_then(|record| String::from_utf8(record.value).ok()) {
+    ///         println!("Interval changed to {value}");
+    ///     } else {
+    ///         println!("Interval was removed");
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, topic, key), fields(topic))]
+    pub async fn watch(
+        &mut self,
+        topic: impl Into<String>,
+        key: impl Into<Vec<u8>>,
+    ) -> Result<impl Stream<Item = Result<Option<Record>>>> {
+        let topic = topic.into();
+
+        tracing::Span::current().record("topic", &topic);
+
+        let key = Key {
+            topic,
+            key: key.into(),
+        };
+
+        self.client
+            .watch(DhtKey::from(key))
+            .await
+            .map(|response| {
+                response
+                    .into_inner()
+                    .map_ok(TryInto::try_into)
+                    .map(|res| res?.map_err(Into::into))
+            })
+            .map_err(Into::into)
+    }
+
     /// Removes a record from the key-value store.
     ///
-    /// This only applies to the local node and only affects the network once the record expires.
+    /// The record is replaced by a tombstone on every peer holding it. The tombstone expires
+    /// together with the removed record, so that peers that missed the removal can't restore
+    /// the record in the meantime. Putting the record again afterwards is still possible.
+    ///
+    /// Returns the number of peers (including the local node) that acknowledged the removal.
+    /// This is `0` if the record was not found.
     ///
     /// # Errors
     ///
-    /// Returns an error if the RPC call fails.
+    /// Returns an error if the RPC call fails or if the record is owned by another peer.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut kv_service = connection.kv();
+    /// let replicas = kv_service.remove_record("topic", "key").await.unwrap();
+    ///
+    /// println!("Record removed from {replicas} peers");
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, topic, key), fields(topic))]
     pub async fn remove_record(
         &mut self,
         topic: impl Into<String>,
         key: impl Into<Vec<u8>>,
-    ) -> Result<()> {
-        self.remove_record(topic, key).await?;
-        Ok(())
+    ) -> Result<u64> {
+        let topic = topic.into();
+
+        tracing::Span::current().record("topic", &topic);
+
+        let key = Key {
+            topic,
+            key: key.into(),
+        };
+
+        self.client
+            .remove_record(DhtKey::from(key))
+            .await
+            .map(|response| response.into_inner().replicas)
+            .map_err(Into::into)
     }
 }
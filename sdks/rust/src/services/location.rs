@@ -0,0 +1,219 @@
+use futures::{Stream, StreamExt as _, TryStreamExt as _};
+use hyveos_core::{
+    grpc::{self, location_client::LocationClient},
+    location::{Location, NearbyPeer, NearbyQuery, PeerLocation},
+};
+use libp2p_identity::PeerId;
+use tonic::transport::Channel;
+
+use crate::{connection::Connection, error::Result};
+
+/// A handle to the location service.
+///
+/// Exposes methods to interact with the location service,
+/// such as setting the location of the local runtime, getting the location of a peer,
+/// subscribing to location updates of other peers, and finding peers nearby.
+///
+/// The location service is only available if the runtime was built with the `location` feature.
+///
+/// # Example
+///
+/// ```no_run
+/// use hyveos_sdk::{services::Location, Connection};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let connection = Connection::new().await.unwrap();
+/// let mut location_service = connection.location();
+///
+/// let location = Location::new(48.137, 11.575).unwrap().with_accuracy(5.0);
+/// location_service.set(location).await.unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Service {
+    client: LocationClient<Channel>,
+}
+
+impl Service {
+    pub(crate) fn new(connection: &Connection) -> Self {
+        let client = LocationClient::new(connection.channel.clone());
+
+        Self { client }
+    }
+
+    /// Sets the location of the local runtime.
+    ///
+    /// The location is announced to all peers that are subscribed to location updates.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::{services::Location, Connection};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut location_service = connection.location();
+    ///
+    /// let location = Location::new(48.137, 11.575)
+    ///     .unwrap()
+    ///     .with_altitude(519.0)
+    ///     .with_accuracy(5.0);
+    /// location_service.set(location).await.unwrap();
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self))]
+    pub async fn set(&mut self, location: Location) -> Result<()> {
+        self.client.set(grpc::Position::from(location)).await?;
+
+        Ok(())
+    }
+
+    /// Gets the last known location of a peer.
+    ///
+    /// If `peer_id` is `None`, the location of the local runtime is returned.
+    /// Returns `None` if the peer has not set its location yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails or if the peer could not be reached.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::{Connection, PeerId};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut location_service = connection.location();
+    ///
+    /// let peer_id: PeerId = "12D3KooW...".parse().unwrap();
+    /// if let Some(location) = location_service.get(Some(peer_id)).await.unwrap() {
+    ///     println!("{peer_id} is at {}, {}", location.latitude, location.longitude);
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self))]
+    pub async fn get(&mut self, peer_id: Option<PeerId>) -> Result<Option<Location>> {
+        let request = grpc::LocationQuery {
+            peer: peer_id.map(Into::into),
+        };
+
+        self.client
+            .get(request)
+            .await?
+            .into_inner()
+            .try_into()
+            .map_err(Into::into)
+    }
+
+    /// Subscribes to location updates of other peers.
+    ///
+    /// If `peer_id` is set, only updates of that peer are emitted.
+    /// Peers only announce their location when it is set,
+    /// so [`Service::get`] should be used to get the current location of a peer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails. The stream emits errors that occur in the runtime
+    /// while processing the updates, as well as data conversion errors.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use futures::TryStreamExt as _;
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut location_service = connection.location();
+    /// let mut updates = location_service.subscribe(None).await.unwrap();
+    ///
+    /// while let Some(update) = updates.try_next().await.unwrap() {
+    ///     println!("{} moved to {:?}", update.peer_id, update.location);
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self))]
+    pub async fn subscribe(
+        &mut self,
+        peer_id: Option<PeerId>,
+    ) -> Result<impl Stream<Item = Result<PeerLocation>>> {
+        let request = grpc::LocationSubscription {
+            peer: peer_id.map(Into::into),
+        };
+
+        self.client
+            .subscribe(request)
+            .await
+            .map(|response| {
+                response
+                    .into_inner()
+                    .map_ok(TryInto::try_into)
+                    .map(|res| res?.map_err(Into::into))
+            })
+            .map_err(Into::into)
+    }
+
+    /// Gets the peers near a point, sorted by distance.
+    ///
+    /// The runtime keeps a table of the locations other peers announced,
+    /// so no network requests are made. The local runtime is never part of the result.
+    /// If the query has no center, the location of the local runtime is used.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails, or if the query has no center
+    /// and the location of the local runtime is not set.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::num::NonZeroUsize;
+    ///
+    /// use hyveos_sdk::{
+    ///     services::{GeoPoint, NearbyQuery},
+    ///     Connection,
+    /// };
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut location_service = connection.location();
+    ///
+    /// // All peers within 50 m of a point
+    /// let point = GeoPoint::new(48.137, 11.575).unwrap();
+    /// let query = NearbyQuery::new().center(point).radius(50.0);
+    /// let peers = location_service.get_nearby_peers(query).await.unwrap();
+    ///
+    /// // The three peers nearest to the local runtime
+    /// let query = NearbyQuery::new().limit(NonZeroUsize::new(3).unwrap());
+    /// let nearest = location_service.get_nearby_peers(query).await.unwrap();
+    ///
+    /// let mut req_resp_service = connection.req_resp();
+    /// for peer in nearest {
+    ///     println!("{} is {:.1} m away", peer.peer_id, peer.distance);
+    ///     req_resp_service
+    ///         .send_request(peer.peer_id, "hello", None)
+    ///         .await
+    ///         .unwrap();
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self))]
+    pub async fn get_nearby_peers(&mut self, query: NearbyQuery) -> Result<Vec<NearbyPeer>> {
+        self.client
+            .get_nearby_peers(grpc::NearbyPeersQuery::from(query))
+            .await?
+            .into_inner()
+            .try_into()
+            .map_err(Into::into)
+    }
+}
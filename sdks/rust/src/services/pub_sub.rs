@@ -1,10 +1,13 @@
 #[cfg(feature = "serde")]
 use futures::future;
 use futures::{Stream, StreamExt as _, TryStreamExt as _};
-pub use hyveos_core::pub_sub::Message;
+pub use hyveos_core::pub_sub::{Message, MessageAcceptance, ReliableEvent, TopicPeers};
 use hyveos_core::{
-    grpc::{pub_sub_client::PubSubClient, PubSubMessage, Topic},
-    pub_sub::{MessageId, ReceivedMessage},
+    grpc::{
+        self, pub_sub_client::PubSubClient, Empty, PubSubMessage, PubSubValidationResult, Topic,
+    },
+    pub_sub::{MessageId, ReceivedMessage, ValidationResult},
+    req_resp::TopicQuery,
 };
 #[cfg(feature = "serde")]
 use libp2p_identity::PeerId;
@@ -113,6 +116,56 @@ impl Service {
             .map_err(Into::into)
     }
 
+    /// Subscribes to all topics matching a query and returns a stream of messages published to
+    /// those topics.
+    ///
+    /// Topics are learned from the subscriptions of other peers and joined as soon as they match
+    /// the query. The matching topic is reported in each message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails. The stream emits errors that occur in the runtime
+    /// while processing the messages, as well as data conversion errors.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use futures::TryStreamExt as _;
+    /// use hyveos_sdk::{services::req_resp::TopicQuery, Connection};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut pub_sub_service = connection.pub_sub();
+    /// let query = TopicQuery::regex("^sensors/.*").unwrap();
+    /// let mut messages = pub_sub_service.subscribe_query(query).await.unwrap();
+    ///
+    /// while let Some(message) = messages.try_next().await.unwrap() {
+    ///     let string = String::from_utf8(message.message.data).unwrap();
+    ///
+    ///     println!("Received message in {}: {string}", message.message.topic);
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, query))]
+    pub async fn subscribe_query(
+        &mut self,
+        query: impl Into<TopicQuery>,
+    ) -> Result<impl Stream<Item = Result<ReceivedMessage>>> {
+        let query = grpc::TopicQuery::from(query.into());
+
+        self.client
+            .subscribe_query(query)
+            .await
+            .map(|response| {
+                response
+                    .into_inner()
+                    .map_ok(TryInto::try_into)
+                    .map(|res| res?.map_err(Into::into))
+            })
+            .map_err(Into::into)
+    }
+
     /// Subscribes to a topic and returns a stream of JSON-encoded messages published to that
     /// topic.
     ///
@@ -289,6 +342,56 @@ impl Service {
             .map_err(Into::into)
     }
 
+    /// Publishes a message to a topic and stores it as the retained message of the topic.
+    ///
+    /// The retained message is kept in the DHT and delivered to every new subscriber of the
+    /// topic as soon as it is found, until it is replaced by the next retained message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails, e.g. if the message couldn't be stored in the DHT.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut pub_sub_service = connection.pub_sub();
+    /// let id = pub_sub_service.publish_retained("pump/mode", "eco").await.unwrap();
+    ///
+    /// println!("Published retained message with id: {id}");
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, topic, data), fields(topic))]
+    pub async fn publish_retained(
+        &mut self,
+        topic: impl Into<String>,
+        data: impl Into<Vec<u8>>,
+    ) -> Result<MessageId> {
+        let topic = topic.into();
+
+        tracing::Span::current().record("topic", &topic);
+
+        let message = Message {
+            topic,
+            data: data.into(),
+        };
+
+        let request = PubSubMessage {
+            retain: Some(true),
+            ..message.into()
+        };
+
+        self.client
+            .publish(request)
+            .await
+            .map(|response| response.into_inner().into())
+            .map_err(Into::into)
+    }
+
     /// Publishes a JSON-encoded message to a topic.
     ///
     /// # Errors
@@ -364,4 +467,279 @@ impl Service {
 
         self.publish(topic, data).await
     }
+
+    /// Subscribes to a reliable topic and returns a stream of events in that topic.
+    ///
+    /// Messages of each publisher are delivered in the order in which they were published. Missed
+    /// messages are fetched from the bounded history of the publisher; if they can't be recovered,
+    /// a [`ReliableEvent::Lost`] event is emitted instead. Messages published before the first
+    /// message received from a publisher are not replayed. Reliable topics are separate from
+    /// regular topics with the same name, messages are published with [`Self::publish_reliable`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails. The stream emits errors that occur in the runtime
+    /// while processing the messages, e.g. if the stream was not polled fast enough and events
+    /// were dropped, as well as data conversion errors.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use futures::TryStreamExt as _;
+    /// use hyveos_sdk::{services::pub_sub::ReliableEvent, Connection};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut pub_sub_service = connection.pub_sub();
+    /// let mut events = pub_sub_service.subscribe_reliable("control").await.unwrap();
+    ///
+    /// while let Some(event) = events.try_next().await.unwrap() {
+    ///     match event {
+    ///         ReliableEvent::Message(message) => {
+    ///             let string = String::from_utf8(message.message.data).unwrap();
+    ///             println!("Received message: {string}");
+    ///         }
+    ///         ReliableEvent::Lost { publisher, count } => {
+    ///             println!("Lost {count} messages from {publisher}");
+    ///         }
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, topic), fields(topic))]
+    pub async fn subscribe_reliable(
+        &mut self,
+        topic: impl Into<String>,
+    ) -> Result<impl Stream<Item = Result<ReliableEvent>>> {
+        let topic = topic.into();
+
+        tracing::Span::current().record("topic", &topic);
+
+        let topic = Topic { topic };
+
+        self.client
+            .subscribe_reliable(topic)
+            .await
+            .map(|response| {
+                response
+                    .into_inner()
+                    .map_ok(TryInto::try_into)
+                    .map(|res| res?.map_err(Into::into))
+            })
+            .map_err(Into::into)
+    }
+
+    /// Publishes a message to a reliable topic.
+    ///
+    /// The message is kept in a bounded history of the local runtime,
+    /// so subscribers that missed it can fetch it later.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut pub_sub_service = connection.pub_sub();
+    /// let id = pub_sub_service.publish_reliable("control", "stop").await.unwrap();
+    ///
+    /// println!("Published message with id: {id}");
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, topic, data), fields(topic))]
+    pub async fn publish_reliable(
+        &mut self,
+        topic: impl Into<String>,
+        data: impl Into<Vec<u8>>,
+    ) -> Result<MessageId> {
+        let topic = topic.into();
+
+        tracing::Span::current().record("topic", &topic);
+
+        let message = Message {
+            topic,
+            data: data.into(),
+        };
+
+        self.client
+            .publish_reliable(PubSubMessage::from(message))
+            .await
+            .map(|response| response.into_inner().into())
+            .map_err(Into::into)
+    }
+
+    /// Registers as the validator of a topic and returns a stream of messages waiting for
+    /// validation.
+    ///
+    /// While the validator is registered, messages received in the topic are only delivered to
+    /// subscribers and forwarded to other peers after they were accepted with
+    /// [`Self::report_validation_result`]. Rejected messages are dropped and the peer that
+    /// propagated them is penalised. Messages that aren't validated within a few seconds are
+    /// ignored. Dropping the stream unregisters the validator.
+    ///
+    /// Only one validator can be registered per topic at a time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails, e.g. if another validator is already registered
+    /// for the topic. The stream emits errors that occur in the runtime while processing the
+    /// messages, as well as data conversion errors.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use futures::TryStreamExt as _;
+    /// use hyveos_sdk::{services::pub_sub::MessageAcceptance, Connection};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut pub_sub_service = connection.pub_sub();
+    /// let mut messages = pub_sub_service.validate("topic").await.unwrap();
+    ///
+    /// while let Some(message) = messages.try_next().await.unwrap() {
+    ///     let acceptance = if String::from_utf8(message.message.data).is_ok() {
+    ///         MessageAcceptance::Accept
+    ///     } else {
+    ///         MessageAcceptance::Reject
+    ///     };
+    ///
+    ///     pub_sub_service
+    ///         .report_validation_result(message.message_id, acceptance)
+    ///         .await
+    ///         .unwrap();
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, topic), fields(topic))]
+    pub async fn validate(
+        &mut self,
+        topic: impl Into<String>,
+    ) -> Result<impl Stream<Item = Result<ReceivedMessage>>> {
+        let topic = topic.into();
+
+        tracing::Span::current().record("topic", &topic);
+
+        let topic = Topic { topic };
+
+        self.client
+            .validate(topic)
+            .await
+            .map(|response| {
+                response
+                    .into_inner()
+                    .map_ok(TryInto::try_into)
+                    .map(|res| res?.map_err(Into::into))
+            })
+            .map_err(Into::into)
+    }
+
+    /// Reports the verdict about a message received from [`Self::validate`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails, e.g. if the message is not waiting for validation
+    /// anymore.
+    #[tracing::instrument(skip(self))]
+    pub async fn report_validation_result(
+        &mut self,
+        message_id: MessageId,
+        acceptance: MessageAcceptance,
+    ) -> Result<()> {
+        let result = ValidationResult {
+            message_id,
+            acceptance,
+        };
+
+        self.client
+            .report_validation_result(PubSubValidationResult::from(result))
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    /// Lists the topics this node is subscribed to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut pub_sub_service = connection.pub_sub();
+    ///
+    /// for topic in pub_sub_service.list_topics().await.unwrap() {
+    ///     println!("Subscribed to {topic}");
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self))]
+    pub async fn list_topics(&mut self) -> Result<Vec<String>> {
+        self.client
+            .list_topics(Empty {})
+            .await
+            .map(|response| {
+                response
+                    .into_inner()
+                    .topics
+                    .into_iter()
+                    .map(|topic| topic.topic)
+                    .collect()
+            })
+            .map_err(Into::into)
+    }
+
+    /// Gets the peers that are known to be subscribed to a topic and the peers in the gossipsub
+    /// mesh of the topic.
+    ///
+    /// Messages published in the topic are forwarded directly to the mesh peers, all other
+    /// subscribers receive them from there.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut pub_sub_service = connection.pub_sub();
+    /// let peers = pub_sub_service.topic_peers("topic").await.unwrap();
+    ///
+    /// println!("Mesh peers:");
+    /// for peer_id in peers.mesh {
+    ///     println!("- {peer_id}");
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, topic), fields(topic))]
+    pub async fn topic_peers(&mut self, topic: impl Into<String>) -> Result<TopicPeers> {
+        let topic = topic.into();
+
+        tracing::Span::current().record("topic", &topic);
+
+        self.client
+            .topic_peers(Topic { topic })
+            .await?
+            .into_inner()
+            .try_into()
+            .map_err(Into::into)
+    }
 }
@@ -1,4 +1,4 @@
-use std::marker::PhantomData;
+use std::{marker::PhantomData, time::Duration};
 
 #[cfg(feature = "serde")]
 use derive_where::derive_where;
@@ -7,7 +7,11 @@ use futures::future;
 use futures::{Stream, StreamExt as _, TryStreamExt as _};
 pub use hyveos_core::req_resp::{Request, Response, ResponseError, TopicQuery};
 use hyveos_core::{
-    grpc::{req_resp_client::ReqRespClient, OptionalTopicQuery, SendRequest, SendResponse},
+    dht::Key,
+    grpc::{
+        req_resp_client::ReqRespClient, CallRequest, OptionalTopicQuery, PeerResponse, ResponseEnd,
+        SendProvidersRequest, SendRequest, SendResponse,
+    },
     req_resp::InboundRequest as CoreInboundRequest,
 };
 use libp2p_identity::PeerId;
@@ -55,19 +59,32 @@ pub struct InboundRequest<T> {
 #[must_use = "requests require a response"]
 pub struct InboundRequestHandle<'a> {
     id: u64,
+    streaming: bool,
     service: Service,
     phantom: PhantomData<&'a ()>,
 }
 
 impl InboundRequestHandle<'_> {
-    fn new(id: u64, service: Service) -> Self {
+    fn new(id: u64, streaming: bool, service: Service) -> Self {
         Self {
             id,
+            streaming,
             service,
             phantom: PhantomData,
         }
     }
 
+    /// Returns whether the requester expects a stream of response chunks.
+    ///
+    /// A streaming request can be answered with any number of chunks using
+    /// [`InboundRequestHandle::respond_chunk`], followed by [`InboundRequestHandle::end`].
+    /// Responding with [`InboundRequestHandle::respond`] sends a single chunk and ends the
+    /// response.
+    #[must_use]
+    pub fn is_streaming(&self) -> bool {
+        self.streaming
+    }
+
     /// Responds to this request with a successful response.
     ///
     /// # Errors
@@ -129,6 +146,67 @@ impl InboundRequestHandle<'_> {
             .await
     }
 
+    /// Sends one chunk of the response to this streaming request.
+    ///
+    /// Waits while the requester is not keeping up with the chunks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails, if this request is not a streaming request, or if
+    /// the requester cancelled the request.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use futures::TryStreamExt as _;
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut req_resp_service = connection.req_resp();
+    /// let mut requests = req_resp_service.recv(None).await.unwrap();
+    ///
+    /// while let Some((_, mut handle)) = requests.try_next().await.unwrap() {
+    ///     if handle.is_streaming() {
+    ///         for i in 0..10 {
+    ///             handle.respond_chunk(format!("Chunk {i}")).await.unwrap();
+    ///         }
+    ///         handle.end().await.unwrap();
+    ///     } else {
+    ///         handle.respond("Hello from the other side!").await.unwrap();
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn respond_chunk(&mut self, data: impl Into<Vec<u8>>) -> Result<()> {
+        self.service
+            .respond_chunk(self.id, Response::Data(data.into()))
+            .await
+    }
+
+    /// Sends an error as one chunk of the response to this streaming request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails, if this request is not a streaming request, or if
+    /// the requester cancelled the request.
+    pub async fn respond_chunk_with_error(&mut self, error: impl Into<String>) -> Result<()> {
+        self.service
+            .respond_chunk(self.id, Response::Error(error.into().into()))
+            .await
+    }
+
+    /// Ends the response to this streaming request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails, if this request is not a streaming request, or if
+    /// the requester cancelled the request.
+    pub async fn end(mut self) -> Result<()> {
+        self.service.end_response(self.id).await
+    }
+
     #[doc(hidden)]
     pub fn id(&self) -> u64 {
         self.id
@@ -163,6 +241,9 @@ pub trait TypedService {
 
     async fn respond(&mut self, id: u64, response: &Self::Resp) -> Result<()>;
     async fn respond_with_error(&mut self, id: u64, error: impl Into<String>) -> Result<()>;
+    async fn respond_chunk(&mut self, id: u64, response: &Self::Resp) -> Result<()>;
+    async fn respond_chunk_with_error(&mut self, id: u64, error: impl Into<String>) -> Result<()>;
+    async fn end_response(&mut self, id: u64) -> Result<()>;
 }
 
 /// A handle that lets you respond to an inbound request.
@@ -205,6 +286,7 @@ pub trait TypedService {
 #[must_use = "requests require a response"]
 pub struct TypedInboundRequestHandle<'a, Service, Resp> {
     id: u64,
+    streaming: bool,
     service: Service,
     phantom: PhantomData<&'a Resp>,
 }
@@ -215,14 +297,26 @@ where
     Service: TypedService<Resp = Resp>,
     Resp: Serialize + DeserializeOwned,
 {
-    fn new(id: u64, service: Service) -> Self {
+    fn new(id: u64, streaming: bool, service: Service) -> Self {
         Self {
             id,
+            streaming,
             service,
             phantom: PhantomData,
         }
     }
 
+    /// Returns whether the requester expects a stream of response chunks.
+    ///
+    /// A streaming request can be answered with any number of chunks using
+    /// [`TypedInboundRequestHandle::respond_chunk`], followed by
+    /// [`TypedInboundRequestHandle::end`]. Responding with [`TypedInboundRequestHandle::respond`]
+    /// sends a single chunk and ends the response.
+    #[must_use]
+    pub fn is_streaming(&self) -> bool {
+        self.streaming
+    }
+
     /// Responds to this request with a successful response.
     ///
     /// # Errors
@@ -303,6 +397,70 @@ where
     pub async fn respond_with_error(mut self, error: impl Into<String>) -> Result<()> {
         self.service.respond_with_error(self.id, error).await
     }
+
+    /// Sends one chunk of the response to this streaming request.
+    ///
+    /// Waits while the requester is not keeping up with the chunks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call or serialization fails, if this request is not a streaming
+    /// request, or if the requester cancelled the request.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use futures::TryStreamExt as _;
+    /// use hyveos_sdk::Connection;
+    /// use serde::{Serialize, Deserialize};
+    ///
+    /// #[derive(Debug, Serialize, Deserialize)]
+    /// struct ExampleRequest {
+    ///    count: usize,
+    /// }
+    ///
+    /// #[derive(Debug, Serialize, Deserialize)]
+    /// struct ExampleResponse {
+    ///    index: usize,
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut req_resp_service = connection.req_resp_json::<ExampleRequest, ExampleResponse>();
+    /// let mut requests = req_resp_service.recv(None).await.unwrap();
+    ///
+    /// while let Some((request, mut handle)) = requests.try_next().await.unwrap() {
+    ///     for index in 0..request.data.count {
+    ///         handle.respond_chunk(&ExampleResponse { index }).await.unwrap();
+    ///     }
+    ///     handle.end().await.unwrap();
+    /// }
+    /// # }
+    /// ```
+    pub async fn respond_chunk(&mut self, data: &Resp) -> Result<()> {
+        self.service.respond_chunk(self.id, data).await
+    }
+
+    /// Sends an error as one chunk of the response to this streaming request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails, if this request is not a streaming request, or if
+    /// the requester cancelled the request.
+    pub async fn respond_chunk_with_error(&mut self, error: impl Into<String>) -> Result<()> {
+        self.service.respond_chunk_with_error(self.id, error).await
+    }
+
+    /// Ends the response to this streaming request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails, if this request is not a streaming request, or if
+    /// the requester cancelled the request.
+    pub async fn end(mut self) -> Result<()> {
+        self.service.end_response(self.id).await
+    }
 }
 
 /// A handle to the request-response service.
@@ -396,14 +554,79 @@ impl Service {
         data: impl Into<Vec<u8>>,
         topic: Option<String>,
     ) -> Result<Response> {
-        let request = Request {
-            data: data.into(),
-            topic: topic.map(Into::into),
-        };
+        self.send(peer_id, data.into(), topic, None).await
+    }
+
+    /// Sends a request with an optional topic to a peer and returns the response, giving up
+    /// after the given timeout.
+    ///
+    /// The timeout has a resolution of milliseconds and is capped at five minutes. If no response
+    /// is received in time, the response is [`ResponseError::Timeout`]. Dropping the returned
+    /// future cancels the request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use futures::StreamExt as _;
+    /// use hyveos_sdk::{
+    ///     services::req_resp::{Response, ResponseError},
+    ///     Connection,
+    /// };
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut discovery_service = connection.discovery();
+    /// let peer_id = discovery_service
+    ///     .get_providers("identification", "example")
+    ///     .await
+    ///     .unwrap()
+    ///     .next()
+    ///     .await
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// let mut req_resp_service = connection.req_resp();
+    /// let response = req_resp_service
+    ///     .send_request_with_timeout(peer_id, "ping", None, Duration::from_secs(5))
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// if let Response::Error(ResponseError::Timeout) = response {
+    ///     println!("Peer did not respond in time");
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, data))]
+    pub async fn send_request_with_timeout(
+        &mut self,
+        peer_id: PeerId,
+        data: impl Into<Vec<u8>>,
+        topic: Option<String>,
+        timeout: Duration,
+    ) -> Result<Response> {
+        self.send(peer_id, data.into(), topic, Some(timeout)).await
+    }
+
+    async fn send(
+        &mut self,
+        peer_id: PeerId,
+        data: Vec<u8>,
+        topic: Option<String>,
+        timeout: Option<Duration>,
+    ) -> Result<Response> {
+        let request = Request { data, topic };
 
         let request = SendRequest {
             peer: peer_id.into(),
             msg: request.into(),
+            timeout_ms: timeout.map(|timeout| timeout.as_millis().try_into().unwrap_or(u64::MAX)),
         };
 
         self.client
@@ -414,6 +637,269 @@ impl Service {
             .map_err(Into::into)
     }
 
+    /// Sends a request with an optional topic to a peer and returns the stream of response
+    /// chunks.
+    ///
+    /// The peer must be subscribed to the topic (using [`Service::recv`]) in order to receive the
+    /// request, and can answer it with any number of chunks. The stream ends after the last chunk,
+    /// or with an error response if the request fails. Dropping the stream cancels the request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails. The stream emits errors that occur in the runtime
+    /// while receiving the chunks, as well as data conversion errors.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use futures::{StreamExt as _, TryStreamExt as _};
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut discovery_service = connection.discovery();
+    /// let peer_id = discovery_service
+    ///     .get_providers("identification", "example")
+    ///     .await
+    ///     .unwrap()
+    ///     .next()
+    ///     .await
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// let mut req_resp_service = connection.req_resp();
+    /// let mut responses = req_resp_service
+    ///     .send_streaming_request(peer_id, "Count to ten", None)
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// while let Some(response) = responses.try_next().await.unwrap() {
+    ///     let data = Vec::try_from(response).unwrap();
+    ///     println!("Received chunk: {}", String::from_utf8(data).unwrap());
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, data))]
+    pub async fn send_streaming_request(
+        &mut self,
+        peer_id: PeerId,
+        data: impl Into<Vec<u8>>,
+        topic: Option<String>,
+    ) -> Result<impl Stream<Item = Result<Response>>> {
+        let request = Request {
+            data: data.into(),
+            topic,
+        };
+
+        let request = SendRequest {
+            peer: peer_id.into(),
+            msg: request.into(),
+            timeout_ms: None,
+        };
+
+        self.client
+            .send_streaming(request)
+            .await
+            .map(|response| {
+                response
+                    .into_inner()
+                    .map_ok(TryInto::try_into)
+                    .map(|res| res?.map_err(Into::into))
+            })
+            .map_err(Into::into)
+    }
+
+    /// Sends a request with an optional topic to all providers of a discovery key and returns a
+    /// stream of their responses as they arrive.
+    ///
+    /// The providers are looked up like with
+    /// [`discovery::Service::get_providers`](crate::services::discovery::Service::get_providers).
+    /// At most `max_peers` providers receive the request, if it is set. The provider lookup and
+    /// all requests are given up after `timeout`, which has a resolution of milliseconds and is
+    /// capped at five minutes. Requests that are still running by then are answered with
+    /// [`ResponseError::Timeout`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails. The stream emits errors that occur in the runtime
+    /// while sending the requests, as well as data conversion errors.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use futures::TryStreamExt as _;
+    /// use hyveos_sdk::{services::req_resp::Response, Connection};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut req_resp_service = connection.req_resp();
+    /// let mut responses = req_resp_service
+    ///     .send_to_providers(
+    ///         "identification",
+    ///         "example",
+    ///         "Hello, world!",
+    ///         None,
+    ///         Some(Duration::from_secs(10)),
+    ///         None,
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// while let Some((peer_id, response)) = responses.try_next().await.unwrap() {
+    ///     if let Response::Data(data) = response {
+    ///         println!("Received response from {peer_id}: {}", String::from_utf8(data).unwrap());
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, discovery_topic, key, data))]
+    pub async fn send_to_providers(
+        &mut self,
+        discovery_topic: impl Into<String>,
+        key: impl Into<Vec<u8>>,
+        data: impl Into<Vec<u8>>,
+        topic: Option<String>,
+        timeout: Option<Duration>,
+        max_peers: Option<usize>,
+    ) -> Result<impl Stream<Item = Result<(PeerId, Response)>>> {
+        let key = Key {
+            topic: discovery_topic.into(),
+            key: key.into(),
+        };
+        let request = Request {
+            data: data.into(),
+            topic,
+        };
+
+        let request = SendProvidersRequest {
+            key: key.into(),
+            msg: request.into(),
+            timeout_ms: timeout.map(|timeout| timeout.as_millis().try_into().unwrap_or(u64::MAX)),
+            max_peers: max_peers.map(|max_peers| max_peers.try_into().unwrap_or(u64::MAX)),
+        };
+
+        self.client
+            .send_to_providers(request)
+            .await
+            .map(|response| {
+                response
+                    .into_inner()
+                    .map_ok(|PeerResponse { peer, response }| -> Result<_> {
+                        Ok((PeerId::try_from(peer)?, Response::try_from(response)?))
+                    })
+                    .map(|res| res?)
+            })
+            .map_err(Into::into)
+    }
+
+    /// Sends a request with an optional topic to one of the providers of a discovery key and
+    /// returns the provider together with its response.
+    ///
+    /// Providers that are neighbours are preferred, and consecutive calls are spread across the
+    /// providers. If a provider fails or times out, the request is retried on the next one. See
+    /// [`CallOptions`] for the timeout, the number of attempts, and how long the providers are
+    /// cached. To call the same service repeatedly, use [`Service::service_client`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails or if no provider was found.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::{services::req_resp::CallOptions, Connection};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut req_resp_service = connection.req_resp();
+    /// let (peer_id, response) = req_resp_service
+    ///     .call("services", "echo", "Hello, world!", None, CallOptions::new())
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let data = Vec::try_from(response).unwrap();
+    /// println!("Received response from {peer_id}: {}", String::from_utf8(data).unwrap());
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, discovery_topic, key, data))]
+    pub async fn call(
+        &mut self,
+        discovery_topic: impl Into<String>,
+        key: impl Into<Vec<u8>>,
+        data: impl Into<Vec<u8>>,
+        topic: Option<String>,
+        options: CallOptions,
+    ) -> Result<(PeerId, Response)> {
+        let key = Key {
+            topic: discovery_topic.into(),
+            key: key.into(),
+        };
+        let request = Request {
+            data: data.into(),
+            topic,
+        };
+
+        let CallOptions {
+            timeout,
+            max_attempts,
+            refresh_interval,
+        } = options;
+
+        let request = CallRequest {
+            key: key.into(),
+            msg: request.into(),
+            timeout_ms: timeout.map(|timeout| timeout.as_millis().try_into().unwrap_or(u64::MAX)),
+            max_attempts: max_attempts
+                .map(|max_attempts| max_attempts.try_into().unwrap_or(u64::MAX)),
+            refresh_interval_ms: refresh_interval.map(|refresh_interval| {
+                refresh_interval.as_millis().try_into().unwrap_or(u64::MAX)
+            }),
+        };
+
+        let PeerResponse { peer, response } = self.client.call(request).await?.into_inner();
+
+        Ok((peer.try_into()?, response.try_into()?))
+    }
+
+    /// Returns a client that sends requests to any provider of a discovery key.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut echo = connection.req_resp().service_client("services", "echo");
+    ///
+    /// for i in 0..10 {
+    ///     let (peer_id, response) = echo.call(format!("Hello #{i}")).await.unwrap();
+    ///     let data = Vec::try_from(response).unwrap();
+    ///     println!("{peer_id} answered: {}", String::from_utf8(data).unwrap());
+    /// }
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn service_client(
+        &self,
+        discovery_topic: impl Into<String>,
+        key: impl Into<Vec<u8>>,
+    ) -> ServiceClient {
+        ServiceClient {
+            service: self.clone(),
+            discovery_topic: discovery_topic.into(),
+            key: key.into(),
+            topic: None,
+            options: CallOptions::new(),
+        }
+    }
+
     /// Subscribes to a topic and returns a stream of tuples of requests sent by peers to this
     /// topic together with an [`InboundRequestHandle`], providing methods to respond to the
     /// request.
@@ -448,9 +934,64 @@ impl Service {
         &mut self,
         query: Option<TopicQuery>,
     ) -> Result<impl Stream<Item = Result<(InboundRequest<Vec<u8>>, InboundRequestHandle<'_>)>>>
+    {
+        self.subscribe(query, None).await
+    }
+
+    /// Like [`Service::recv`], but requests that aren't responded to within `response_timeout`
+    /// are answered with [`ResponseError::Timeout`] by the runtime.
+    ///
+    /// Without a response timeout, requests are answered after the maximum request timeout
+    /// of 300 seconds. Either way, requests are answered with an error as soon as the returned
+    /// stream is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails. The stream emits errors that occur in the runtime
+    /// while processing the providers, as well as data conversion errors.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use futures::TryStreamExt as _;
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut req_resp_service = connection.req_resp();
+    /// let mut requests = req_resp_service
+    ///     .recv_with_response_timeout(None, Duration::from_secs(5))
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// while let Some((_request, handle)) = requests.try_next().await.unwrap() {
+    ///     handle.respond("Hello from the other side!").await.unwrap();
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self))]
+    pub async fn recv_with_response_timeout(
+        &mut self,
+        query: Option<TopicQuery>,
+        response_timeout: Duration,
+    ) -> Result<impl Stream<Item = Result<(InboundRequest<Vec<u8>>, InboundRequestHandle<'_>)>>>
+    {
+        self.subscribe(query, Some(response_timeout)).await
+    }
+
+    async fn subscribe(
+        &mut self,
+        query: Option<TopicQuery>,
+        response_timeout: Option<Duration>,
+    ) -> Result<impl Stream<Item = Result<(InboundRequest<Vec<u8>>, InboundRequestHandle<'_>)>>>
     {
         let query = OptionalTopicQuery {
             query: query.map(Into::into),
+            response_timeout_ms: response_timeout
+                .map(|timeout| timeout.as_millis().try_into().unwrap_or(u64::MAX)),
         };
 
         self.client
@@ -466,6 +1007,7 @@ impl Service {
                                 id,
                                 peer_id,
                                 req: Request { data, topic },
+                                streaming,
                             } = req.try_into()?;
 
                             let request = InboundRequest {
@@ -474,29 +1016,165 @@ impl Service {
                                 data,
                             };
 
-                            let handle = InboundRequestHandle::new(id, service.clone());
+                            let handle = InboundRequestHandle::new(id, streaming, service.clone());
+
+                            Ok((request, handle))
+                        })
+                        .map(|res| res?)
+                }
+            })
+            .map_err(Into::into)
+    }
+
+    #[doc(hidden)]
+    #[tracing::instrument(skip(self))]
+    pub async fn respond(&mut self, id: u64, response: Response) -> Result<()> {
+        let send_response = SendResponse {
+            seq: id,
+            response: response.into(),
+        };
+
+        self.client
+            .respond(send_response)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    #[doc(hidden)]
+    #[tracing::instrument(skip(self))]
+    pub async fn respond_chunk(&mut self, id: u64, response: Response) -> Result<()> {
+        let send_response = SendResponse {
+            seq: id,
+            response: response.into(),
+        };
+
+        self.client
+            .respond_chunk(send_response)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    #[doc(hidden)]
+    #[tracing::instrument(skip(self))]
+    pub async fn end_response(&mut self, id: u64) -> Result<()> {
+        self.client
+            .end_response(ResponseEnd { seq: id })
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+}
+
+/// Options for calls to any provider of a discovery key.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use hyveos_sdk::{services::req_resp::CallOptions, Connection};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let connection = Connection::new().await.unwrap();
+/// let options = CallOptions::new()
+///     .timeout(Duration::from_secs(2))
+///     .max_attempts(5)
+///     .refresh_interval(Duration::from_secs(30));
+/// let mut echo = connection
+///     .req_resp()
+///     .service_client("services", "echo")
+///     .options(options);
+/// let (peer_id, _) = echo.call("Hello, world!").await.unwrap();
+/// println!("Called {peer_id}");
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CallOptions {
+    pub timeout: Option<Duration>,
+    pub max_attempts: Option<usize>,
+    pub refresh_interval: Option<Duration>,
+}
+
+impl CallOptions {
+    /// Creates new options that try up to three providers with the default request timeout and
+    /// cache the providers for a minute.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the time after which the request to a provider is given up and retried on the next
+    /// one.
+    ///
+    /// The timeout has a resolution of milliseconds and is capped at five minutes.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum number of providers that are tried.
+    #[must_use]
+    pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Sets how long the providers of the discovery key are cached before they are looked up
+    /// again.
+    #[must_use]
+    pub fn refresh_interval(mut self, refresh_interval: Duration) -> Self {
+        self.refresh_interval = Some(refresh_interval);
+        self
+    }
+}
+
+/// A client that sends requests to any provider of a discovery key.
+///
+/// Created with [`Service::service_client`]. See [`Service::call`] for how the provider is chosen.
+#[derive(Debug, Clone)]
+pub struct ServiceClient {
+    service: Service,
+    discovery_topic: String,
+    key: Vec<u8>,
+    topic: Option<String>,
+    options: CallOptions,
+}
 
-                            Ok((request, handle))
-                        })
-                        .map(|res| res?)
-                }
-            })
-            .map_err(Into::into)
+impl ServiceClient {
+    /// Sets the topic the requests are sent with.
+    #[must_use]
+    pub fn topic(mut self, topic: impl Into<String>) -> Self {
+        self.topic = Some(topic.into());
+        self
     }
 
-    #[doc(hidden)]
-    #[tracing::instrument(skip(self))]
-    pub async fn respond(&mut self, id: u64, response: Response) -> Result<()> {
-        let send_response = SendResponse {
-            seq: id,
-            response: response.into(),
-        };
+    /// Sets the options of the calls.
+    #[must_use]
+    pub fn options(mut self, options: CallOptions) -> Self {
+        self.options = options;
+        self
+    }
 
-        self.client
-            .respond(send_response)
+    /// Sends a request to one of the providers and returns the provider together with its
+    /// response.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails or if no provider was found.
+    pub async fn call(&mut self, data: impl Into<Vec<u8>>) -> Result<(PeerId, Response)> {
+        self.service
+            .call(
+                self.discovery_topic.clone(),
+                self.key.clone(),
+                data,
+                self.topic.clone(),
+                self.options,
+            )
             .await
-            .map(|_| ())
-            .map_err(Into::into)
     }
 }
 
@@ -644,6 +1322,128 @@ where
         }
     }
 
+    /// Sends a request with an optional topic to a peer and returns the stream of response
+    /// chunks.
+    ///
+    /// The peer must be subscribed to the topic (using [`JsonService::recv`]) in order to receive
+    /// the request, and can answer it with any number of chunks. The stream ends after the last
+    /// chunk, or with an error response if the request fails. Dropping the stream cancels the
+    /// request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call or serialization fails. The stream emits errors that
+    /// occur in the runtime while receiving the chunks, as well as deserialization errors.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use futures::{StreamExt as _, TryStreamExt as _};
+    /// use hyveos_sdk::Connection;
+    /// use serde::{Serialize, Deserialize};
+    ///
+    /// #[derive(Debug, Serialize, Deserialize)]
+    /// struct ExampleRequest {
+    ///    count: usize,
+    /// }
+    ///
+    /// #[derive(Debug, Serialize, Deserialize)]
+    /// struct ExampleResponse {
+    ///    index: usize,
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut discovery_service = connection.discovery();
+    /// let peer_id = discovery_service
+    ///     .get_providers("identification", "example")
+    ///     .await
+    ///     .unwrap()
+    ///     .next()
+    ///     .await
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// let mut req_resp_service = connection.req_resp_json();
+    /// let request = ExampleRequest { count: 10 };
+    /// let mut responses = req_resp_service
+    ///     .send_streaming_request(peer_id, &request, None)
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// while let Some(response) = responses.try_next().await.unwrap() {
+    ///     let data: ExampleResponse = Result::from(response).unwrap();
+    ///     println!("Received chunk: {data:?}");
+    /// }
+    /// # }
+    /// ```
+    pub async fn send_streaming_request(
+        &mut self,
+        peer_id: PeerId,
+        data: &Req,
+        topic: Option<String>,
+    ) -> Result<impl Stream<Item = Result<TypedResponse<Resp>>>> {
+        let data = serde_json::to_vec(data)?;
+
+        let responses = self
+            .inner
+            .send_streaming_request(peer_id, data, topic)
+            .await?;
+
+        Ok(responses.and_then(|response| {
+            future::ready(match response {
+                Response::Data(data) => serde_json::from_slice(&data)
+                    .map(TypedResponse::Data)
+                    .map_err(Into::into),
+                Response::Error(error) => Ok(TypedResponse::Error(error)),
+            })
+        }))
+    }
+
+    /// Returns a client that sends requests to any provider of a discovery key.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::Connection;
+    /// use serde::{Serialize, Deserialize};
+    ///
+    /// #[derive(Debug, Serialize, Deserialize)]
+    /// struct ExampleRequest {
+    ///    message: String,
+    /// }
+    ///
+    /// #[derive(Debug, Serialize, Deserialize)]
+    /// struct ExampleResponse {
+    ///    message: String,
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut echo = connection
+    ///     .req_resp_json::<ExampleRequest, ExampleResponse>()
+    ///     .service_client("services", "echo");
+    ///
+    /// let request = ExampleRequest { message: "Hello, world!".to_string() };
+    /// let (peer_id, response) = echo.call(&request).await.unwrap();
+    /// let data: ExampleResponse = Result::from(response).unwrap();
+    /// println!("{peer_id} answered: {data:?}");
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn service_client(
+        &self,
+        discovery_topic: impl Into<String>,
+        key: impl Into<Vec<u8>>,
+    ) -> JsonServiceClient<Req, Resp> {
+        JsonServiceClient {
+            inner: self.inner.service_client(discovery_topic, key),
+            _phantom: PhantomData,
+        }
+    }
+
     /// Subscribes to a topic and returns a stream of requests sent by peers to this topic, wrapped
     /// into an [`InboundRequestHandle`], providing methods to respond to the request.
     ///
@@ -703,7 +1503,12 @@ where
                     topic,
                     data,
                 } = request;
-                let InboundRequestHandle { id, service, .. } = handle;
+                let InboundRequestHandle {
+                    id,
+                    streaming,
+                    service,
+                    ..
+                } = handle;
 
                 let service = Self::new_from(service);
 
@@ -716,7 +1521,7 @@ where
                                 data,
                             };
 
-                            let handle = TypedInboundRequestHandle::new(id, service);
+                            let handle = TypedInboundRequestHandle::new(id, streaming, service);
 
                             (request, handle)
                         })
@@ -748,6 +1553,76 @@ where
 
         self.inner.respond(id, response).await
     }
+
+    async fn respond_chunk(&mut self, id: u64, data: &Resp) -> Result<()> {
+        let data = serde_json::to_vec(data)?;
+
+        let response = Response::Data(data);
+
+        self.inner.respond_chunk(id, response).await
+    }
+
+    async fn respond_chunk_with_error(&mut self, id: u64, error: impl Into<String>) -> Result<()> {
+        let response = Response::Error(error.into().into());
+
+        self.inner.respond_chunk(id, response).await
+    }
+
+    async fn end_response(&mut self, id: u64) -> Result<()> {
+        self.inner.end_response(id).await
+    }
+}
+
+/// A client that sends JSON-encoded requests to any provider of a discovery key.
+///
+/// Created with [`JsonService::service_client`]. See [`Service::call`] for how the provider is
+/// chosen.
+#[cfg(feature = "json")]
+#[derive_where(Debug, Clone)]
+pub struct JsonServiceClient<Req, Resp> {
+    inner: ServiceClient,
+    _phantom: PhantomData<(Req, Resp)>,
+}
+
+#[cfg(feature = "json")]
+impl<Req, Resp> JsonServiceClient<Req, Resp>
+where
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    /// Sets the topic the requests are sent with.
+    #[must_use]
+    pub fn topic(mut self, topic: impl Into<String>) -> Self {
+        self.inner = self.inner.topic(topic);
+        self
+    }
+
+    /// Sets the options of the calls.
+    #[must_use]
+    pub fn options(mut self, options: CallOptions) -> Self {
+        self.inner = self.inner.options(options);
+        self
+    }
+
+    /// Sends a request to one of the providers and returns the provider together with its
+    /// response.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call, serialization, or deserialization fails, or if no
+    /// provider was found.
+    pub async fn call(&mut self, data: &Req) -> Result<(PeerId, TypedResponse<Resp>)> {
+        let data = serde_json::to_vec(data)?;
+
+        let (peer_id, response) = self.inner.call(data).await?;
+
+        let response = match response {
+            Response::Data(data) => TypedResponse::Data(serde_json::from_slice(&data)?),
+            Response::Error(error) => TypedResponse::Error(error),
+        };
+
+        Ok((peer_id, response))
+    }
 }
 
 /// A handle to the request-response service with CBOR-encoded requests and responses.
@@ -894,6 +1769,128 @@ where
         }
     }
 
+    /// Sends a request with an optional topic to a peer and returns the stream of response
+    /// chunks.
+    ///
+    /// The peer must be subscribed to the topic (using [`CborService::recv`]) in order to receive
+    /// the request, and can answer it with any number of chunks. The stream ends after the last
+    /// chunk, or with an error response if the request fails. Dropping the stream cancels the
+    /// request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call or serialization fails. The stream emits errors that
+    /// occur in the runtime while receiving the chunks, as well as deserialization errors.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use futures::{StreamExt as _, TryStreamExt as _};
+    /// use hyveos_sdk::Connection;
+    /// use serde::{Serialize, Deserialize};
+    ///
+    /// #[derive(Debug, Serialize, Deserialize)]
+    /// struct ExampleRequest {
+    ///    count: usize,
+    /// }
+    ///
+    /// #[derive(Debug, Serialize, Deserialize)]
+    /// struct ExampleResponse {
+    ///    index: usize,
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut discovery_service = connection.discovery();
+    /// let peer_id = discovery_service
+    ///     .get_providers("identification", "example")
+    ///     .await
+    ///     .unwrap()
+    ///     .next()
+    ///     .await
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// let mut req_resp_service = connection.req_resp_cbor();
+    /// let request = ExampleRequest { count: 10 };
+    /// let mut responses = req_resp_service
+    ///     .send_streaming_request(peer_id, &request, None)
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// while let Some(response) = responses.try_next().await.unwrap() {
+    ///     let data: ExampleResponse = Result::from(response).unwrap();
+    ///     println!("Received chunk: {data:?}");
+    /// }
+    /// # }
+    /// ```
+    pub async fn send_streaming_request(
+        &mut self,
+        peer_id: PeerId,
+        data: &Req,
+        topic: Option<String>,
+    ) -> Result<impl Stream<Item = Result<TypedResponse<Resp>>>> {
+        let data = serde_cbor::to_vec(data)?;
+
+        let responses = self
+            .inner
+            .send_streaming_request(peer_id, data, topic)
+            .await?;
+
+        Ok(responses.and_then(|response| {
+            future::ready(match response {
+                Response::Data(data) => serde_cbor::from_slice(&data)
+                    .map(TypedResponse::Data)
+                    .map_err(Into::into),
+                Response::Error(error) => Ok(TypedResponse::Error(error)),
+            })
+        }))
+    }
+
+    /// Returns a client that sends requests to any provider of a discovery key.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::Connection;
+    /// use serde::{Serialize, Deserialize};
+    ///
+    /// #[derive(Debug, Serialize, Deserialize)]
+    /// struct ExampleRequest {
+    ///    message: String,
+    /// }
+    ///
+    /// #[derive(Debug, Serialize, Deserialize)]
+    /// struct ExampleResponse {
+    ///    message: String,
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut echo = connection
+    ///     .req_resp_cbor::<ExampleRequest, ExampleResponse>()
+    ///     .service_client("services", "echo");
+    ///
+    /// let request = ExampleRequest { message: "Hello, world!".to_string() };
+    /// let (peer_id, response) = echo.call(&request).await.unwrap();
+    /// let data: ExampleResponse = Result::from(response).unwrap();
+    /// println!("{peer_id} answered: {data:?}");
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn service_client(
+        &self,
+        discovery_topic: impl Into<String>,
+        key: impl Into<Vec<u8>>,
+    ) -> CborServiceClient<Req, Resp> {
+        CborServiceClient {
+            inner: self.inner.service_client(discovery_topic, key),
+            _phantom: PhantomData,
+        }
+    }
+
     /// Subscribes to a topic and returns a stream of requests sent by peers to this topic, wrapped
     /// into an [`InboundRequestHandle`], providing methods to respond to the request.
     ///
@@ -953,7 +1950,12 @@ where
                     topic,
                     data,
                 } = request;
-                let InboundRequestHandle { id, service, .. } = handle;
+                let InboundRequestHandle {
+                    id,
+                    streaming,
+                    service,
+                    ..
+                } = handle;
 
                 let service = Self::new_from(service);
 
@@ -966,7 +1968,7 @@ where
                                 data,
                             };
 
-                            let handle = TypedInboundRequestHandle::new(id, service);
+                            let handle = TypedInboundRequestHandle::new(id, streaming, service);
 
                             (request, handle)
                         })
@@ -998,4 +2000,74 @@ where
 
         self.inner.respond(id, response).await
     }
+
+    async fn respond_chunk(&mut self, id: u64, data: &Resp) -> Result<()> {
+        let data = serde_cbor::to_vec(data)?;
+
+        let response = Response::Data(data);
+
+        self.inner.respond_chunk(id, response).await
+    }
+
+    async fn respond_chunk_with_error(&mut self, id: u64, error: impl Into<String>) -> Result<()> {
+        let response = Response::Error(error.into().into());
+
+        self.inner.respond_chunk(id, response).await
+    }
+
+    async fn end_response(&mut self, id: u64) -> Result<()> {
+        self.inner.end_response(id).await
+    }
+}
+
+/// A client that sends CBOR-encoded requests to any provider of a discovery key.
+///
+/// Created with [`CborService::service_client`]. See [`Service::call`] for how the provider is
+/// chosen.
+#[cfg(feature = "cbor")]
+#[derive_where(Debug, Clone)]
+pub struct CborServiceClient<Req, Resp> {
+    inner: ServiceClient,
+    _phantom: PhantomData<(Req, Resp)>,
+}
+
+#[cfg(feature = "cbor")]
+impl<Req, Resp> CborServiceClient<Req, Resp>
+where
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    /// Sets the topic the requests are sent with.
+    #[must_use]
+    pub fn topic(mut self, topic: impl Into<String>) -> Self {
+        self.inner = self.inner.topic(topic);
+        self
+    }
+
+    /// Sets the options of the calls.
+    #[must_use]
+    pub fn options(mut self, options: CallOptions) -> Self {
+        self.inner = self.inner.options(options);
+        self
+    }
+
+    /// Sends a request to one of the providers and returns the provider together with its
+    /// response.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call, serialization, or deserialization fails, or if no
+    /// provider was found.
+    pub async fn call(&mut self, data: &Req) -> Result<(PeerId, TypedResponse<Resp>)> {
+        let data = serde_cbor::to_vec(data)?;
+
+        let (peer_id, response) = self.inner.call(data).await?;
+
+        let response = match response {
+            Response::Data(data) => TypedResponse::Data(serde_cbor::from_slice(&data)?),
+            Response::Error(error) => TypedResponse::Error(error),
+        };
+
+        Ok((peer_id, response))
+    }
 }
@@ -0,0 +1,341 @@
+use std::{
+    fmt, io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use futures::{
+    channel::mpsc,
+    future,
+    stream::{self, BoxStream},
+    StreamExt as _, TryStreamExt as _,
+};
+use hyveos_core::grpc::{
+    self, accept_stream_frame, accepted_stream_frame, forward_stream_frame, open_stream_frame,
+    stream_client::StreamClient,
+};
+use libp2p_identity::PeerId;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tonic::transport::Channel;
+
+use crate::{
+    connection::Connection,
+    error::{Error, Result},
+};
+
+/// The number of writes that are buffered before writing has to wait for the runtime.
+const WRITE_BUFFER: usize = 16;
+
+/// A handle to the stream service.
+///
+/// Exposes methods to open byte streams to apps on other peers and to accept byte streams
+/// opened by apps on other peers.
+///
+/// # Example
+///
+/// ```no_run
+/// use hyveos_sdk::Connection;
+/// use tokio::io::AsyncWriteExt as _;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let connection = Connection::new().await.unwrap();
+/// let mut stream_service = connection.stream();
+/// let (peer_id, mut stream) = stream_service.accept("echo").await.unwrap();
+///
+/// println!("Accepted stream from {peer_id}");
+/// stream.write_all(b"Hello from the other side!").await.unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Service {
+    client: StreamClient<Channel>,
+}
+
+impl Service {
+    pub(crate) fn new(connection: &Connection) -> Self {
+        let client = StreamClient::new(connection.channel.clone());
+
+        Self { client }
+    }
+
+    /// Opens a byte stream to an app on a peer that accepts streams for the protocol.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails or if the stream can't be opened, for example
+    /// because no app on the peer accepts streams for the protocol.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use futures::StreamExt as _;
+    /// use hyveos_sdk::Connection;
+    /// use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut discovery_service = connection.discovery();
+    /// let peer_id = discovery_service
+    ///     .get_providers("identification", "example")
+    ///     .await
+    ///     .unwrap()
+    ///     .next()
+    ///     .await
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// let mut stream_service = connection.stream();
+    /// let mut stream = stream_service.open(peer_id, "echo").await.unwrap();
+    ///
+    /// stream.write_all(b"Hello, world!").await.unwrap();
+    /// stream.shutdown().await.unwrap();
+    ///
+    /// let mut response = String::new();
+    /// stream.read_to_string(&mut response).await.unwrap();
+    /// println!("Received: {response}");
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, protocol))]
+    pub async fn open(
+        &mut self,
+        peer_id: PeerId,
+        protocol: impl Into<String>,
+    ) -> Result<AppStream> {
+        let request = grpc::OpenStreamRequest {
+            peer: peer_id.into(),
+            protocol: protocol.into(),
+        };
+
+        let (sender, receiver) = mpsc::channel(WRITE_BUFFER);
+        let frames = stream::once(future::ready(open_stream_frame::Frame::Open(request)))
+            .chain(receiver.map(|data| open_stream_frame::Frame::Data(grpc::Data { data })))
+            .map(|frame| grpc::OpenStreamFrame { frame: Some(frame) });
+
+        let incoming = self
+            .client
+            .open(frames)
+            .await?
+            .into_inner()
+            .map_ok(|data| data.data)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            .boxed();
+
+        Ok(AppStream::new(sender, incoming))
+    }
+
+    /// Waits for an app on another peer to open a byte stream for the protocol.
+    ///
+    /// Returns the peer that opened the stream together with the stream. Once an app accepted
+    /// streams for a protocol, streams for it are queued until they are accepted. If no app
+    /// waits for streams of the protocol for a while, it is closed again for other peers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails or if the runtime sends invalid data.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyveos_sdk::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut stream_service = connection.stream();
+    ///
+    /// loop {
+    ///     let (peer_id, stream) = stream_service.accept("echo").await.unwrap();
+    ///     println!("Accepted stream from {peer_id}");
+    ///
+    ///     tokio::spawn(async move {
+    ///         let (mut reader, mut writer) = tokio::io::split(stream);
+    ///         tokio::io::copy(&mut reader, &mut writer).await.unwrap();
+    ///     });
+    /// }
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self, protocol))]
+    pub async fn accept(&mut self, protocol: impl Into<String>) -> Result<(PeerId, AppStream)> {
+        let protocol = accept_stream_frame::Frame::Protocol(protocol.into());
+
+        let (sender, receiver) = mpsc::channel(WRITE_BUFFER);
+        let frames = stream::once(future::ready(protocol))
+            .chain(receiver.map(|data| accept_stream_frame::Frame::Data(grpc::Data { data })))
+            .map(|frame| grpc::AcceptStreamFrame { frame: Some(frame) });
+
+        let mut incoming = self.client.accept(frames).await?.into_inner();
+
+        let peer_id = match incoming.message().await?.and_then(|frame| frame.frame) {
+            Some(accepted_stream_frame::Frame::Peer(peer)) => peer.try_into()?,
+            _ => return Err(Error::UnexpectedStreamFrame),
+        };
+
+        let incoming = incoming
+            .map(|frame| {
+                match frame
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+                    .frame
+                {
+                    Some(accepted_stream_frame::Frame::Data(data)) => Ok(data.data),
+                    _ => Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Unexpected stream frame",
+                    )),
+                }
+            })
+            .boxed();
+
+        Ok((peer_id, AppStream::new(sender, incoming)))
+    }
+
+    /// Opens a TCP connection to a port on the local host of a peer.
+    ///
+    /// The returned stream carries the data of the connection. The peer has to allow port
+    /// forwarding, and may restrict the ports that can be forwarded to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails, if the peer doesn't allow forwarding to the port,
+    /// or if nothing listens on the port on the peer.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use futures::StreamExt as _;
+    /// use hyveos_sdk::Connection;
+    /// use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let connection = Connection::new().await.unwrap();
+    /// let mut discovery_service = connection.discovery();
+    /// let peer_id = discovery_service
+    ///     .get_providers("web", "server")
+    ///     .await
+    ///     .unwrap()
+    ///     .next()
+    ///     .await
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// let mut stream_service = connection.stream();
+    /// let mut stream = stream_service.forward_port(peer_id, 80).await.unwrap();
+    ///
+    /// stream.write_all(b"GET / HTTP/1.0\r\n\r\n").await.unwrap();
+    ///
+    /// let mut response = String::new();
+    /// stream.read_to_string(&mut response).await.unwrap();
+    /// println!("Received: {response}");
+    /// # }
+    /// ```
+    #[tracing::instrument(skip(self))]
+    pub async fn forward_port(&mut self, peer_id: PeerId, port: u16) -> Result<AppStream> {
+        let request = grpc::ForwardRequest {
+            peer: peer_id.into(),
+            port: port.into(),
+        };
+
+        let (sender, receiver) = mpsc::channel(WRITE_BUFFER);
+        let frames = stream::once(future::ready(forward_stream_frame::Frame::Forward(request)))
+            .chain(receiver.map(|data| forward_stream_frame::Frame::Data(grpc::Data { data })))
+            .map(|frame| grpc::ForwardStreamFrame { frame: Some(frame) });
+
+        let incoming = self
+            .client
+            .forward(frames)
+            .await?
+            .into_inner()
+            .map_ok(|data| data.data)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            .boxed();
+
+        Ok(AppStream::new(sender, incoming))
+    }
+}
+
+/// A byte stream to an app on another peer.
+///
+/// Shutting down the stream for writing lets the other app read to the end, while reading is
+/// still possible. Dropping the stream closes it entirely.
+pub struct AppStream {
+    sender: mpsc::Sender<Vec<u8>>,
+    incoming: BoxStream<'static, io::Result<Vec<u8>>>,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl AppStream {
+    fn new(
+        sender: mpsc::Sender<Vec<u8>>,
+        incoming: BoxStream<'static, io::Result<Vec<u8>>>,
+    ) -> Self {
+        Self {
+            sender,
+            incoming,
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl fmt::Debug for AppStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AppStream")
+            .field("buffered", &(self.buffer.len() - self.position))
+            .finish_non_exhaustive()
+    }
+}
+
+impl AsyncRead for AppStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+
+        while this.position == this.buffer.len() {
+            match ready!(this.incoming.poll_next_unpin(cx)) {
+                Some(Ok(data)) => {
+                    this.buffer = data;
+                    this.position = 0;
+                }
+                Some(Err(e)) => return Poll::Ready(Err(e)),
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+
+        let len = buf.remaining().min(this.buffer.len() - this.position);
+        buf.put_slice(&this.buffer[this.position..this.position + len]);
+        this.position += len;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for AppStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.sender.poll_ready(cx)).map_err(|_| io::ErrorKind::BrokenPipe)?;
+        self.sender
+            .start_send(buf.to_vec())
+            .map_err(|_| io::ErrorKind::BrokenPipe)?;
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // Written data is handed to the runtime right away
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.sender.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}